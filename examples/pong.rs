@@ -1,6 +1,12 @@
 use bevy_ecs::component::Component;
 use wakey_2d_engine::{
-    core::world::{Bounds, Position, Renderable, Size, Velocity},
+    HorizontalAlign, VerticalAlign,
+    core::{
+        collision::{Collider, TriggerKind},
+        lighting::{AmbientLight, Occluder, PointLight},
+        particles::{Curve, ParticleEmitter},
+        world::{Bounds, Position, Renderable, Size, Text, Velocity},
+    },
     prelude::*,
 };
 use winit::keyboard::KeyCode;
@@ -13,6 +19,7 @@ const AI_SPEED_OFFSET: f32 = 0.7;
 const BALL_SIZE: f32 = 10.0;
 const BALL_SPEED: f32 = 400.0; // pixels per second
 const ORIGINAL_HEIGHT: f32 = 600.0;
+const SCORING_ZONE_SIZE: f32 = 1000.0; // sensors just need to be big enough to catch the ball
 
 // Score events
 #[derive(Debug, Clone, Copy)]
@@ -32,6 +39,21 @@ struct AIPaddle;
 #[derive(Component)]
 struct Ball;
 
+// Sensor volumes past each paddle. Whoever's side the ball enters loses the point.
+#[derive(Component, Clone, Copy)]
+enum ScoringZone {
+    Left,
+    Right,
+}
+
+// HUD text entities, updated every frame by hud_system
+#[derive(Component, Clone, Copy)]
+enum HudText {
+    Fps,
+    PlayerScore,
+    AIScore,
+}
+
 struct Pong {
     player_score: u32,
     ai_score: u32,
@@ -42,6 +64,13 @@ impl Game for Pong {
         let screen_dimensions = (engine.renderer().width(), engine.renderer().height());
         let world = engine.world_mut();
 
+        // A floor for the paddles and ball to cast shadows onto
+        world.spawn((
+            Position::new(0.0, 0.0),
+            Size::new(screen_dimensions.0, screen_dimensions.1),
+            Renderable::new([0.35, 0.35, 0.4, 1.0]),
+        ));
+
         // Create player paddle (left side)
         world.spawn((
             PlayerPaddle,
@@ -53,6 +82,7 @@ impl Game for Pong {
                 Position::new(20.0, (screen_dimensions.1 as f32 - PADDLE_HEIGHT) / 2.0),
                 Size::new(PADDLE_WIDTH, PADDLE_HEIGHT),
             ),
+            Occluder::rect(),
         ));
 
         // Create AI paddle (right side)
@@ -72,6 +102,7 @@ impl Game for Pong {
                 ),
                 Size::new(PADDLE_WIDTH, PADDLE_HEIGHT),
             ),
+            Occluder::rect(),
         ));
 
         // Create ball
@@ -91,13 +122,67 @@ impl Game for Pong {
                 ),
                 Size::new(BALL_SIZE, BALL_SIZE),
             ),
+            Collider::new(),
+            // The ball lights up the paddles as it passes them
+            PointLight::new(300.0)
+                .with_color([1.0, 0.9, 0.7, 1.0])
+                .with_intensity(1.5)
+                .with_softness(4.0),
+            Occluder::rect(),
+            // A short trail, and sparks whenever it hits a paddle
+            ParticleEmitter::new(40.0)
+                .with_lifetime(0.2, 0.5)
+                .with_speed(10.0, 120.0)
+                .with_drag(3.0)
+                .with_color(Curve::linear([1.0, 0.9, 0.5, 1.0], [1.0, 0.3, 0.1, 0.0]))
+                .with_size(Curve::linear(6.0, 1.0)),
+        ));
+        world.spawn(AmbientLight::new([0.2, 0.2, 0.25, 1.0]));
+
+        // Create scoring zones just outside the left and right edges of the screen
+        world.spawn((
+            ScoringZone::Left,
+            Position::new(-SCORING_ZONE_SIZE, -SCORING_ZONE_SIZE),
+            Collider::rect(
+                SCORING_ZONE_SIZE,
+                screen_dimensions.1 + SCORING_ZONE_SIZE * 2.0,
+            )
+            .sensor(),
+        ));
+        world.spawn((
+            ScoringZone::Right,
+            Position::new(screen_dimensions.0, -SCORING_ZONE_SIZE),
+            Collider::rect(
+                SCORING_ZONE_SIZE,
+                screen_dimensions.1 + SCORING_ZONE_SIZE * 2.0,
+            )
+            .sensor(),
+        ));
+
+        // HUD
+        world.spawn((
+            HudText::Fps,
+            Position::new(10.0, 10.0),
+            Text::new("").with_size(10.0),
+        ));
+        world.spawn((
+            HudText::PlayerScore,
+            Position::new(10.0, 30.0),
+            Text::new(""),
+        ));
+        // Anchored to the top right corner, so it grows to the left
+        world.spawn((
+            HudText::AIScore,
+            Position::new(screen_dimensions.0 - 10.0, 30.0),
+            Text::new("").with_align(HorizontalAlign::Right, VerticalAlign::Top),
         ));
     }
 
     fn update(&mut self, engine: &mut Engine, delta_time: f32) {
         // Run Pong-specific systems
         player_paddle_system(engine, delta_time);
-        let score_event = ball_physics_system(engine, delta_time);
+        let score_event = scoring_system(engine);
+        ball_physics_system(engine, delta_time);
         ball_paddle_collision_system(engine);
         ai_paddle_system(engine, delta_time);
 
@@ -108,36 +193,20 @@ impl Game for Pong {
             ScoreEvent::NoScore => {}
         }
 
-        // Render UI
-        let fps = engine.time().fps();
-        let screen_width = engine.renderer().width();
-
-        engine.renderer_mut().queue_text(
-            &format!("FPS: {:.1}", fps),
-            (10.0, 10.0),         // position
-            10.0,                 // font size
-            [1.0, 1.0, 1.0, 1.0], // white color
-        );
-
-        // Render scores
-        engine.renderer_mut().queue_text(
-            &format!("Player: {}", self.player_score),
-            (10.0, 30.0),
-            16.0,
-            [1.0, 1.0, 1.0, 1.0],
-        );
-
-        engine.renderer_mut().queue_text(
-            &format!("AI: {}", self.ai_score),
-            (screen_width - 150.0, 30.0),
-            16.0,
-            [1.0, 1.0, 1.0, 1.0],
-        );
+        hud_system(engine, self.player_score, self.ai_score);
     }
 
     fn on_resize(&mut self, engine: &mut Engine, width: f32, height: f32) {
         let world = engine.world_mut();
 
+        // Keep the AI score in the top right corner
+        let mut hud_query = world.query::<(&mut Position, &HudText)>();
+        for (mut pos, hud_text) in hud_query.iter_mut(world) {
+            if let HudText::AIScore = hud_text {
+                pos.x = width - 10.0;
+            }
+        }
+
         let scale_factor = height / ORIGINAL_HEIGHT;
 
         // Scale paddle dimensions
@@ -184,6 +253,19 @@ impl Game for Pong {
             vel.x = vel.x.signum() * BALL_SPEED * scale_factor;
             vel.y = vel.y.signum() * BALL_SPEED * scale_factor;
         }
+
+        // Keep the scoring zones hugging the screen edges
+        let mut zone_query = world.query::<(&mut Position, &mut Collider, &ScoringZone)>();
+        for (mut pos, mut collider, zone) in zone_query.iter_mut(world) {
+            pos.x = match zone {
+                ScoringZone::Left => -SCORING_ZONE_SIZE,
+                ScoringZone::Right => width,
+            };
+            collider.size = Some(Size::new(
+                SCORING_ZONE_SIZE,
+                height + SCORING_ZONE_SIZE * 2.0,
+            ));
+        }
     }
 
     fn on_event(&mut self, engine: &mut Engine, event: &winit::event::WindowEvent) -> bool {
@@ -230,7 +312,7 @@ fn player_paddle_system(engine: &mut Engine, delta_time: f32) {
     }
 }
 
-fn ball_physics_system(engine: &mut Engine, delta_time: f32) -> ScoreEvent {
+fn ball_physics_system(engine: &mut Engine, delta_time: f32) {
     let screen_dimensions = (engine.renderer().width(), engine.renderer().height());
     let world = engine.world_mut();
 
@@ -283,26 +365,60 @@ fn ball_physics_system(engine: &mut Engine, delta_time: f32) -> ScoreEvent {
             bounds.max_y = y + size.height;
         }
     }
+}
 
-    // Reset ball if out of bounds and return scoring event
-    let mut query = world.query::<(&mut Position, &mut Velocity, &Size, &Ball)>();
-    let mut iter = query.iter_mut(world);
-    if let Some((mut pos, mut vel, size, _)) = iter.next() {
-        if pos.x < 0.0 {
-            // Ball went past left side - AI scores
-            pos.x = (screen_dimensions.0 - size.width) / 2.0;
-            pos.y = (screen_dimensions.1 - size.height) / 2.0;
-            return ScoreEvent::AIScored;
-        } else if pos.x > screen_dimensions.0 {
-            // Ball went past right side - Player scores
-            pos.x = (screen_dimensions.0 - size.width) / 2.0;
-            pos.y = (screen_dimensions.1 - size.height) / 2.0;
-            vel.x = -vel.x;
-            vel.y = -vel.y;
-            return ScoreEvent::PlayerScored;
+fn hud_system(engine: &mut Engine, player_score: u32, ai_score: u32) {
+    let fps = engine.time().fps();
+    let world = engine.world_mut();
+
+    let mut query = world.query::<(&mut Text, &HudText)>();
+    for (mut text, hud_text) in query.iter_mut(world) {
+        match hud_text {
+            HudText::Fps => text.set_text(format!("FPS: {fps:.1}")),
+            HudText::PlayerScore => text.set_text(format!("Player: {player_score}")),
+            HudText::AIScore => text.set_text(format!("AI: {ai_score}")),
+        }
+    }
+}
+
+fn scoring_system(engine: &mut Engine) -> ScoreEvent {
+    let screen_dimensions = (engine.renderer().width(), engine.renderer().height());
+
+    // Find out which scoring zone (if any) the ball entered last frame
+    let entered_zone = engine
+        .trigger_events()
+        .filter(|event| event.kind == TriggerKind::Enter)
+        .find_map(|event| {
+            let world = engine.world();
+            let zone = *world.get::<ScoringZone>(event.sensor)?;
+            world.get::<Ball>(event.other)?;
+            Some((zone, event.other))
+        });
+
+    let Some((zone, ball)) = entered_zone else {
+        return ScoreEvent::NoScore;
+    };
+
+    // Reset the ball to the middle of the screen
+    let world = engine.world_mut();
+    let size = *world.get::<Size>(ball).unwrap();
+    if let Some(mut pos) = world.get_mut::<Position>(ball) {
+        pos.x = (screen_dimensions.0 - size.width) / 2.0;
+        pos.y = (screen_dimensions.1 - size.height) / 2.0;
+    }
+
+    match zone {
+        // Ball went past left side - AI scores
+        ScoringZone::Left => ScoreEvent::AIScored,
+        // Ball went past right side - Player scores
+        ScoringZone::Right => {
+            if let Some(mut vel) = world.get_mut::<Velocity>(ball) {
+                vel.x = -vel.x;
+                vel.y = -vel.y;
+            }
+            ScoreEvent::PlayerScored
         }
     }
-    ScoreEvent::NoScore
 }
 
 fn ball_paddle_collision_system(engine: &mut Engine) {
@@ -327,10 +443,12 @@ fn ball_paddle_collision_system(engine: &mut Engine) {
             let mut paddle_query = world.query::<(&Bounds, &PlayerPaddle)>();
             for (paddle_bounds, _) in paddle_query.iter(world) {
                 if ball_bounds.intersects(paddle_bounds) {
-                    let mut vel_query = world.query::<(&mut Velocity, &Ball)>();
-                    for (mut vel, _) in vel_query.iter_mut(world) {
+                    let mut vel_query =
+                        world.query::<(&mut Velocity, &mut ParticleEmitter, &Ball)>();
+                    // Only hit one ball
+                    if let Some((mut vel, mut sparks, _)) = vel_query.iter_mut(world).next() {
                         vel.x = -vel.x;
-                        break; // Only hit one ball
+                        sparks.burst(25);
                     }
                     break;
                 }
@@ -342,10 +460,12 @@ fn ball_paddle_collision_system(engine: &mut Engine) {
             let mut paddle_query = world.query::<(&Bounds, &AIPaddle)>();
             for (paddle_bounds, _) in paddle_query.iter(world) {
                 if ball_bounds.intersects(paddle_bounds) {
-                    let mut vel_query = world.query::<(&mut Velocity, &Ball)>();
-                    for (mut vel, _) in vel_query.iter_mut(world) {
+                    let mut vel_query =
+                        world.query::<(&mut Velocity, &mut ParticleEmitter, &Ball)>();
+                    // Only hit one ball
+                    if let Some((mut vel, mut sparks, _)) = vel_query.iter_mut(world).next() {
                         vel.x = -vel.x;
-                        break; // Only hit one ball
+                        sparks.burst(25);
                     }
                     break;
                 }
@@ -0,0 +1,697 @@
+//! Sprites from Aseprite, either the `.aseprite`/`.ase` file itself or its JSON sprite sheet
+//! export.
+//!
+//! Native files are flattened when they're loaded. Visible layers are blended with their
+//! opacity into one sprite sheet texture, one cell per frame. Blend modes other than normal
+//! are drawn as normal, and tilemap layers are skipped. JSON exports point at their own sheet
+//! image, which is loaded next to the `.json` file. Trimmed frames aren't supported since
+//! regions get stretched over the whole sprite, so export without trimming.
+//!
+//! Tags become `AnimationClip`s, played with the `SpriteAnimation` component, and slices keep
+//! their nine-slice centers and pivots.
+
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+
+use crate::assets::{Assets, Handle, Texture, TextureRegion};
+
+/// A sprite sheet with its frames, animation tags and slices
+pub struct Aseprite {
+    /// Canvas size in pixels, the size of every frame
+    pub width: u32,
+    pub height: u32,
+    pub frames: Vec<AsepriteFrame>,
+    pub clips: Vec<AnimationClip>,
+    pub slices: Vec<AsepriteSlice>,
+    /// The sprite sheet, set once the file is loaded through `Assets`
+    pub texture: Option<Handle<Texture>>,
+    // Flattened frames of a native file, handed to `Assets` along with the sheet
+    sheet: Option<Texture>,
+    // Sheet image of a JSON export, relative to the .json file
+    image: Option<PathBuf>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AsepriteFrame {
+    /// Where the frame is in the sheet
+    pub region: TextureRegion,
+    /// Seconds
+    pub duration: f32,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum AnimationDirection {
+    #[default]
+    Forward,
+    Reverse,
+    /// Forward then back, without repeating the first and last frames
+    PingPong,
+    /// Backwards then forward
+    PingPongReverse,
+}
+
+/// A named range of frames, from an Aseprite tag
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnimationClip {
+    pub name: String,
+    /// First and last frame, inclusive
+    pub from: usize,
+    pub to: usize,
+    pub direction: AnimationDirection,
+    /// Times to play the clip before stopping on its last frame, `None` loops forever. A
+    /// ping-pong counts once for the way there and back.
+    pub repeat: Option<u32>,
+}
+
+impl AnimationClip {
+    /// Frame indices in the order they're shown over one play
+    pub fn frames(&self) -> Vec<usize> {
+        let (from, to) = (self.from.min(self.to), self.from.max(self.to));
+        let forward = from..=to;
+        // The ends aren't shown twice when turning around
+        let inner = from + 1..to;
+        match self.direction {
+            AnimationDirection::Forward => forward.collect(),
+            AnimationDirection::Reverse => forward.rev().collect(),
+            AnimationDirection::PingPong => forward.chain(inner.rev()).collect(),
+            AnimationDirection::PingPongReverse => forward.rev().chain(inner).collect(),
+        }
+    }
+}
+
+/// A named rectangle drawn over the canvas, e.g. a hitbox or the stretchable parts of a panel
+#[derive(Clone, Debug, PartialEq)]
+pub struct AsepriteSlice {
+    pub name: String,
+    /// Sorted by frame, each one applies until the next
+    pub keys: Vec<SliceKey>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SliceKey {
+    /// First frame this key applies to
+    pub frame: usize,
+    /// On the canvas, in pixels
+    pub bounds: TextureRegion,
+    /// Nine-slice center, relative to `bounds`
+    pub center: Option<TextureRegion>,
+    /// Relative to `bounds`
+    pub pivot: Option<(f32, f32)>,
+}
+
+impl AsepriteSlice {
+    /// The key in effect on a frame
+    pub fn key(&self, frame: usize) -> Option<&SliceKey> {
+        self.keys.iter().rev().find(|key| key.frame <= frame)
+    }
+}
+
+impl Aseprite {
+    /// Parse either format, picking it from the file extension
+    pub fn parse(bytes: &[u8], path: &Path) -> Result<Self, String> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            "json" => {
+                let source = std::str::from_utf8(bytes).map_err(|e| e.to_string())?;
+                parse_json(source)
+            }
+            _ => parse_native(bytes),
+        }
+    }
+
+    pub fn clip(&self, name: &str) -> Option<&AnimationClip> {
+        self.clips.iter().find(|clip| clip.name == name)
+    }
+
+    pub fn slice(&self, name: &str) -> Option<&AsepriteSlice> {
+        self.slices.iter().find(|slice| slice.name == name)
+    }
+
+    /// Where a slice is in the sheet on a frame, for drawing just that part of it
+    pub fn slice_region(&self, name: &str, frame: usize) -> Option<TextureRegion> {
+        let key = self.slice(name)?.key(frame)?;
+        let frame = self.frames.get(frame)?.region;
+        Some(TextureRegion::new(
+            frame.x + key.bounds.x,
+            frame.y + key.bounds.y,
+            key.bounds.width,
+            key.bounds.height,
+        ))
+    }
+
+    pub(crate) fn load_textures(&mut self, assets: &mut Assets, path: &Path) {
+        if let Some(sheet) = self.sheet.take() {
+            self.texture = Some(assets.add(sheet));
+        } else if let Some(image) = &self.image {
+            let directory = path.parent().unwrap_or(Path::new(""));
+            self.texture = Some(assets.load_async(directory.join(image)));
+        }
+    }
+}
+
+// JSON export
+
+fn rect(value: &Value) -> TextureRegion {
+    let number = |name| value.get(name).and_then(Value::as_f64).unwrap_or(0.0) as f32;
+    TextureRegion::new(number("x"), number("y"), number("w"), number("h"))
+}
+
+fn parse_json(source: &str) -> Result<Aseprite, String> {
+    let root: Value = serde_json::from_str(source).map_err(|e| e.to_string())?;
+    let meta = root.get("meta").ok_or("not an Aseprite sprite sheet")?;
+
+    // The hash export keys frames by name, in frame order
+    let frame_values: Vec<&Value> = match root.get("frames") {
+        Some(Value::Array(frames)) => frames.iter().collect(),
+        Some(Value::Object(frames)) => frames.values().collect(),
+        _ => return Err("sprite sheet without frames".to_string()),
+    };
+    if frame_values
+        .iter()
+        .any(|frame| frame.get("rotated").and_then(Value::as_bool) == Some(true))
+    {
+        return Err("rotated frames aren't supported".to_string());
+    }
+    let frames: Vec<AsepriteFrame> = frame_values
+        .iter()
+        .map(|frame| AsepriteFrame {
+            region: rect(frame.get("frame").unwrap_or(&Value::Null)),
+            duration: frame
+                .get("duration")
+                .and_then(Value::as_f64)
+                .unwrap_or(100.0) as f32
+                / 1000.0,
+        })
+        .collect();
+    let size = frame_values
+        .first()
+        .and_then(|frame| frame.get("sourceSize"))
+        .unwrap_or(&Value::Null);
+
+    let clips = meta
+        .get("frameTags")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|tag| AnimationClip {
+            name: tag
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            from: tag.get("from").and_then(Value::as_u64).unwrap_or(0) as usize,
+            to: tag.get("to").and_then(Value::as_u64).unwrap_or(0) as usize,
+            direction: match tag.get("direction").and_then(Value::as_str) {
+                Some("reverse") => AnimationDirection::Reverse,
+                Some("pingpong") => AnimationDirection::PingPong,
+                Some("pingpong_reverse") => AnimationDirection::PingPongReverse,
+                _ => AnimationDirection::Forward,
+            },
+            // Written as a string, and left out for tags that loop forever
+            repeat: tag
+                .get("repeat")
+                .and_then(|repeat| match repeat {
+                    Value::String(repeat) => repeat.parse().ok(),
+                    repeat => repeat.as_u64().map(|repeat| repeat as u32),
+                })
+                .filter(|&repeat| repeat > 0),
+        })
+        .collect();
+
+    let slices = meta
+        .get("slices")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|slice| AsepriteSlice {
+            name: slice
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            keys: slice
+                .get("keys")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .map(|key| SliceKey {
+                    frame: key.get("frame").and_then(Value::as_u64).unwrap_or(0) as usize,
+                    bounds: rect(key.get("bounds").unwrap_or(&Value::Null)),
+                    center: key.get("center").map(rect),
+                    pivot: key.get("pivot").map(|pivot| {
+                        let pivot = rect(pivot);
+                        (pivot.x, pivot.y)
+                    }),
+                })
+                .collect(),
+        })
+        .collect();
+
+    Ok(Aseprite {
+        width: size.get("w").and_then(Value::as_u64).unwrap_or(0) as u32,
+        height: size.get("h").and_then(Value::as_u64).unwrap_or(0) as u32,
+        frames,
+        clips,
+        slices,
+        texture: None,
+        sheet: None,
+        image: meta.get("image").and_then(Value::as_str).map(PathBuf::from),
+    })
+}
+
+// Native files, see https://github.com/aseprite/aseprite/blob/main/docs/ase-file-specs.md
+
+const HEADER_MAGIC: u16 = 0xa5e0;
+const FRAME_MAGIC: u16 = 0xf1fa;
+
+const CHUNK_OLD_PALETTE: u16 = 0x0004;
+const CHUNK_LAYER: u16 = 0x2004;
+const CHUNK_CEL: u16 = 0x2005;
+const CHUNK_TAGS: u16 = 0x2018;
+const CHUNK_PALETTE: u16 = 0x2019;
+const CHUNK_SLICE: u16 = 0x2022;
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let end = self.position + count;
+        let bytes = self
+            .bytes
+            .get(self.position..end)
+            .ok_or("unexpected end of file")?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, count: usize) -> Result<(), String> {
+        self.take(count).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn i16(&mut self) -> Result<i16, String> {
+        Ok(self.u16()? as i16)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        Ok(self.u32()? as i32)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let length = self.u16()? as usize;
+        Ok(String::from_utf8_lossy(self.take(length)?).into_owned())
+    }
+}
+
+struct Layer {
+    visible: bool,
+    background: bool,
+    opacity: f32,
+    // Tilemap layers have cels of tile ids instead of pixels
+    tilemap: bool,
+}
+
+#[derive(Clone)]
+struct Cel {
+    layer: usize,
+    x: i32,
+    y: i32,
+    opacity: f32,
+    z_index: i32,
+    width: usize,
+    height: usize,
+    // In the file's color depth
+    pixels: Vec<u8>,
+}
+
+fn parse_native(bytes: &[u8]) -> Result<Aseprite, String> {
+    let mut reader = Reader::new(bytes);
+    reader.skip(4)?;
+    if reader.u16()? != HEADER_MAGIC {
+        return Err("not an Aseprite file".to_string());
+    }
+    let frame_count = reader.u16()? as usize;
+    let width = reader.u16()? as usize;
+    let height = reader.u16()? as usize;
+    let depth = reader.u16()?;
+    if !matches!(depth, 8 | 16 | 32) {
+        return Err(format!("unsupported color depth {depth}"));
+    }
+    let layer_opacity_valid = reader.u32()? & 1 != 0;
+    reader.skip(10)?;
+    let transparent_index = reader.u8()?;
+    reader.skip(128 - 29)?;
+
+    let mut layers: Vec<Layer> = Vec::new();
+    // Visibility of the groups the next layer could be in, by nesting level
+    let mut groups: Vec<bool> = Vec::new();
+    let mut palette = vec![[0u8; 4]; 256];
+    let mut frames: Vec<(f32, Vec<Cel>)> = Vec::new();
+    let mut clips = Vec::new();
+    let mut slices = Vec::new();
+
+    for _ in 0..frame_count {
+        let frame_start = reader.position;
+        let frame_size = reader.u32()? as usize;
+        if reader.u16()? != FRAME_MAGIC {
+            return Err("corrupt frame header".to_string());
+        }
+        let old_chunk_count = reader.u16()? as usize;
+        let duration = reader.u16()? as f32 / 1000.0;
+        reader.skip(2)?;
+        let chunk_count = match reader.u32()? as usize {
+            0 => old_chunk_count,
+            count => count,
+        };
+
+        let mut cels: Vec<Cel> = Vec::new();
+        for _ in 0..chunk_count {
+            let chunk_start = reader.position;
+            let chunk_size = reader.u32()? as usize;
+            let chunk_type = reader.u16()?;
+            let mut chunk = Reader::new(
+                bytes
+                    .get(reader.position..chunk_start + chunk_size)
+                    .ok_or("unexpected end of file")?,
+            );
+
+            match chunk_type {
+                CHUNK_OLD_PALETTE => {
+                    let mut index = 0;
+                    for _ in 0..chunk.u16()? {
+                        index += chunk.u8()? as usize;
+                        let count = match chunk.u8()? {
+                            0 => 256,
+                            count => count as usize,
+                        };
+                        for _ in 0..count {
+                            let rgb = chunk.take(3)?;
+                            if let Some(color) = palette.get_mut(index) {
+                                *color = [rgb[0], rgb[1], rgb[2], 255];
+                            }
+                            index += 1;
+                        }
+                    }
+                }
+                CHUNK_PALETTE => {
+                    let size = chunk.u32()? as usize;
+                    let (first, last) = (chunk.u32()? as usize, chunk.u32()? as usize);
+                    chunk.skip(8)?;
+                    palette.resize(size.max(palette.len()), [0; 4]);
+                    for index in first..=last {
+                        let flags = chunk.u16()?;
+                        let rgba = chunk.take(4)?;
+                        if flags & 1 != 0 {
+                            chunk.string()?;
+                        }
+                        if let Some(color) = palette.get_mut(index) {
+                            *color = [rgba[0], rgba[1], rgba[2], rgba[3]];
+                        }
+                    }
+                }
+                CHUNK_LAYER => {
+                    let flags = chunk.u16()?;
+                    let kind = chunk.u16()?;
+                    let level = chunk.u16()? as usize;
+                    chunk.skip(6)?;
+                    let opacity = chunk.u8()?;
+
+                    groups.truncate(level);
+                    let visible = flags & 1 != 0 && groups.iter().all(|&visible| visible);
+                    if kind == 1 {
+                        groups.push(visible);
+                    }
+                    layers.push(Layer {
+                        visible: visible && kind != 1,
+                        background: flags & 8 != 0,
+                        opacity: if layer_opacity_valid {
+                            opacity as f32 / 255.0
+                        } else {
+                            1.0
+                        },
+                        tilemap: kind == 2,
+                    });
+                }
+                CHUNK_CEL => {
+                    let layer = chunk.u16()? as usize;
+                    let (x, y) = (chunk.i16()? as i32, chunk.i16()? as i32);
+                    let opacity = chunk.u8()? as f32 / 255.0;
+                    let kind = chunk.u16()?;
+                    let z_index = chunk.i16()? as i32;
+                    chunk.skip(5)?;
+
+                    match kind {
+                        // Raw or zlib compressed pixels
+                        0 | 2 => {
+                            let (width, height) = (chunk.u16()? as usize, chunk.u16()? as usize);
+                            let rest = chunk.take(chunk.bytes.len() - chunk.position)?;
+                            let pixels = if kind == 0 {
+                                rest.to_vec()
+                            } else {
+                                let mut pixels = Vec::new();
+                                flate2::read::ZlibDecoder::new(rest)
+                                    .read_to_end(&mut pixels)
+                                    .map_err(|e| format!("corrupt cel: {e}"))?;
+                                pixels
+                            };
+                            if pixels.len() < width * height * (depth as usize / 8) {
+                                return Err("cel is missing pixels".to_string());
+                            }
+                            cels.push(Cel {
+                                layer,
+                                x,
+                                y,
+                                opacity,
+                                z_index,
+                                width,
+                                height,
+                                pixels,
+                            });
+                        }
+                        // Same pixels as the cel on this layer in an earlier frame
+                        1 => {
+                            let linked = chunk.u16()? as usize;
+                            if let Some(cel) = frames
+                                .get(linked)
+                                .and_then(|(_, cels)| cels.iter().find(|cel| cel.layer == layer))
+                            {
+                                cels.push(Cel {
+                                    x,
+                                    y,
+                                    opacity,
+                                    z_index,
+                                    ..cel.clone()
+                                });
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                CHUNK_TAGS => {
+                    let count = chunk.u16()?;
+                    chunk.skip(8)?;
+                    for _ in 0..count {
+                        let (from, to) = (chunk.u16()? as usize, chunk.u16()? as usize);
+                        let direction = match chunk.u8()? {
+                            1 => AnimationDirection::Reverse,
+                            2 => AnimationDirection::PingPong,
+                            3 => AnimationDirection::PingPongReverse,
+                            _ => AnimationDirection::Forward,
+                        };
+                        let repeat = chunk.u16()? as u32;
+                        chunk.skip(10)?;
+                        clips.push(AnimationClip {
+                            name: chunk.string()?,
+                            from,
+                            to,
+                            direction,
+                            repeat: (repeat > 0).then_some(repeat),
+                        });
+                    }
+                }
+                CHUNK_SLICE => {
+                    let count = chunk.u32()?;
+                    let flags = chunk.u32()?;
+                    chunk.skip(4)?;
+                    let name = chunk.string()?;
+                    let mut keys = Vec::new();
+                    for _ in 0..count {
+                        let frame = chunk.u32()? as usize;
+                        let bounds = TextureRegion::new(
+                            chunk.i32()? as f32,
+                            chunk.i32()? as f32,
+                            chunk.u32()? as f32,
+                            chunk.u32()? as f32,
+                        );
+                        let center = if flags & 1 != 0 {
+                            Some(TextureRegion::new(
+                                chunk.i32()? as f32,
+                                chunk.i32()? as f32,
+                                chunk.u32()? as f32,
+                                chunk.u32()? as f32,
+                            ))
+                        } else {
+                            None
+                        };
+                        let pivot = if flags & 2 != 0 {
+                            Some((chunk.i32()? as f32, chunk.i32()? as f32))
+                        } else {
+                            None
+                        };
+                        keys.push(SliceKey {
+                            frame,
+                            bounds,
+                            center,
+                            pivot,
+                        });
+                    }
+                    slices.push(AsepriteSlice { name, keys });
+                }
+                _ => {}
+            }
+            reader.position = chunk_start + chunk_size.max(6);
+        }
+
+        reader.position = frame_start + frame_size;
+        frames.push((duration, cels));
+    }
+
+    // Frames go in a roughly square grid, so long animations don't outgrow the texture limit
+    let columns = (frame_count as f32).sqrt().ceil().max(1.0) as usize;
+    let rows = frame_count.div_ceil(columns).max(1);
+    let sheet_width = columns * width;
+    let mut sheet = vec![0u8; sheet_width * rows * height * 4];
+    let mut canvas = vec![0u8; width * height * 4];
+    let mut sprite_frames = Vec::with_capacity(frame_count);
+
+    for (index, (duration, mut cels)) in frames.into_iter().enumerate() {
+        // A cel's z-index moves it up or down among the layers
+        cels.sort_by_key(|cel| (cel.layer as i32 + cel.z_index, cel.z_index));
+        canvas.fill(0);
+        for cel in &cels {
+            let Some(layer) = layers.get(cel.layer) else {
+                continue;
+            };
+            if !layer.visible || layer.tilemap {
+                continue;
+            }
+            let transparent = (!layer.background).then_some(transparent_index);
+            blend_cel(
+                &mut canvas,
+                (width, height),
+                cel,
+                cel.opacity * layer.opacity,
+                depth,
+                &palette,
+                transparent,
+            );
+        }
+
+        let (column, row) = (index % columns, index / columns);
+        for y in 0..height {
+            let start = ((row * height + y) * sheet_width + column * width) * 4;
+            sheet[start..start + width * 4]
+                .copy_from_slice(&canvas[y * width * 4..(y + 1) * width * 4]);
+        }
+        sprite_frames.push(AsepriteFrame {
+            region: TextureRegion::new(
+                (column * width) as f32,
+                (row * height) as f32,
+                width as f32,
+                height as f32,
+            ),
+            duration,
+        });
+    }
+
+    Ok(Aseprite {
+        width: width as u32,
+        height: height as u32,
+        frames: sprite_frames,
+        clips,
+        slices,
+        texture: None,
+        sheet: Some(Texture::from_rgba(
+            sheet_width as u32,
+            (rows * height) as u32,
+            sheet,
+        )),
+        image: None,
+    })
+}
+
+/// Draw a cel over the canvas with normal blending
+fn blend_cel(
+    canvas: &mut [u8],
+    (width, height): (usize, usize),
+    cel: &Cel,
+    opacity: f32,
+    depth: u16,
+    palette: &[[u8; 4]],
+    transparent_index: Option<u8>,
+) {
+    let bytes_per_pixel = depth as usize / 8;
+    for y in 0..cel.height {
+        let canvas_y = cel.y + y as i32;
+        if canvas_y < 0 || canvas_y >= height as i32 {
+            continue;
+        }
+        for x in 0..cel.width {
+            let canvas_x = cel.x + x as i32;
+            if canvas_x < 0 || canvas_x >= width as i32 {
+                continue;
+            }
+
+            let source = &cel.pixels[(y * cel.width + x) * bytes_per_pixel..];
+            let [r, g, b, a] = match depth {
+                32 => [source[0], source[1], source[2], source[3]],
+                16 => [source[0], source[0], source[0], source[1]],
+                _ if Some(source[0]) == transparent_index => [0; 4],
+                _ => palette.get(source[0] as usize).copied().unwrap_or([0; 4]),
+            };
+
+            let source_alpha = a as f32 / 255.0 * opacity;
+            if source_alpha <= 0.0 {
+                continue;
+            }
+            let target = &mut canvas[(canvas_y as usize * width + canvas_x as usize) * 4..][..4];
+            let target_alpha = target[3] as f32 / 255.0;
+            let alpha = source_alpha + target_alpha * (1.0 - source_alpha);
+            for (channel, value) in [r, g, b].into_iter().enumerate() {
+                let blended = (value as f32 * source_alpha
+                    + target[channel] as f32 * target_alpha * (1.0 - source_alpha))
+                    / alpha;
+                target[channel] = blended.round() as u8;
+            }
+            target[3] = (alpha * 255.0).round() as u8;
+        }
+    }
+}
@@ -0,0 +1,281 @@
+//! Typed, reference counted asset handles
+
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{assets::Asset, error::AssetError};
+
+/// Untyped id of an asset inside its storage
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AssetId(pub(crate) u64);
+
+/// A reference to a loaded asset of type `T`.
+///
+/// Every clone counts as a reference. Once the last handle is dropped the asset can be freed
+/// with `Assets::unload_unused`.
+pub struct Handle<T> {
+    id: AssetId,
+    // Shared with the storage entry, so strong_count - 1 is the number of live handles
+    token: Arc<()>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    pub fn id(&self) -> AssetId {
+        self.id
+    }
+}
+
+// Manual impls so handles don't require T: Clone/PartialEq/etc.
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            token: self.token.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handle")
+            .field("type", &std::any::type_name::<T>())
+            .field("id", &self.id.0)
+            .finish()
+    }
+}
+
+/// Where an asset is in its loading process
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoadState {
+    /// Still being read/decoded, or waiting for its GPU upload
+    Loading,
+    Loaded,
+    /// Loading failed, with the error message
+    Failed(String),
+    /// The handle doesn't point at anything (the asset was unloaded)
+    NotLoaded,
+}
+
+struct Entry<T> {
+    // None while loading or after a failed load
+    asset: Option<T>,
+    error: Option<String>,
+    token: Arc<()>,
+    path: Option<PathBuf>,
+}
+
+/// Every loaded asset of one type, deduplicated by path
+pub struct AssetStorage<T> {
+    entries: HashMap<AssetId, Entry<T>>,
+    by_path: HashMap<PathBuf, AssetId>,
+    next_id: u64,
+}
+
+impl<T> AssetStorage<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            by_path: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    pub(crate) fn insert(&mut self, asset: T, path: Option<PathBuf>) -> Handle<T> {
+        let handle = self.reserve(path);
+        self.complete(handle.id, Ok(asset));
+        handle
+    }
+
+    /// Create an empty entry for an asset that is still loading
+    pub(crate) fn reserve(&mut self, path: Option<PathBuf>) -> Handle<T> {
+        let id = AssetId(self.next_id);
+        self.next_id += 1;
+
+        let token = Arc::new(());
+        if let Some(path) = &path {
+            self.by_path.insert(path.clone(), id);
+        }
+        self.entries.insert(
+            id,
+            Entry {
+                asset: None,
+                error: None,
+                token: token.clone(),
+                path,
+            },
+        );
+
+        Handle {
+            id,
+            token,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Fill in a reserved entry. Does nothing if the entry was unloaded in the meantime.
+    pub(crate) fn complete(&mut self, id: AssetId, result: Result<T, AssetError>) {
+        let Some(entry) = self.entries.get_mut(&id) else {
+            return;
+        };
+
+        match result {
+            Ok(asset) => {
+                entry.asset = Some(asset);
+                entry.error = None;
+            }
+            Err(e) => {
+                log::error!("{e}");
+                entry.error = Some(e.to_string());
+            }
+        }
+    }
+
+    /// New handle to an asset that was already loaded (or is loading) from `path`.
+    /// Failed loads are ignored so they can be retried.
+    pub(crate) fn handle_for_path(&self, path: &Path) -> Option<Handle<T>> {
+        let id = *self.by_path.get(path)?;
+        let entry = self.entries.get(&id)?;
+        if entry.error.is_some() {
+            return None;
+        }
+
+        Some(Handle {
+            id,
+            token: entry.token.clone(),
+            _marker: PhantomData,
+        })
+    }
+
+    /// The asset, or `None` if it's still loading, failed or was unloaded
+    pub fn get(&self, handle: &Handle<T>) -> Option<&T> {
+        self.entries.get(&handle.id)?.asset.as_ref()
+    }
+
+    pub fn get_mut(&mut self, handle: &Handle<T>) -> Option<&mut T> {
+        self.entries.get_mut(&handle.id)?.asset.as_mut()
+    }
+
+    pub(crate) fn get_by_id(&self, id: AssetId) -> Option<&T> {
+        self.entries.get(&id)?.asset.as_ref()
+    }
+
+    /// Number of live handles pointing at the asset
+    pub fn ref_count(&self, handle: &Handle<T>) -> usize {
+        self.entries
+            .get(&handle.id)
+            .map_or(0, |entry| Arc::strong_count(&entry.token) - 1)
+    }
+
+    /// Path the asset was loaded from, if it came from disk
+    pub fn path(&self, id: AssetId) -> Option<&Path> {
+        self.entries.get(&id)?.path.as_deref()
+    }
+
+    /// Ids and paths of every asset that came from disk
+    pub(crate) fn paths(&self) -> impl Iterator<Item = (AssetId, &Path)> {
+        self.by_path.iter().map(|(path, id)| (*id, path.as_path()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Every asset that has finished loading
+    pub fn iter(&self) -> impl Iterator<Item = (AssetId, &T)> {
+        self.entries
+            .iter()
+            .filter_map(|(id, entry)| Some((*id, entry.asset.as_ref()?)))
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = (AssetId, &mut T)> {
+        self.entries
+            .iter_mut()
+            .filter_map(|(id, entry)| Some((*id, entry.asset.as_mut()?)))
+    }
+
+    /// Drop every asset nobody holds a handle to anymore and hand them back
+    pub(crate) fn remove_unused(&mut self) -> Vec<T> {
+        let unused: Vec<AssetId> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| Arc::strong_count(&entry.token) == 1)
+            .map(|(id, _)| *id)
+            .collect();
+
+        unused
+            .into_iter()
+            .filter_map(|id| {
+                let entry = self.entries.remove(&id)?;
+                if let Some(path) = &entry.path {
+                    // Only forget the path if a newer entry didn't take it over
+                    if self.by_path.get(path) == Some(&id) {
+                        self.by_path.remove(path);
+                    }
+                }
+                entry.asset
+            })
+            .collect()
+    }
+}
+
+impl<T: Asset> AssetStorage<T> {
+    /// Swap in a freshly loaded version of an asset, keeping every handle pointing at it.
+    /// A failed reload keeps the old version around.
+    pub(crate) fn reload(&mut self, id: AssetId, result: Result<T, AssetError>) {
+        let Some(entry) = self.entries.get_mut(&id) else {
+            return;
+        };
+
+        match (result, &mut entry.asset) {
+            (Ok(new), Some(asset)) => asset.replace(new),
+            (Ok(new), None) => entry.asset = Some(new),
+            (Err(e), _) => {
+                log::error!("Failed to reload asset: {e}");
+                return;
+            }
+        }
+        entry.error = None;
+    }
+
+    pub fn state(&self, handle: &Handle<T>) -> LoadState {
+        self.state_by_id(handle.id)
+    }
+
+    pub(crate) fn state_by_id(&self, id: AssetId) -> LoadState {
+        let Some(entry) = self.entries.get(&id) else {
+            return LoadState::NotLoaded;
+        };
+
+        match (&entry.asset, &entry.error) {
+            (_, Some(error)) => LoadState::Failed(error.clone()),
+            (Some(asset), None) if asset.is_ready() => LoadState::Loaded,
+            _ => LoadState::Loading,
+        }
+    }
+}
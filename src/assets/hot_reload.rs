@@ -0,0 +1,104 @@
+//! Watches the asset root for changed files (behind the `hot-reload` feature)
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::Duration,
+};
+
+use notify::{Config, Event, EventKind, PollWatcher, RecursiveMode, Watcher};
+
+use crate::error::AssetError;
+
+/// How to find out about changed files
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HotReloadMode {
+    /// The OS file watcher, falling back to polling if it isn't available or the game looks
+    /// like it's running in a container. Pick `Poll` if changes still don't show up, e.g. in a
+    /// VM with a shared folder.
+    #[default]
+    Auto,
+    /// Check file modification times every interval. Slower, but works on mounted volumes in
+    /// containers and VMs where OS change events never arrive.
+    Poll(Duration),
+}
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+pub(crate) struct HotReloader {
+    // Never read, but dropping it stops the watching
+    _watcher: Box<dyn Watcher + Send>,
+    events: mpsc::Receiver<notify::Result<Event>>,
+}
+
+impl HotReloader {
+    pub(crate) fn new(root: &Path, mode: HotReloadMode) -> Result<Self, AssetError> {
+        let (sender, events) = mpsc::channel();
+
+        let mut watcher: Box<dyn Watcher + Send> = match mode {
+            HotReloadMode::Auto if in_container() => {
+                log::info!("Running in a container, polling for asset changes");
+                Box::new(poll_watcher(sender, DEFAULT_POLL_INTERVAL)?)
+            }
+            HotReloadMode::Auto => match notify::recommended_watcher(sender.clone()) {
+                Ok(watcher) => Box::new(watcher),
+                Err(e) => {
+                    log::warn!("File watcher unavailable ({e}), polling for asset changes");
+                    Box::new(poll_watcher(sender, DEFAULT_POLL_INTERVAL)?)
+                }
+            },
+            HotReloadMode::Poll(interval) => Box::new(poll_watcher(sender, interval)?),
+        };
+
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .map_err(|e| AssetError::Watch(format!("{}: {e}", root.display())))?;
+        log::info!("Watching {} for asset changes", root.display());
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    /// Every file that was written since the last call. Editors tend to write a file several
+    /// times when saving, so each path only shows up once.
+    pub(crate) fn changed_paths(&self) -> HashSet<PathBuf> {
+        let mut changed = HashSet::new();
+        for event in self.events.try_iter() {
+            match event {
+                Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                    // Normalized so they can be compared with the asset paths
+                    changed.extend(
+                        event
+                            .paths
+                            .iter()
+                            .filter_map(|path| std::fs::canonicalize(path).ok()),
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("Asset watcher error: {e}"),
+            }
+        }
+        changed
+    }
+}
+
+fn poll_watcher(
+    sender: mpsc::Sender<notify::Result<Event>>,
+    interval: Duration,
+) -> Result<PollWatcher, AssetError> {
+    PollWatcher::new(sender, Config::default().with_poll_interval(interval))
+        .map_err(|e| AssetError::Watch(e.to_string()))
+}
+
+// Bind mounted volumes usually don't pass OS change events through to the container
+fn in_container() -> bool {
+    Path::new("/.dockerenv").exists()
+        || std::fs::read_to_string("/proc/1/cgroup").is_ok_and(|cgroup| {
+            ["docker", "kubepods", "containerd", "lxc"]
+                .iter()
+                .any(|runtime| cgroup.contains(runtime))
+        })
+}
@@ -0,0 +1,102 @@
+//! Worker thread pool for loading assets in the background
+
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex, mpsc},
+    thread::JoinHandle,
+};
+
+use crate::assets::Assets;
+
+type Job = (
+    Box<dyn FnOnce() -> Completion + Send>,
+    Box<dyn FnOnce(String) -> Completion + Send>,
+);
+
+/// Runs on the main thread once a background load is done, to put the result in place
+pub(crate) type Completion = Box<dyn FnOnce(&mut Assets) + Send>;
+
+pub(crate) struct LoaderPool {
+    // Dropped before joining so the workers see the channel close and exit
+    jobs: Option<mpsc::Sender<Job>>,
+    completed: mpsc::Receiver<Completion>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl LoaderPool {
+    pub(crate) fn new(threads: usize) -> Self {
+        let (job_sender, job_receiver) = mpsc::channel::<Job>();
+        let (completed_sender, completed) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let workers = (0..threads.max(1))
+            .map(|index| {
+                let job_receiver = job_receiver.clone();
+                let completed_sender = completed_sender.clone();
+
+                std::thread::Builder::new()
+                    .name(format!("asset-loader-{index}"))
+                    .spawn(move || {
+                        loop {
+                            // The lock is only held while waiting for the next job
+                            let job = job_receiver.lock().unwrap().recv();
+                            let Ok((job, on_panic)) = job else {
+                                break;
+                            };
+                            // A panicking decoder shouldn't take the worker down with it, or
+                            // leave its handle loading forever
+                            let completion = panic::catch_unwind(AssertUnwindSafe(job))
+                                .unwrap_or_else(|payload| on_panic(panic_message(&*payload)));
+                            if completed_sender.send(completion).is_err() {
+                                break;
+                            }
+                        }
+                    })
+                    .expect("Failed to spawn asset loader thread")
+            })
+            .collect();
+
+        Self {
+            jobs: Some(job_sender),
+            completed,
+            workers,
+        }
+    }
+
+    /// Run `job` on a worker. The completion it returns is handed back by `completed`. If the
+    /// job panics, the completion from `on_panic` is handed back instead.
+    pub(crate) fn spawn(
+        &self,
+        job: impl FnOnce() -> Completion + Send + 'static,
+        on_panic: impl FnOnce(String) -> Completion + Send + 'static,
+    ) {
+        if let Some(jobs) = &self.jobs {
+            let _ = jobs.send((Box::new(job), Box::new(on_panic)));
+        }
+    }
+
+    /// Completions of every job that finished since the last call
+    pub(crate) fn completed(&self) -> Vec<Completion> {
+        self.completed.try_iter().collect()
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+impl Drop for LoaderPool {
+    fn drop(&mut self) {
+        self.jobs.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
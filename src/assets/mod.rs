@@ -0,0 +1,677 @@
+//! Asset loading and caching.
+//!
+//! `Assets` loads textures, fonts, bitmap fonts, sounds, shaders, data files, Aseprite sprites,
+//! Tiled maps and LDtk projects by path and hands out typed `Handle`s. Loading the same path
+//! twice returns the same asset, and assets nobody holds a handle to anymore can be freed with
+//! `Assets::unload_unused`.
+//!
+//! `Assets::load_async` does the reading and decoding on a pool of worker threads instead, so a
+//! pile of textures doesn't stall `Game::init`. Textures are then uploaded to the GPU on the
+//! main thread, a few per frame (see `Assets::set_upload_budget`).
+//!
+//! With the `hot-reload` feature, `Assets::watch` reloads files in place when they change on
+//! disk, so existing handles see the new version on the next frame.
+
+pub mod aseprite;
+pub mod handle;
+#[cfg(feature = "hot-reload")]
+pub mod hot_reload;
+pub(crate) mod loader;
+pub mod types;
+
+use std::path::{Path, PathBuf};
+
+use wgpu_renderer::{Renderer, ShaderCode, TextureFilter, TextureId};
+
+pub use aseprite::{
+    AnimationClip, AnimationDirection, Aseprite, AsepriteFrame, AsepriteSlice, SliceKey,
+};
+pub use handle::{AssetId, AssetStorage, Handle, LoadState};
+#[cfg(feature = "hot-reload")]
+pub use hot_reload::HotReloadMode;
+pub use types::{DataFile, Font, Shader, Texture, TextureRegion};
+
+use crate::{
+    assets::{loader::LoaderPool, types::decode_error},
+    audio::Sound,
+    error::AssetError,
+    level::{LdtkProject, TiledMap},
+    text::BitmapFont,
+};
+
+/// Default amount of texture data uploaded to the GPU per frame
+const DEFAULT_UPLOAD_BUDGET: usize = 4 * 1024 * 1024;
+
+/// Something `Assets` can load from a file
+pub trait Asset: Sized + Send + Sync + 'static {
+    /// Decode the asset from the raw file contents. `path` is the full path of the file, for
+    /// error messages, picking a format and finding other files it refers to.
+    fn from_bytes(bytes: Vec<u8>, path: &Path) -> Result<Self, AssetError>;
+
+    /// Other files `from_bytes` read, relative to the asset's own file. Changing one of them
+    /// hot reloads the asset too.
+    #[doc(hidden)]
+    fn source_files(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
+
+    /// Whether the asset is usable yet. Textures aren't until they've been uploaded to the GPU.
+    fn is_ready(&self) -> bool {
+        true
+    }
+
+    /// Load other assets this one refers to, like a bitmap font's page images. `path` is the
+    /// asset's own path relative to the asset root.
+    #[doc(hidden)]
+    fn load_dependencies(&mut self, _assets: &mut Assets, _path: &Path) {}
+
+    /// Take over the contents of a reloaded version of this asset
+    #[doc(hidden)]
+    fn replace(&mut self, new: Self) {
+        *self = new;
+    }
+
+    #[doc(hidden)]
+    fn storage(assets: &Assets) -> &AssetStorage<Self>;
+
+    #[doc(hidden)]
+    fn storage_mut(assets: &mut Assets) -> &mut AssetStorage<Self>;
+}
+
+/// The engine's asset cache, available through `engine.assets()`
+pub struct Assets {
+    root: PathBuf,
+    textures: AssetStorage<Texture>,
+    fonts: AssetStorage<Font>,
+    bitmap_fonts: AssetStorage<BitmapFont>,
+    sounds: AssetStorage<Sound>,
+    shaders: AssetStorage<Shader>,
+    data: AssetStorage<DataFile>,
+    tiled_maps: AssetStorage<TiledMap>,
+    ldtk_projects: AssetStorage<LdtkProject>,
+    aseprites: AssetStorage<Aseprite>,
+    // GPU textures of unloaded assets, freed on the next update
+    pending_texture_frees: Vec<TextureId>,
+    // Started the first time something is loaded in the background
+    loader: Option<LoaderPool>,
+    upload_budget: usize,
+    #[cfg(feature = "hot-reload")]
+    hot_reloader: Option<hot_reload::HotReloader>,
+}
+
+// Looks up the state of one asset type by id
+type StateOf = fn(&Assets, AssetId) -> LoadState;
+
+/// A set of assets to track together, e.g. everything a loading screen waits for
+#[derive(Default)]
+pub struct LoadGroup {
+    // Each asset type lives in its own storage, so keep a way to look the state up with the id
+    entries: Vec<(AssetId, StateOf)>,
+}
+
+impl LoadGroup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add<T: Asset>(&mut self, handle: &Handle<T>) {
+        self.entries.push((handle.id(), state_of::<T>));
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+fn state_of<T: Asset>(assets: &Assets, id: AssetId) -> LoadState {
+    T::storage(assets).state_by_id(id)
+}
+
+/// How far along a `LoadGroup` is
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LoadProgress {
+    pub loaded: usize,
+    pub failed: usize,
+    pub total: usize,
+}
+
+impl LoadProgress {
+    /// 0.0 to 1.0, failed assets count as done
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 1.0;
+        }
+        (self.loaded + self.failed) as f32 / self.total as f32
+    }
+
+    /// Every asset either loaded or failed
+    pub fn is_done(&self) -> bool {
+        self.loaded + self.failed >= self.total
+    }
+}
+
+impl Assets {
+    /// Paths passed to `load` are resolved relative to `root`
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            textures: AssetStorage::new(),
+            fonts: AssetStorage::new(),
+            bitmap_fonts: AssetStorage::new(),
+            sounds: AssetStorage::new(),
+            shaders: AssetStorage::new(),
+            data: AssetStorage::new(),
+            tiled_maps: AssetStorage::new(),
+            ldtk_projects: AssetStorage::new(),
+            aseprites: AssetStorage::new(),
+            pending_texture_frees: Vec::new(),
+            loader: None,
+            upload_budget: DEFAULT_UPLOAD_BUDGET,
+            #[cfg(feature = "hot-reload")]
+            hot_reloader: None,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Load an asset from disk, or get another handle to it if it's already loaded
+    pub fn load<T: Asset>(&mut self, path: impl AsRef<Path>) -> Result<Handle<T>, AssetError> {
+        let path = path.as_ref();
+        if let Some(handle) = T::storage(self).handle_for_path(path) {
+            return Ok(handle);
+        }
+
+        let full_path = self.root.join(path);
+        let bytes = std::fs::read(&full_path).map_err(|source| AssetError::Io {
+            path: full_path.clone(),
+            source,
+        })?;
+        let mut asset = T::from_bytes(bytes, &full_path)?;
+        asset.load_dependencies(self, path);
+
+        Ok(T::storage_mut(self).insert(asset, Some(path.to_path_buf())))
+    }
+
+    /// Start loading an asset on a worker thread and return its handle right away.
+    /// Check on it with `state`, or add it to a `LoadGroup`.
+    pub fn load_async<T: Asset>(&mut self, path: impl AsRef<Path>) -> Handle<T> {
+        let path = path.as_ref();
+        if let Some(handle) = T::storage(self).handle_for_path(path) {
+            return handle;
+        }
+
+        let handle = T::storage_mut(self).reserve(Some(path.to_path_buf()));
+        let id = handle.id();
+        let path = path.to_path_buf();
+        let full_path = self.root.join(&path);
+        let panic_path = full_path.clone();
+
+        self.loader
+            .get_or_insert_with(|| {
+                let threads =
+                    std::thread::available_parallelism().map_or(2, |threads| threads.get().min(4));
+                LoaderPool::new(threads)
+            })
+            .spawn(
+                move || {
+                    let result = std::fs::read(&full_path)
+                        .map_err(|source| AssetError::Io {
+                            path: full_path.clone(),
+                            source,
+                        })
+                        .and_then(|bytes| T::from_bytes(bytes, &full_path));
+
+                    Box::new(move |assets: &mut Assets| {
+                        let result = result.map(|mut asset| {
+                            asset.load_dependencies(assets, &path);
+                            asset
+                        });
+                        T::storage_mut(assets).complete(id, result);
+                    })
+                },
+                move |message| {
+                    Box::new(move |assets: &mut Assets| {
+                        let error =
+                            decode_error(&panic_path, format!("decoder panicked: {message}"));
+                        T::storage_mut(assets).complete(id, Err(error));
+                    })
+                },
+            );
+
+        handle
+    }
+
+    /// Load every path in the background, adding them all to `group`
+    pub fn load_group<T: Asset>(
+        &mut self,
+        group: &mut LoadGroup,
+        paths: impl IntoIterator<Item = impl AsRef<Path>>,
+    ) -> Vec<Handle<T>> {
+        paths
+            .into_iter()
+            .map(|path| {
+                let handle = self.load_async::<T>(path);
+                group.add(&handle);
+                handle
+            })
+            .collect()
+    }
+
+    pub fn state<T: Asset>(&self, handle: &Handle<T>) -> LoadState {
+        T::storage(self).state(handle)
+    }
+
+    pub fn progress(&self, group: &LoadGroup) -> LoadProgress {
+        let mut progress = LoadProgress {
+            total: group.len(),
+            ..LoadProgress::default()
+        };
+
+        for (id, state_of) in &group.entries {
+            match state_of(self, *id) {
+                LoadState::Loaded => progress.loaded += 1,
+                // Unloaded assets will never finish, so don't hold the loading screen up for them
+                LoadState::Failed(_) | LoadState::NotLoaded => progress.failed += 1,
+                LoadState::Loading => {}
+            }
+        }
+        progress
+    }
+
+    /// Maximum bytes of texture data uploaded to the GPU per frame. At least one texture is
+    /// always uploaded so big ones can't get stuck.
+    pub fn set_upload_budget(&mut self, bytes_per_frame: usize) {
+        self.upload_budget = bytes_per_frame;
+    }
+
+    /// Start watching the asset root, reloading assets whose files change
+    #[cfg(feature = "hot-reload")]
+    pub fn watch(&mut self, mode: HotReloadMode) -> Result<(), AssetError> {
+        self.hot_reloader = Some(hot_reload::HotReloader::new(&self.root, mode)?);
+        Ok(())
+    }
+
+    #[cfg(feature = "hot-reload")]
+    pub fn is_watching(&self) -> bool {
+        self.hot_reloader.is_some()
+    }
+
+    #[cfg(feature = "hot-reload")]
+    fn reload_changed(&mut self) {
+        let Some(hot_reloader) = &self.hot_reloader else {
+            return;
+        };
+        let changed = hot_reloader.changed_paths();
+        if changed.is_empty() {
+            return;
+        }
+
+        self.reload_changed_of::<Texture>(&changed);
+        self.reload_changed_of::<Font>(&changed);
+        self.reload_changed_of::<BitmapFont>(&changed);
+        self.reload_changed_of::<Sound>(&changed);
+        self.reload_changed_of::<Shader>(&changed);
+        self.reload_changed_of::<DataFile>(&changed);
+        self.reload_changed_of::<TiledMap>(&changed);
+        self.reload_changed_of::<LdtkProject>(&changed);
+        self.reload_changed_of::<Aseprite>(&changed);
+    }
+
+    #[cfg(feature = "hot-reload")]
+    fn reload_changed_of<T: Asset>(&mut self, changed: &std::collections::HashSet<PathBuf>) {
+        let storage = T::storage(self);
+        let reloads: Vec<(AssetId, PathBuf, PathBuf)> = storage
+            .paths()
+            .filter_map(|(id, path)| {
+                let full_path = std::fs::canonicalize(self.root.join(path)).ok()?;
+                let directory = full_path.parent().unwrap_or(Path::new(""));
+                let source_changed = || {
+                    storage.get_by_id(id).is_some_and(|asset| {
+                        asset.source_files().iter().any(|file| {
+                            std::fs::canonicalize(directory.join(file))
+                                .is_ok_and(|file| changed.contains(&file))
+                        })
+                    })
+                };
+                (changed.contains(&full_path) || source_changed())
+                    .then(|| (id, path.to_path_buf(), full_path))
+            })
+            .collect();
+
+        for (id, path, full_path) in reloads {
+            log::info!("Reloading {}", full_path.display());
+            let result = std::fs::read(&full_path)
+                .map_err(|source| AssetError::Io {
+                    path: full_path.clone(),
+                    source,
+                })
+                .and_then(|bytes| T::from_bytes(bytes, &full_path))
+                .map(|mut asset| {
+                    asset.load_dependencies(self, &path);
+                    asset
+                });
+            T::storage_mut(self).reload(id, result);
+        }
+    }
+
+    /// Add an asset that didn't come from a file
+    pub fn add<T: Asset>(&mut self, asset: T) -> Handle<T> {
+        T::storage_mut(self).insert(asset, None)
+    }
+
+    pub fn get<T: Asset>(&self, handle: &Handle<T>) -> Option<&T> {
+        T::storage(self).get(handle)
+    }
+
+    pub fn get_mut<T: Asset>(&mut self, handle: &Handle<T>) -> Option<&mut T> {
+        T::storage_mut(self).get_mut(handle)
+    }
+
+    /// Number of live handles to the asset
+    pub fn ref_count<T: Asset>(&self, handle: &Handle<T>) -> usize {
+        T::storage(self).ref_count(handle)
+    }
+
+    pub fn storage<T: Asset>(&self) -> &AssetStorage<T> {
+        T::storage(self)
+    }
+
+    /// Free every asset that no handle points at anymore. Returns how many were freed.
+    ///
+    /// This isn't done automatically so assets can be preloaded without holding on to handles.
+    pub fn unload_unused(&mut self) -> usize {
+        let textures = self.textures.remove_unused();
+        self.pending_texture_frees
+            .extend(textures.iter().filter_map(Texture::gpu_id));
+
+        textures.len()
+            + self.fonts.remove_unused().len()
+            + self.bitmap_fonts.remove_unused().len()
+            + self.sounds.remove_unused().len()
+            + self.shaders.remove_unused().len()
+            + self.data.remove_unused().len()
+            + self.tiled_maps.remove_unused().len()
+            + self.ldtk_projects.remove_unused().len()
+            + self.aseprites.remove_unused().len()
+    }
+
+    /// Finish background loads and sync textures and fonts with the renderer.
+    /// Called by the engine every frame before drawing.
+    pub(crate) fn update(&mut self, renderer: &mut Renderer) {
+        if let Some(loader) = &self.loader {
+            for completion in loader.completed() {
+                completion(self);
+            }
+        }
+
+        #[cfg(feature = "hot-reload")]
+        self.reload_changed();
+
+        for id in self.pending_texture_frees.drain(..) {
+            renderer.remove_texture(id);
+        }
+
+        // Fonts can't be removed from the renderer, so a reloaded font gets a new id and the
+        // old one just sits there unused
+        for (_, font) in self.fonts.iter_mut() {
+            if font.id.is_none() {
+                font.id = Some(renderer.add_font(font.font().clone()));
+            }
+        }
+
+        // Bitmap fonts and Aseprite sheets are pixel art, keep them crisp
+        for (_, font) in self.bitmap_fonts.iter() {
+            for page in &font.pages {
+                if let Some(texture) = self.textures.get_mut(page) {
+                    texture.set_filter(TextureFilter::Nearest);
+                }
+            }
+        }
+        for (_, sprite) in self.aseprites.iter() {
+            if let Some(texture) = sprite
+                .texture
+                .as_ref()
+                .and_then(|sheet| self.textures.get_mut(sheet))
+            {
+                texture.set_filter(TextureFilter::Nearest);
+            }
+        }
+
+        let mut uploaded = 0;
+        for (_, texture) in self.textures.iter_mut() {
+            if texture.filter_changed
+                && let Some(id) = texture.gpu
+            {
+                renderer.set_texture_filter(id, texture.filter());
+                texture.filter_changed = false;
+            }
+
+            if texture.pixels.is_none() || (uploaded > 0 && uploaded >= self.upload_budget) {
+                continue;
+            }
+
+            let pixels = texture.pixels.take().unwrap_or_default();
+            match texture.gpu {
+                // Reloaded in place, keep the same GPU id so draws pick it up
+                Some(id) => renderer.update_texture(
+                    id,
+                    texture.width(),
+                    texture.height(),
+                    &pixels,
+                    texture.filter(),
+                ),
+                None => {
+                    texture.gpu = Some(renderer.create_texture(
+                        texture.width(),
+                        texture.height(),
+                        &pixels,
+                        texture.filter(),
+                    ))
+                }
+            }
+            texture.filter_changed = false;
+            uploaded += pixels.len();
+        }
+    }
+}
+
+impl Asset for Texture {
+    fn from_bytes(bytes: Vec<u8>, path: &Path) -> Result<Self, AssetError> {
+        Texture::from_image_bytes(&bytes).map_err(|e| decode_error(path, e))
+    }
+
+    fn is_ready(&self) -> bool {
+        self.gpu.is_some() && self.pixels.is_none()
+    }
+
+    // Keep the GPU texture so sprites drawing it don't need a new id, the new pixels get
+    // written into it on the next update. The filter was set in code, so it stays too.
+    fn replace(&mut self, new: Self) {
+        let (gpu, filter) = (self.gpu, self.filter());
+        *self = new;
+        self.gpu = gpu;
+        self.set_filter(filter);
+    }
+
+    fn storage(assets: &Assets) -> &AssetStorage<Self> {
+        &assets.textures
+    }
+
+    fn storage_mut(assets: &mut Assets) -> &mut AssetStorage<Self> {
+        &mut assets.textures
+    }
+}
+
+impl Asset for Font {
+    fn from_bytes(bytes: Vec<u8>, path: &Path) -> Result<Self, AssetError> {
+        Font::from_bytes(bytes).map_err(|e| decode_error(path, e))
+    }
+
+    fn is_ready(&self) -> bool {
+        self.id.is_some()
+    }
+
+    fn storage(assets: &Assets) -> &AssetStorage<Self> {
+        &assets.fonts
+    }
+
+    fn storage_mut(assets: &mut Assets) -> &mut AssetStorage<Self> {
+        &mut assets.fonts
+    }
+}
+
+impl Asset for BitmapFont {
+    fn from_bytes(bytes: Vec<u8>, path: &Path) -> Result<Self, AssetError> {
+        let source = String::from_utf8(bytes).map_err(|e| decode_error(path, e))?;
+        BitmapFont::parse(&source).map_err(|e| decode_error(path, e))
+    }
+
+    // Page files are relative to the .fnt file
+    fn load_dependencies(&mut self, assets: &mut Assets, path: &Path) {
+        let directory = path.parent().unwrap_or(Path::new(""));
+        self.pages = self
+            .page_files()
+            .iter()
+            .map(|file| assets.load_async(directory.join(file)))
+            .collect();
+    }
+
+    fn storage(assets: &Assets) -> &AssetStorage<Self> {
+        &assets.bitmap_fonts
+    }
+
+    fn storage_mut(assets: &mut Assets) -> &mut AssetStorage<Self> {
+        &mut assets.bitmap_fonts
+    }
+}
+
+impl Asset for Sound {
+    fn from_bytes(bytes: Vec<u8>, path: &Path) -> Result<Self, AssetError> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        Sound::from_bytes(&bytes, &extension).map_err(|e| decode_error(path, e))
+    }
+
+    fn storage(assets: &Assets) -> &AssetStorage<Self> {
+        &assets.sounds
+    }
+
+    fn storage_mut(assets: &mut Assets) -> &mut AssetStorage<Self> {
+        &mut assets.sounds
+    }
+}
+
+impl Asset for Shader {
+    fn from_bytes(bytes: Vec<u8>, path: &Path) -> Result<Self, AssetError> {
+        let source = String::from_utf8(bytes).map_err(|e| decode_error(path, e))?;
+        let code = ShaderCode::from_source(source, path).map_err(|e| decode_error(path, e))?;
+        Ok(Shader::new(code))
+    }
+
+    // Materials watching this shader see the new generation and recompile it
+    fn replace(&mut self, new: Self) {
+        self.generation = self.generation.wrapping_add(1);
+        self.code = new.code;
+    }
+
+    fn storage(assets: &Assets) -> &AssetStorage<Self> {
+        &assets.shaders
+    }
+
+    fn storage_mut(assets: &mut Assets) -> &mut AssetStorage<Self> {
+        &mut assets.shaders
+    }
+}
+
+impl Asset for DataFile {
+    fn from_bytes(bytes: Vec<u8>, _path: &Path) -> Result<Self, AssetError> {
+        Ok(DataFile::new(bytes))
+    }
+
+    fn storage(assets: &Assets) -> &AssetStorage<Self> {
+        &assets.data
+    }
+
+    fn storage_mut(assets: &mut Assets) -> &mut AssetStorage<Self> {
+        &mut assets.data
+    }
+}
+
+impl Asset for TiledMap {
+    fn from_bytes(bytes: Vec<u8>, path: &Path) -> Result<Self, AssetError> {
+        let source = String::from_utf8(bytes).map_err(|e| decode_error(path, e))?;
+        TiledMap::parse(&source, path).map_err(|e| decode_error(path, e))
+    }
+
+    fn source_files(&self) -> Vec<PathBuf> {
+        self.tilesets
+            .iter()
+            .filter_map(|tileset| tileset.source.clone())
+            .collect()
+    }
+
+    fn load_dependencies(&mut self, assets: &mut Assets, path: &Path) {
+        self.load_textures(assets, path);
+    }
+
+    fn storage(assets: &Assets) -> &AssetStorage<Self> {
+        &assets.tiled_maps
+    }
+
+    fn storage_mut(assets: &mut Assets) -> &mut AssetStorage<Self> {
+        &mut assets.tiled_maps
+    }
+}
+
+impl Asset for LdtkProject {
+    fn from_bytes(bytes: Vec<u8>, path: &Path) -> Result<Self, AssetError> {
+        let source = String::from_utf8(bytes).map_err(|e| decode_error(path, e))?;
+        LdtkProject::parse(&source, path).map_err(|e| decode_error(path, e))
+    }
+
+    fn source_files(&self) -> Vec<PathBuf> {
+        self.levels()
+            .filter_map(|level| level.file.clone())
+            .collect()
+    }
+
+    fn load_dependencies(&mut self, assets: &mut Assets, path: &Path) {
+        self.load_textures(assets, path);
+    }
+
+    fn storage(assets: &Assets) -> &AssetStorage<Self> {
+        &assets.ldtk_projects
+    }
+
+    fn storage_mut(assets: &mut Assets) -> &mut AssetStorage<Self> {
+        &mut assets.ldtk_projects
+    }
+}
+
+impl Asset for Aseprite {
+    fn from_bytes(bytes: Vec<u8>, path: &Path) -> Result<Self, AssetError> {
+        Aseprite::parse(&bytes, path).map_err(|e| decode_error(path, e))
+    }
+
+    fn load_dependencies(&mut self, assets: &mut Assets, path: &Path) {
+        self.load_textures(assets, path);
+    }
+
+    fn storage(assets: &Assets) -> &AssetStorage<Self> {
+        &assets.aseprites
+    }
+
+    fn storage_mut(assets: &mut Assets) -> &mut AssetStorage<Self> {
+        &mut assets.aseprites
+    }
+}
@@ -0,0 +1,181 @@
+//! The asset types the engine knows how to load
+
+use std::path::Path;
+
+use wgpu_renderer::{FontArc, FontId, MaterialDescriptor, ShaderCode, TextureFilter, TextureId};
+
+use crate::error::AssetError;
+
+/// An image loaded into a GPU texture.
+///
+/// Decoding happens when the asset is loaded, the GPU upload happens on the main thread right
+/// before the next frame is drawn.
+pub struct Texture {
+    width: u32,
+    height: u32,
+    filter: TextureFilter,
+    // Decoded RGBA8 pixels waiting to be uploaded
+    pub(crate) pixels: Option<Vec<u8>>,
+    pub(crate) gpu: Option<TextureId>,
+    pub(crate) filter_changed: bool,
+}
+
+impl Texture {
+    /// Build a texture from tightly packed RGBA8 pixels
+    pub fn from_rgba(width: u32, height: u32, rgba: Vec<u8>) -> Self {
+        Self {
+            width,
+            height,
+            filter: TextureFilter::default(),
+            pixels: Some(rgba),
+            gpu: None,
+            filter_changed: false,
+        }
+    }
+
+    /// Decode a PNG/JPEG/etc. image
+    pub fn from_image_bytes(bytes: &[u8]) -> Result<Self, image::ImageError> {
+        let image = image::load_from_memory(bytes)?.to_rgba8();
+        let (width, height) = image.dimensions();
+        Ok(Self::from_rgba(width, height, image.into_raw()))
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn filter(&self) -> TextureFilter {
+        self.filter
+    }
+
+    /// Use `TextureFilter::Nearest` for crisp pixel art
+    pub fn set_filter(&mut self, filter: TextureFilter) {
+        if self.filter != filter {
+            self.filter = filter;
+            self.filter_changed = true;
+        }
+    }
+
+    /// The renderer's id for this texture, `None` until it has been uploaded
+    pub fn gpu_id(&self) -> Option<TextureId> {
+        self.gpu
+    }
+
+    /// UV coordinates for a pixel region of this texture, or the whole texture for `None`
+    pub fn uv(&self, region: Option<&TextureRegion>) -> [f32; 4] {
+        match region {
+            Some(region) => [
+                region.x / self.width as f32,
+                region.y / self.height as f32,
+                (region.x + region.width) / self.width as f32,
+                (region.y + region.height) / self.height as f32,
+            ],
+            None => wgpu_renderer::FULL_UV,
+        }
+    }
+}
+
+/// A rectangle inside a texture, in pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureRegion {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl TextureRegion {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// A TrueType/OpenType font. Like textures, it's handed to the renderer right before the
+/// next frame is drawn.
+pub struct Font {
+    font: FontArc,
+    pub(crate) id: Option<FontId>,
+}
+
+impl Font {
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, String> {
+        let font = FontArc::try_from_vec(bytes).map_err(|e| e.to_string())?;
+        Ok(Self { font, id: None })
+    }
+
+    pub fn font(&self) -> &FontArc {
+        &self.font
+    }
+
+    /// The renderer's id for this font, for use in `TextSpan::with_font`.
+    /// `None` until the font has been added to the renderer.
+    ///
+    /// The renderer can't replace fonts, so a hot reloaded font gets a new id and sections
+    /// holding on to the old one keep drawing the old version. Look the id up when building
+    /// sections instead of caching it, like `Text` components do. Every reload also leaves the
+    /// old version in the renderer, which only matters for long editing sessions.
+    pub fn font_id(&self) -> Option<FontId> {
+        self.id
+    }
+}
+
+/// A WGSL or GLSL shader file, for materials. Pass it to `Engine::watch_material` to have the
+/// material pick up changes to the file.
+pub struct Shader {
+    pub(crate) code: ShaderCode,
+    // Bumped on every reload so materials using it know to rebuild
+    pub(crate) generation: u32,
+}
+
+impl Shader {
+    pub fn new(code: ShaderCode) -> Self {
+        Self {
+            code,
+            generation: 0,
+        }
+    }
+
+    pub fn code(&self) -> &ShaderCode {
+        &self.code
+    }
+
+    /// A material with this as its fragment shader
+    pub fn material(&self) -> MaterialDescriptor {
+        MaterialDescriptor::new(self.code.clone())
+    }
+}
+
+/// Raw file contents, for levels, configs and anything else the engine doesn't decode itself
+pub struct DataFile {
+    bytes: Vec<u8>,
+}
+
+impl DataFile {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self { bytes }
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn as_str(&self) -> Result<&str, std::str::Utf8Error> {
+        std::str::from_utf8(&self.bytes)
+    }
+}
+
+pub(crate) fn decode_error(path: &Path, message: impl ToString) -> AssetError {
+    AssetError::DecodeError {
+        path: path.to_path_buf(),
+        message: message.to_string(),
+    }
+}
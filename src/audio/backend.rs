@@ -0,0 +1,189 @@
+//! Audio output backends. The mixer doesn't care where its samples end up.
+
+use std::sync::{Arc, Mutex};
+
+use crate::{audio::mixer::SharedMixer, error::AudioError};
+
+/// Something that pulls mixed samples out of the mixer, usually an output device
+pub trait AudioBackend {
+    /// Output sample rate the mixer should produce
+    fn sample_rate(&self) -> u32;
+
+    /// Start pulling audio from the mixer. Called once when the backend is handed to `Audio`.
+    fn start(&mut self, mixer: SharedMixer) -> Result<(), AudioError>;
+}
+
+/// Backend without an output device. Nothing is mixed until `NullHandle::render` is called,
+/// which makes it handy for CI and tests that want to assert on the mixed samples.
+pub struct NullBackend {
+    sample_rate: u32,
+    handle: NullHandle,
+}
+
+impl NullBackend {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            handle: NullHandle::default(),
+        }
+    }
+
+    /// Grab this before handing the backend to `Audio` to drive the mixer manually
+    pub fn handle(&self) -> NullHandle {
+        self.handle.clone()
+    }
+}
+
+impl Default for NullBackend {
+    fn default() -> Self {
+        Self::new(44_100)
+    }
+}
+
+impl AudioBackend for NullBackend {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn start(&mut self, mixer: SharedMixer) -> Result<(), AudioError> {
+        *self.handle.mixer.lock().unwrap() = Some(mixer);
+        Ok(())
+    }
+}
+
+/// Drives a `NullBackend`'s mixer and keeps everything it produced
+#[derive(Clone, Default)]
+pub struct NullHandle {
+    mixer: Arc<Mutex<Option<SharedMixer>>>,
+    recorded: Arc<Mutex<Vec<f32>>>,
+}
+
+impl NullHandle {
+    /// Mix `frames` stereo frames, append them to the recording and return them
+    pub fn render(&self, frames: usize) -> Vec<f32> {
+        let mut out = vec![0.0; frames * 2];
+        if let Some(mixer) = self.mixer.lock().unwrap().as_ref() {
+            mixer.lock().unwrap().mix(&mut out);
+        }
+        self.recorded.lock().unwrap().extend_from_slice(&out);
+        out
+    }
+
+    /// Every interleaved stereo sample rendered so far
+    pub fn recorded(&self) -> Vec<f32> {
+        self.recorded.lock().unwrap().clone()
+    }
+
+    pub fn clear(&self) {
+        self.recorded.lock().unwrap().clear();
+    }
+}
+
+/// Plays through the default output device using cpal
+#[cfg(feature = "cpal")]
+pub struct CpalBackend {
+    device: cpal::Device,
+    config: cpal::SupportedStreamConfig,
+    // The stream stops playing as soon as it is dropped
+    stream: Option<cpal::Stream>,
+}
+
+#[cfg(feature = "cpal")]
+impl CpalBackend {
+    pub fn new() -> Result<Self, AudioError> {
+        use cpal::traits::{DeviceTrait, HostTrait};
+
+        let host = cpal::default_host();
+        let device = host
+            .default_output_device()
+            .ok_or(AudioError::NoOutputDevice)?;
+        let config = device
+            .default_output_config()
+            .map_err(|e| AudioError::BackendError(e.to_string()))?;
+
+        Ok(Self {
+            device,
+            config,
+            stream: None,
+        })
+    }
+}
+
+#[cfg(feature = "cpal")]
+impl AudioBackend for CpalBackend {
+    fn sample_rate(&self) -> u32 {
+        self.config.sample_rate().0
+    }
+
+    fn start(&mut self, mixer: SharedMixer) -> Result<(), AudioError> {
+        use cpal::traits::StreamTrait;
+
+        let stream_config: cpal::StreamConfig = self.config.clone().into();
+        let stream = match self.config.sample_format() {
+            cpal::SampleFormat::F32 => {
+                build_cpal_stream::<f32>(&self.device, &stream_config, mixer)
+            }
+            cpal::SampleFormat::I16 => {
+                build_cpal_stream::<i16>(&self.device, &stream_config, mixer)
+            }
+            cpal::SampleFormat::U16 => {
+                build_cpal_stream::<u16>(&self.device, &stream_config, mixer)
+            }
+            other => Err(AudioError::BackendError(format!(
+                "unsupported sample format {other:?}"
+            ))),
+        }?;
+
+        stream
+            .play()
+            .map_err(|e| AudioError::BackendError(e.to_string()))?;
+        self.stream = Some(stream);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "cpal")]
+fn build_cpal_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mixer: SharedMixer,
+) -> Result<cpal::Stream, AudioError>
+where
+    T: cpal::SizedSample + cpal::FromSample<f32>,
+{
+    use cpal::{Sample, traits::DeviceTrait};
+
+    let channels = config.channels as usize;
+    let mut scratch = Vec::new();
+
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+                // The mixer always produces stereo, map it onto however many channels the device has
+                let frames = data.len() / channels;
+                scratch.resize(frames * 2, 0.0);
+                match mixer.lock() {
+                    Ok(mut mixer) => mixer.mix(&mut scratch),
+                    Err(_) => scratch.fill(0.0),
+                }
+
+                for (frame, out) in data.chunks_exact_mut(channels).enumerate() {
+                    let (left, right) = (scratch[frame * 2], scratch[frame * 2 + 1]);
+                    if channels == 1 {
+                        out[0] = T::from_sample((left + right) * 0.5);
+                        continue;
+                    }
+
+                    out[0] = T::from_sample(left);
+                    out[1] = T::from_sample(right);
+                    for sample in &mut out[2..] {
+                        *sample = T::from_sample(0.0f32);
+                    }
+                }
+            },
+            |err| log::error!("Audio stream error: {err}"),
+            None,
+        )
+        .map_err(|e| AudioError::BackendError(e.to_string()))
+}
@@ -0,0 +1,258 @@
+//! Software mixer shared between the game thread and the audio backend
+
+use std::sync::{Arc, Mutex};
+
+use crate::audio::{Bus, PlaybackSettings, SoundInstance, sound::Sound};
+
+/// The mixer as handed to audio backends. Backends lock it and call `Mixer::mix` whenever
+/// the output device wants more samples.
+pub type SharedMixer = Arc<Mutex<Mixer>>;
+
+/// Linear gain ramp measured in output frames
+#[derive(Clone, Copy, Debug)]
+struct Fade {
+    from: f32,
+    to: f32,
+    length: u32,
+    elapsed: u32,
+}
+
+impl Fade {
+    fn new(from: f32, to: f32, length: u32) -> Self {
+        Self {
+            from,
+            to,
+            length,
+            elapsed: 0,
+        }
+    }
+
+    fn gain(&self) -> f32 {
+        if self.is_done() {
+            return self.to;
+        }
+        let t = self.elapsed as f32 / self.length as f32;
+        self.from + (self.to - self.from) * t
+    }
+
+    fn is_done(&self) -> bool {
+        self.elapsed >= self.length
+    }
+}
+
+struct Voice {
+    instance: SoundInstance,
+    sound: Sound,
+    // Fractional frame position inside the sound
+    cursor: f64,
+    settings: PlaybackSettings,
+    fade: Option<Fade>,
+    stop_after_fade: bool,
+    finished: bool,
+}
+
+impl Voice {
+    fn fade_gain(&self) -> f32 {
+        self.fade.map_or(1.0, |fade| fade.gain())
+    }
+
+    fn advance(&mut self, step: f64) {
+        self.cursor += step;
+
+        if let Some(fade) = &mut self.fade {
+            fade.elapsed = fade.elapsed.saturating_add(1);
+            if fade.is_done() && self.stop_after_fade {
+                self.finished = true;
+            }
+        }
+
+        let frames = self.sound.frames() as f64;
+        if self.cursor >= frames {
+            if self.settings.looping && frames > 0.0 {
+                self.cursor %= frames;
+            } else {
+                self.finished = true;
+            }
+        }
+    }
+}
+
+/// Mixes every playing voice into interleaved stereo output
+pub struct Mixer {
+    sample_rate: u32,
+    voices: Vec<Voice>,
+    master_volume: f32,
+    bus_volumes: [f32; Bus::COUNT],
+    music: Option<SoundInstance>,
+    next_instance: u64,
+}
+
+impl Mixer {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            voices: Vec::new(),
+            master_volume: 1.0,
+            bus_volumes: [1.0; Bus::COUNT],
+            music: None,
+            next_instance: 0,
+        }
+    }
+
+    /// Output sample rate in Hz
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Number of sounds currently playing (including music that is fading out)
+    pub fn active_voices(&self) -> usize {
+        self.voices.len()
+    }
+
+    /// Fill `out` with interleaved stereo samples. Finished one-shot sounds are dropped.
+    pub fn mix(&mut self, out: &mut [f32]) {
+        out.fill(0.0);
+        let frames = out.len() / 2;
+
+        for voice in &mut self.voices {
+            let bus_gain = self.bus_volumes[voice.settings.bus.index()];
+            let (pan_left, pan_right) = pan_gains(voice.settings.pan);
+            // Pitch shifting and resampling to the output rate are the same thing here
+            let step = voice.settings.pitch.max(0.0) as f64 * voice.sound.sample_rate() as f64
+                / self.sample_rate as f64;
+
+            for frame in 0..frames {
+                if voice.finished {
+                    break;
+                }
+
+                let gain =
+                    voice.settings.volume * bus_gain * self.master_volume * voice.fade_gain();
+                let (left, right) = voice.sound.sample_at(voice.cursor);
+                out[frame * 2] += left * gain * pan_left;
+                out[frame * 2 + 1] += right * gain * pan_right;
+
+                voice.advance(step);
+            }
+        }
+
+        self.voices.retain(|voice| !voice.finished);
+
+        for sample in out.iter_mut() {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+    }
+
+    pub(crate) fn play(&mut self, sound: Sound, settings: PlaybackSettings) -> SoundInstance {
+        self.add_voice(sound, settings, None)
+    }
+
+    pub(crate) fn play_music(&mut self, sound: Sound, crossfade: f32) -> SoundInstance {
+        let fade_frames = self.seconds_to_frames(crossfade);
+
+        if let Some(current) = self.music.take() {
+            self.fade_out(current, fade_frames);
+        }
+
+        let settings = PlaybackSettings {
+            bus: Bus::Music,
+            looping: true,
+            ..PlaybackSettings::default()
+        };
+        let instance = self.add_voice(sound, settings, Some(Fade::new(0.0, 1.0, fade_frames)));
+        self.music = Some(instance);
+        instance
+    }
+
+    pub(crate) fn stop_music(&mut self, fade_out: f32) {
+        if let Some(current) = self.music.take() {
+            let fade_frames = self.seconds_to_frames(fade_out);
+            self.fade_out(current, fade_frames);
+        }
+    }
+
+    pub(crate) fn stop(&mut self, instance: SoundInstance) {
+        self.voices.retain(|voice| voice.instance != instance);
+        if self.music == Some(instance) {
+            self.music = None;
+        }
+    }
+
+    pub(crate) fn is_playing(&self, instance: SoundInstance) -> bool {
+        self.voices.iter().any(|voice| voice.instance == instance)
+    }
+
+    /// Modify the settings of a playing sound. Does nothing if it already finished.
+    pub(crate) fn update_settings(
+        &mut self,
+        instance: SoundInstance,
+        update: impl FnOnce(&mut PlaybackSettings),
+    ) {
+        if let Some(voice) = self.voice_mut(instance) {
+            update(&mut voice.settings);
+        }
+    }
+
+    pub(crate) fn set_master_volume(&mut self, volume: f32) {
+        self.master_volume = volume.max(0.0);
+    }
+
+    pub(crate) fn master_volume(&self) -> f32 {
+        self.master_volume
+    }
+
+    pub(crate) fn set_bus_volume(&mut self, bus: Bus, volume: f32) {
+        self.bus_volumes[bus.index()] = volume.max(0.0);
+    }
+
+    pub(crate) fn bus_volume(&self, bus: Bus) -> f32 {
+        self.bus_volumes[bus.index()]
+    }
+
+    fn add_voice(
+        &mut self,
+        sound: Sound,
+        settings: PlaybackSettings,
+        fade: Option<Fade>,
+    ) -> SoundInstance {
+        let instance = SoundInstance(self.next_instance);
+        self.next_instance += 1;
+
+        self.voices.push(Voice {
+            instance,
+            sound,
+            cursor: 0.0,
+            settings,
+            fade,
+            stop_after_fade: false,
+            finished: false,
+        });
+        instance
+    }
+
+    fn fade_out(&mut self, instance: SoundInstance, fade_frames: u32) {
+        if let Some(voice) = self.voice_mut(instance) {
+            // Start from wherever the voice currently is, in case it was still fading in
+            voice.fade = Some(Fade::new(voice.fade_gain(), 0.0, fade_frames));
+            voice.stop_after_fade = true;
+            voice.finished = fade_frames == 0;
+        }
+    }
+
+    fn voice_mut(&mut self, instance: SoundInstance) -> Option<&mut Voice> {
+        self.voices
+            .iter_mut()
+            .find(|voice| voice.instance == instance)
+    }
+
+    fn seconds_to_frames(&self, seconds: f32) -> u32 {
+        (seconds.max(0.0) * self.sample_rate as f32) as u32
+    }
+}
+
+/// Left/right gains for a pan value in -1.0 (left) ..= 1.0 (right).
+/// Centered sounds play at full volume on both sides.
+fn pan_gains(pan: f32) -> (f32, f32) {
+    let pan = pan.clamp(-1.0, 1.0);
+    ((1.0 - pan).min(1.0), (1.0 + pan).min(1.0))
+}
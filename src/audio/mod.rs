@@ -0,0 +1,287 @@
+//! Audio playback: one-shot sound effects, looping music with crossfades and volume buses.
+//!
+//! Everything is mixed in software by the `Mixer` and handed to a pluggable `AudioBackend`.
+//! Games normally use the output device picked by the engine, but any backend can be passed
+//! through `EngineConfig::with_audio_backend` (e.g. `NullBackend` for CI).
+
+pub mod backend;
+pub mod mixer;
+pub mod sound;
+pub mod spatial;
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+#[cfg(feature = "cpal")]
+pub use backend::CpalBackend;
+pub use backend::{AudioBackend, NullBackend, NullHandle};
+pub use mixer::{Mixer, SharedMixer};
+pub use sound::Sound;
+pub use spatial::{AudioEmitter, AudioListener, Falloff};
+
+use crate::error::AudioError;
+
+/// Volume group a sound plays through. Every bus is scaled by the master volume.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Bus {
+    Sfx,
+    Music,
+}
+
+impl Bus {
+    pub(crate) const COUNT: usize = 2;
+
+    pub(crate) fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// How a sound should be played
+#[derive(Clone, Copy, Debug)]
+pub struct PlaybackSettings {
+    pub volume: f32,
+    /// Playback speed multiplier. 2.0 plays an octave higher (and twice as fast).
+    pub pitch: f32,
+    /// -1.0 is fully left, 1.0 is fully right
+    pub pan: f32,
+    pub looping: bool,
+    pub bus: Bus,
+}
+
+impl Default for PlaybackSettings {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            pitch: 1.0,
+            pan: 0.0,
+            looping: false,
+            bus: Bus::Sfx,
+        }
+    }
+}
+
+impl PlaybackSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    pub fn with_pitch(mut self, pitch: f32) -> Self {
+        self.pitch = pitch;
+        self
+    }
+
+    pub fn with_pan(mut self, pan: f32) -> Self {
+        self.pan = pan;
+        self
+    }
+
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    pub fn with_bus(mut self, bus: Bus) -> Self {
+        self.bus = bus;
+        self
+    }
+}
+
+/// Identifies a sound that was started with `Audio::play`. Stays valid (but inert) after the
+/// sound finishes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SoundInstance(pub(crate) u64);
+
+/// The engine's audio resource, available through `engine.audio()`
+pub struct Audio {
+    mixer: SharedMixer,
+    // Kept alive for as long as the engine runs, dropping it stops output
+    _backend: Box<dyn AudioBackend>,
+}
+
+impl Audio {
+    pub fn new(mut backend: Box<dyn AudioBackend>) -> Result<Self, AudioError> {
+        let mixer = Arc::new(Mutex::new(Mixer::new(backend.sample_rate())));
+        backend.start(mixer.clone())?;
+
+        Ok(Self {
+            mixer,
+            _backend: backend,
+        })
+    }
+
+    /// Use the system's default output device, falling back to a silent `NullBackend` if there
+    /// isn't one (or the `cpal` feature is disabled)
+    pub fn with_default_backend() -> Self {
+        #[cfg(feature = "cpal")]
+        match backend::CpalBackend::new().and_then(|backend| Self::new(Box::new(backend))) {
+            Ok(audio) => return audio,
+            Err(e) => log::warn!("Falling back to null audio backend: {e}"),
+        }
+
+        Self::null()
+    }
+
+    /// Start `backend`, falling back to a silent `NullBackend` if it fails to start
+    pub fn with_backend(backend: Box<dyn AudioBackend>) -> Self {
+        Self::new(backend).unwrap_or_else(|e| {
+            log::warn!("Falling back to null audio backend: {e}");
+            Self::null()
+        })
+    }
+
+    fn null() -> Self {
+        Self::new(Box::new(NullBackend::default()))
+            .expect("Null audio backend should never fail to start")
+    }
+
+    /// Play a one-shot sound effect with default settings
+    pub fn play(&self, sound: &Sound) -> SoundInstance {
+        self.play_with(sound, PlaybackSettings::default())
+    }
+
+    pub fn play_with(&self, sound: &Sound, settings: PlaybackSettings) -> SoundInstance {
+        self.mixer().play(sound.clone(), settings)
+    }
+
+    /// Start looping `sound` on the music bus, crossfading from the current track over
+    /// `crossfade` seconds
+    pub fn play_music(&self, sound: &Sound, crossfade: f32) -> SoundInstance {
+        self.mixer().play_music(sound.clone(), crossfade)
+    }
+
+    /// Fade out the current music track over `fade_out` seconds
+    pub fn stop_music(&self, fade_out: f32) {
+        self.mixer().stop_music(fade_out);
+    }
+
+    /// Stop a sound immediately
+    pub fn stop(&self, instance: SoundInstance) {
+        self.mixer().stop(instance);
+    }
+
+    pub fn is_playing(&self, instance: SoundInstance) -> bool {
+        self.mixer().is_playing(instance)
+    }
+
+    pub fn set_volume(&self, instance: SoundInstance, volume: f32) {
+        self.mixer()
+            .update_settings(instance, |settings| settings.volume = volume);
+    }
+
+    pub fn set_pitch(&self, instance: SoundInstance, pitch: f32) {
+        self.mixer()
+            .update_settings(instance, |settings| settings.pitch = pitch);
+    }
+
+    pub fn set_pan(&self, instance: SoundInstance, pan: f32) {
+        self.mixer()
+            .update_settings(instance, |settings| settings.pan = pan);
+    }
+
+    pub fn master_volume(&self) -> f32 {
+        self.mixer().master_volume()
+    }
+
+    pub fn set_master_volume(&self, volume: f32) {
+        self.mixer().set_master_volume(volume);
+    }
+
+    pub fn bus_volume(&self, bus: Bus) -> f32 {
+        self.mixer().bus_volume(bus)
+    }
+
+    pub fn set_bus_volume(&self, bus: Bus, volume: f32) {
+        self.mixer().set_bus_volume(bus, volume);
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.mixer().sample_rate()
+    }
+
+    fn mixer(&self) -> MutexGuard<'_, Mixer> {
+        // A poisoned lock just means the audio thread panicked mid-mix, the state is still usable
+        self.mixer.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One second of a constant 0.5 on both channels, at the null backend's rate
+    fn tone() -> Sound {
+        Sound::from_stereo_samples(vec![0.5; 44_100 * 2], 44_100)
+    }
+
+    fn null_audio() -> (Audio, NullHandle) {
+        let backend = NullBackend::default();
+        let handle = backend.handle();
+        let audio = Audio::new(Box::new(backend)).unwrap();
+        (audio, handle)
+    }
+
+    fn assert_frames(samples: &[f32], left: f32, right: f32) {
+        for frame in samples.chunks(2) {
+            assert!(
+                (frame[0] - left).abs() < 1e-6,
+                "left {} != {left}",
+                frame[0]
+            );
+            assert!(
+                (frame[1] - right).abs() < 1e-6,
+                "right {} != {right}",
+                frame[1]
+            );
+        }
+    }
+
+    #[test]
+    fn silent_without_sounds() {
+        let (_audio, handle) = null_audio();
+        assert_frames(&handle.render(64), 0.0, 0.0);
+    }
+
+    #[test]
+    fn volume_scales_samples() {
+        let (audio, handle) = null_audio();
+        audio.play_with(&tone(), PlaybackSettings::new().with_volume(0.5));
+        assert_frames(&handle.render(64), 0.25, 0.25);
+    }
+
+    #[test]
+    fn pan_silences_the_other_side() {
+        let (audio, handle) = null_audio();
+        audio.play_with(&tone(), PlaybackSettings::new().with_pan(1.0));
+        assert_frames(&handle.render(64), 0.0, 0.5);
+
+        let (audio, handle) = null_audio();
+        audio.play_with(&tone(), PlaybackSettings::new().with_pan(-0.5));
+        assert_frames(&handle.render(64), 0.5, 0.25);
+    }
+
+    #[test]
+    fn bus_and_master_volumes_multiply() {
+        let (audio, handle) = null_audio();
+        audio.set_bus_volume(Bus::Sfx, 0.5);
+        audio.set_master_volume(0.5);
+        audio.play(&tone());
+        audio.play_with(&tone(), PlaybackSettings::new().with_bus(Bus::Music));
+        // Sfx gets 0.5 * 0.5 * 0.5, music only the master volume
+        assert_frames(&handle.render(64), 0.375, 0.375);
+    }
+
+    #[test]
+    fn finished_sounds_stop_mixing() {
+        let (audio, handle) = null_audio();
+        let short = Sound::from_stereo_samples(vec![0.5; 16 * 2], 44_100);
+        let instance = audio.play(&short);
+        handle.render(32);
+        assert!(!audio.is_playing(instance));
+        assert_frames(&handle.render(16), 0.0, 0.0);
+        assert_eq!(handle.recorded().len(), 48 * 2);
+    }
+}
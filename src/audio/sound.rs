@@ -0,0 +1,139 @@
+//! Decoded sound clips (WAV and OGG Vorbis)
+
+use std::{io::Cursor, path::Path, sync::Arc};
+
+use crate::error::AudioError;
+
+/// A fully decoded sound clip.
+/// Samples are stored as interleaved stereo `f32`s and shared, so cloning a `Sound` is cheap.
+#[derive(Clone, Debug)]
+pub struct Sound {
+    samples: Arc<[f32]>,
+    sample_rate: u32,
+}
+
+impl Sound {
+    /// Build a sound from interleaved stereo samples in the -1.0..=1.0 range
+    pub fn from_stereo_samples(samples: Vec<f32>, sample_rate: u32) -> Self {
+        Self {
+            samples: samples.into(),
+            sample_rate,
+        }
+    }
+
+    /// Load a `.wav` or `.ogg` file, picking the decoder from the file extension
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, AudioError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        Self::from_bytes(&bytes, &extension)
+    }
+
+    /// Decode an in-memory file. `extension` is the file extension without the dot ("wav", "ogg").
+    pub fn from_bytes(bytes: &[u8], extension: &str) -> Result<Self, AudioError> {
+        match extension {
+            "wav" | "wave" => Self::from_wav_bytes(bytes),
+            "ogg" | "oga" => Self::from_ogg_bytes(bytes),
+            other => Err(AudioError::UnsupportedFormat(other.to_string())),
+        }
+    }
+
+    pub fn from_wav_bytes(bytes: &[u8]) -> Result<Self, AudioError> {
+        let mut reader = hound::WavReader::new(Cursor::new(bytes))
+            .map_err(|e| AudioError::DecodeError(e.to_string()))?;
+        let spec = reader.spec();
+
+        let samples: Vec<f32> = match spec.sample_format {
+            hound::SampleFormat::Float => reader
+                .samples::<f32>()
+                .collect::<Result<_, _>>()
+                .map_err(|e| AudioError::DecodeError(e.to_string()))?,
+            hound::SampleFormat::Int => {
+                // Integer samples are scaled down into -1.0..=1.0 based on their bit depth
+                let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .samples::<i32>()
+                    .map(|sample| sample.map(|sample| sample as f32 * scale))
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| AudioError::DecodeError(e.to_string()))?
+            }
+        };
+
+        Ok(Self::from_stereo_samples(
+            to_stereo(samples, spec.channels as usize)?,
+            spec.sample_rate,
+        ))
+    }
+
+    pub fn from_ogg_bytes(bytes: &[u8]) -> Result<Self, AudioError> {
+        let mut reader = lewton::inside_ogg::OggStreamReader::new(Cursor::new(bytes))
+            .map_err(|e| AudioError::DecodeError(e.to_string()))?;
+        let channels = reader.ident_hdr.audio_channels as usize;
+        let sample_rate = reader.ident_hdr.audio_sample_rate;
+
+        let mut samples = Vec::new();
+        while let Some(packet) = reader
+            .read_dec_packet_itl()
+            .map_err(|e| AudioError::DecodeError(e.to_string()))?
+        {
+            samples.extend(packet.into_iter().map(|sample| sample as f32 / 32768.0));
+        }
+
+        Ok(Self::from_stereo_samples(
+            to_stereo(samples, channels)?,
+            sample_rate,
+        ))
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Number of stereo frames in the clip
+    pub fn frames(&self) -> usize {
+        self.samples.len() / 2
+    }
+
+    /// Length of the clip in seconds
+    pub fn duration(&self) -> f32 {
+        self.frames() as f32 / self.sample_rate as f32
+    }
+
+    /// Linearly interpolated stereo frame at a fractional frame position
+    pub(crate) fn sample_at(&self, position: f64) -> (f32, f32) {
+        let frames = self.frames();
+        if frames == 0 {
+            return (0.0, 0.0);
+        }
+
+        let index = (position as usize).min(frames - 1);
+        let next = (index + 1).min(frames - 1);
+        let t = (position - index as f64) as f32;
+
+        let left = self.samples[index * 2] * (1.0 - t) + self.samples[next * 2] * t;
+        let right = self.samples[index * 2 + 1] * (1.0 - t) + self.samples[next * 2 + 1] * t;
+        (left, right)
+    }
+}
+
+/// Convert interleaved samples with any channel count into interleaved stereo
+fn to_stereo(samples: Vec<f32>, channels: usize) -> Result<Vec<f32>, AudioError> {
+    match channels {
+        0 => Err(AudioError::DecodeError("audio has no channels".to_string())),
+        1 => Ok(samples
+            .into_iter()
+            .flat_map(|sample| [sample, sample])
+            .collect()),
+        2 => Ok(samples),
+        // Surround sound just keeps the front left and right channels
+        _ => Ok(samples
+            .chunks_exact(channels)
+            .flat_map(|frame| [frame[0], frame[1]])
+            .collect()),
+    }
+}
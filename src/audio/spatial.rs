@@ -0,0 +1,159 @@
+//! Positional audio. `AudioEmitter`s get their volume and stereo pan from where they are
+//! relative to the `AudioListener`, updated every frame by `spatial_audio_system`.
+
+use std::collections::HashMap;
+
+use bevy_ecs::prelude::{Component, Entity, Resource, World};
+
+use crate::audio::{PlaybackSettings, Sound, SoundInstance};
+
+/// How quickly an emitter gets quieter between its min and max distance
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Falloff {
+    /// Volume drops linearly to zero at the max distance
+    #[default]
+    Linear,
+    /// 1/distance style rolloff. Higher `rolloff` values get quiet faster.
+    /// Still cut off to silence at the max distance.
+    Inverse { rolloff: f32 },
+    /// (1 - t)^exponent where t is 0 at the min distance and 1 at the max distance
+    Exponential { exponent: f32 },
+    /// Custom curve taking t (0 at min distance, 1 at max distance) and returning a gain
+    Custom(fn(f32) -> f32),
+}
+
+impl Falloff {
+    /// Gain for an emitter `distance` units away from the listener
+    pub fn gain(&self, distance: f32, min_distance: f32, max_distance: f32) -> f32 {
+        if distance <= min_distance {
+            return 1.0;
+        }
+        if distance >= max_distance {
+            return 0.0;
+        }
+
+        let t = (distance - min_distance) / (max_distance - min_distance).max(f32::EPSILON);
+        let gain = match *self {
+            Falloff::Linear => 1.0 - t,
+            Falloff::Inverse { rolloff } => {
+                let reference = min_distance.max(1.0);
+                reference / (reference + rolloff.max(0.0) * (distance - min_distance))
+            }
+            Falloff::Exponential { exponent } => (1.0 - t).powf(exponent),
+            Falloff::Custom(curve) => curve(t),
+        };
+        gain.clamp(0.0, 1.0)
+    }
+}
+
+/// Marks the entity sounds are heard from, typically the camera or the player.
+/// Only the first listener found is used.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct AudioListener;
+
+/// A sound that plays from an entity's position. Starts playing when the entity is spawned and
+/// stops when the entity (or this component) goes away.
+#[derive(Component, Clone, Debug)]
+pub struct AudioEmitter {
+    pub sound: Sound,
+    pub volume: f32,
+    pub pitch: f32,
+    pub looping: bool,
+    pub falloff: Falloff,
+    /// Inside this distance the sound plays at full volume
+    pub min_distance: f32,
+    /// Beyond this distance the sound is silent
+    pub max_distance: f32,
+    /// How hard sounds get panned to the side they come from (0.0 disables panning)
+    pub pan_strength: f32,
+}
+
+impl AudioEmitter {
+    pub fn new(sound: Sound) -> Self {
+        Self {
+            sound,
+            volume: 1.0,
+            pitch: 1.0,
+            looping: false,
+            falloff: Falloff::default(),
+            min_distance: 50.0,
+            max_distance: 800.0,
+            pan_strength: 1.0,
+        }
+    }
+
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    pub fn with_pitch(mut self, pitch: f32) -> Self {
+        self.pitch = pitch;
+        self
+    }
+
+    pub fn with_falloff(mut self, falloff: Falloff) -> Self {
+        self.falloff = falloff;
+        self
+    }
+
+    pub fn with_distance(mut self, min_distance: f32, max_distance: f32) -> Self {
+        self.min_distance = min_distance;
+        self.max_distance = max_distance;
+        self
+    }
+
+    pub fn with_pan_strength(mut self, pan_strength: f32) -> Self {
+        self.pan_strength = pan_strength;
+        self
+    }
+
+    /// Volume and pan for an emitter at `emitter` heard from `listener` (both centers)
+    pub fn attenuate(&self, emitter: (f32, f32), listener: (f32, f32)) -> (f32, f32) {
+        let dx = emitter.0 - listener.0;
+        let dy = emitter.1 - listener.1;
+        let distance = (dx * dx + dy * dy).sqrt();
+
+        let volume = self.volume
+            * self
+                .falloff
+                .gain(distance, self.min_distance, self.max_distance);
+
+        // Pan follows the direction of the sound, easing in so things right on top of the
+        // listener don't flip between speakers
+        let pan = if distance > 0.0 {
+            let closeness = (distance / self.min_distance.max(1.0)).min(1.0);
+            (dx / distance) * closeness * self.pan_strength
+        } else {
+            0.0
+        };
+
+        (volume, pan.clamp(-1.0, 1.0))
+    }
+
+    pub(crate) fn playback_settings(&self, volume: f32, pan: f32) -> PlaybackSettings {
+        PlaybackSettings {
+            volume,
+            pitch: self.pitch,
+            pan,
+            looping: self.looping,
+            ..PlaybackSettings::default()
+        }
+    }
+}
+
+/// Sounds started by emitters, so they can be updated and stopped later
+#[derive(Resource, Default)]
+pub(crate) struct ActiveEmitters {
+    pub(crate) instances: HashMap<Entity, SoundInstance>,
+}
+
+/// Registers the resources the spatial audio system needs
+pub(crate) fn init(world: &mut World) {
+    world.init_resource::<ActiveEmitters>();
+}
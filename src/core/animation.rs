@@ -0,0 +1,128 @@
+//! Frame by frame sprite animation from Aseprite sheets, stepped by `animation_system`
+
+use bevy_ecs::prelude::Component;
+
+use crate::assets::{Aseprite, Handle};
+
+/// Plays frames of an `Aseprite` sheet on the entity's `Sprite`, adding one if it doesn't have
+/// it yet. Frames take as long as they were given in Aseprite.
+#[derive(Component, Clone, Debug)]
+pub struct SpriteAnimation {
+    pub sheet: Handle<Aseprite>,
+    /// 1 is normal speed, 2 twice as fast
+    pub speed: f32,
+    /// Set to false to hold the current frame
+    pub playing: bool,
+    // None plays every frame of the sheet
+    clip: Option<String>,
+    // Position in the clip's frame order
+    step: usize,
+    // Seconds into the current frame
+    elapsed: f32,
+    loops: u32,
+    finished: bool,
+    frame: usize,
+}
+
+impl SpriteAnimation {
+    /// Loops through every frame of the sheet until a clip is picked
+    pub fn new(sheet: Handle<Aseprite>) -> Self {
+        Self {
+            sheet,
+            speed: 1.0,
+            playing: true,
+            clip: None,
+            step: 0,
+            elapsed: 0.0,
+            loops: 0,
+            finished: false,
+            frame: 0,
+        }
+    }
+
+    pub fn with_clip(mut self, clip: impl Into<String>) -> Self {
+        self.play(clip);
+        self
+    }
+
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Switch to a clip by its tag name. Does nothing if it's already the one playing, so it's
+    /// fine to call every frame. A clip the sheet doesn't have leaves the sprite as it is.
+    pub fn play(&mut self, clip: impl Into<String>) {
+        let clip = clip.into();
+        if self.clip.as_ref() != Some(&clip) {
+            self.clip = Some(clip);
+            self.restart();
+        }
+    }
+
+    /// Start the current clip over from its first frame
+    pub fn restart(&mut self) {
+        self.step = 0;
+        self.elapsed = 0.0;
+        self.loops = 0;
+        self.finished = false;
+        self.playing = true;
+    }
+
+    pub fn clip(&self) -> Option<&str> {
+        self.clip.as_deref()
+    }
+
+    /// Index of the frame being shown, in the whole sheet
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// Whether a clip with a repeat count has played through and stopped on its last frame
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Move the animation along, returning the frame to show
+    pub(crate) fn advance(&mut self, sheet: &Aseprite, delta_time: f32) -> Option<usize> {
+        let (order, repeat) = match &self.clip {
+            Some(name) => {
+                let clip = sheet.clip(name)?;
+                (clip.frames(), clip.repeat)
+            }
+            None => ((0..sheet.frames.len()).collect(), None),
+        };
+        let last = order.len().checked_sub(1)?;
+        self.step = self.step.min(last);
+
+        if self.playing && !self.finished {
+            self.elapsed += delta_time * self.speed;
+            loop {
+                // Zero length frames would never let the loop end
+                let duration = sheet
+                    .frames
+                    .get(order[self.step])
+                    .map_or(0.0, |frame| frame.duration)
+                    .max(0.001);
+                if self.elapsed < duration {
+                    break;
+                }
+                self.elapsed -= duration;
+                if self.step < last {
+                    self.step += 1;
+                    continue;
+                }
+                self.loops += 1;
+                if repeat.is_some_and(|repeat| self.loops >= repeat) {
+                    self.finished = true;
+                    self.elapsed = 0.0;
+                    break;
+                }
+                self.step = 0;
+            }
+        }
+
+        self.frame = order[self.step];
+        (self.frame < sheet.frames.len()).then_some(self.frame)
+    }
+}
@@ -0,0 +1,43 @@
+//! 2D camera deciding which part of the world ends up on screen
+
+/// Everything in world space (shapes, sprites, world space text) is drawn relative to the
+/// camera. Screen space things like HUD text ignore it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    /// World position shown at the top left corner of the screen
+    pub x: f32,
+    pub y: f32,
+    /// 2.0 draws everything twice as big
+    pub zoom: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            zoom: 1.0,
+        }
+    }
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move the camera so `(x, y)` ends up in the middle of a screen of the given size
+    pub fn look_at(&mut self, x: f32, y: f32, screen_width: f32, screen_height: f32) {
+        self.x = x - screen_width / 2.0 / self.zoom;
+        self.y = y - screen_height / 2.0 / self.zoom;
+    }
+
+    pub fn world_to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        ((x - self.x) * self.zoom, (y - self.y) * self.zoom)
+    }
+
+    /// E.g. to find out what the mouse is pointing at
+    pub fn screen_to_world(&self, x: f32, y: f32) -> (f32, f32) {
+        (x / self.zoom + self.x, y / self.zoom + self.y)
+    }
+}
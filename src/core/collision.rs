@@ -0,0 +1,101 @@
+//! Collider components and trigger (sensor) detection
+//!
+//! Colliders are axis-aligned boxes attached to an entity's `Position`. Sensor colliders never
+//! push anything around, they only report when other colliders enter, stay inside or leave them.
+
+use std::collections::HashSet;
+
+use bevy_ecs::prelude::{Component, Entity, Message, Messages, Resource, World};
+
+use crate::core::world::{Bounds, Position, Size};
+
+/// An axis-aligned box collider, positioned relative to the entity's `Position`
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Collider {
+    pub offset_x: f32,
+    pub offset_y: f32,
+    /// Explicit collider size. `None` uses the entity's `Size` component.
+    pub size: Option<Size>,
+    /// Sensors never block other colliders, they only emit `TriggerEvent`s
+    pub is_sensor: bool,
+}
+
+impl Collider {
+    /// A solid collider that matches the entity's `Size` component
+    pub fn new() -> Self {
+        Self {
+            offset_x: 0.0,
+            offset_y: 0.0,
+            size: None,
+            is_sensor: false,
+        }
+    }
+
+    /// A solid collider with an explicit size, independent of the entity's `Size`
+    pub fn rect(width: f32, height: f32) -> Self {
+        Self {
+            size: Some(Size::new(width, height)),
+            ..Self::new()
+        }
+    }
+
+    /// Turns this collider into a sensor (trigger volume)
+    pub fn sensor(mut self) -> Self {
+        self.is_sensor = true;
+        self
+    }
+
+    pub fn with_offset(mut self, x: f32, y: f32) -> Self {
+        self.offset_x = x;
+        self.offset_y = y;
+        self
+    }
+
+    /// World-space bounds of this collider. Returns `None` if the collider has no explicit size
+    /// and the entity has no `Size` either.
+    pub fn bounds(&self, position: Position, entity_size: Option<&Size>) -> Option<Bounds> {
+        let size = self.size.or_else(|| entity_size.copied())?;
+        Some(Bounds::from_position_and_size(
+            Position::new(position.x + self.offset_x, position.y + self.offset_y),
+            size,
+        ))
+    }
+}
+
+impl Default for Collider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriggerKind {
+    /// The other collider started overlapping the sensor this frame
+    Enter,
+    /// The other collider was already overlapping the sensor and still is
+    Stay,
+    /// The other collider stopped overlapping the sensor (or one of them was despawned)
+    Exit,
+}
+
+/// Emitted by `trigger_system` for every sensor/collider overlap.
+///
+/// Read them with `Engine::trigger_events()` or a `MessageReader<TriggerEvent>` in your own systems.
+#[derive(Message, Clone, Copy, Debug)]
+pub struct TriggerEvent {
+    pub kind: TriggerKind,
+    pub sensor: Entity,
+    pub other: Entity,
+}
+
+/// (sensor, other) pairs that overlapped last frame. Used to tell Enter and Stay apart.
+#[derive(Resource, Default)]
+pub(crate) struct ActiveTriggers {
+    pub(crate) pairs: HashSet<(Entity, Entity)>,
+}
+
+/// Registers the resources the trigger system needs. Called when the engine creates its world.
+pub(crate) fn init(world: &mut World) {
+    world.init_resource::<Messages<TriggerEvent>>();
+    world.init_resource::<ActiveTriggers>();
+}
@@ -0,0 +1,97 @@
+//! Light components, drawn by `lighting_system`.
+//!
+//! Lighting turns on as soon as there's an `AmbientLight` or a `PointLight` in the world.
+//! Everything but text is then darkened to the ambient color, except where point lights shine.
+//! Entities with an `Occluder` block point lights and cast shadows.
+
+use bevy_ecs::prelude::Component;
+
+use crate::core::render::colors::{BLACK, WHITE};
+
+/// A round light centered on the entity, fading out towards `radius`
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct PointLight {
+    /// Alpha is ignored, use `intensity` to dim a light
+    pub color: [f32; 4],
+    /// Brightness at the center. Above 1 lights things brighter than their own color.
+    pub intensity: f32,
+    /// In world units
+    pub radius: f32,
+    /// Whether occluders block this light
+    pub shadows: bool,
+    /// Size of the light itself in world units. Bigger lights cast blurrier shadow edges, 0
+    /// gives sharp ones.
+    pub softness: f32,
+}
+
+impl PointLight {
+    /// A white light casting sharp shadows
+    pub fn new(radius: f32) -> Self {
+        Self {
+            color: WHITE,
+            intensity: 1.0,
+            radius,
+            shadows: true,
+            softness: 0.0,
+        }
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn with_shadows(mut self, shadows: bool) -> Self {
+        self.shadows = shadows;
+        self
+    }
+
+    pub fn with_softness(mut self, softness: f32) -> Self {
+        self.softness = softness;
+        self
+    }
+}
+
+/// Blocks point lights, casting shadows behind the entity. Occluders are lit like everything
+/// else, and lights on the occluder's own entity shine right through it.
+#[derive(Component, Clone, Debug, PartialEq)]
+pub enum Occluder {
+    /// The entity's `Size`, from its position
+    Rect,
+    /// Points relative to the entity's position, in world units
+    Polygon(Vec<(f32, f32)>),
+}
+
+impl Occluder {
+    pub fn rect() -> Self {
+        Occluder::Rect
+    }
+
+    pub fn polygon(points: Vec<(f32, f32)>) -> Self {
+        Occluder::Polygon(points)
+    }
+}
+
+/// How lit everything is without any point lights nearby. Only one is used, put it on any
+/// entity. Without one, scenes with point lights are pitch black around them.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct AmbientLight {
+    pub color: [f32; 4],
+}
+
+impl AmbientLight {
+    pub fn new(color: [f32; 4]) -> Self {
+        Self { color }
+    }
+}
+
+impl Default for AmbientLight {
+    fn default() -> Self {
+        Self { color: BLACK }
+    }
+}
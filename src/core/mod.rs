@@ -1,5 +1,11 @@
+pub mod animation;
+pub mod camera;
+pub mod collision;
 pub mod input;
+pub mod lighting;
+pub mod particles;
 pub mod render;
 pub mod systems;
+pub mod tilemap;
 pub mod time;
 pub mod world;
@@ -0,0 +1,300 @@
+//! Particle effects, simulated and drawn by `particle_system`.
+//!
+//! Every particle in the world is drawn in one batch, on top of shapes and sprites but below
+//! `Text`. No more than `MAX_PARTICLES` are alive at once, emitters just stop spawning until
+//! some die off.
+
+use std::{
+    f32::consts::TAU,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use bevy_ecs::prelude::Component;
+
+use crate::{
+    assets::{Handle, Texture},
+    core::render::colors::WHITE,
+};
+
+/// Particles alive across every emitter
+pub const MAX_PARTICLES: usize = 10_000;
+
+/// Something a `Curve` can blend between
+pub trait Lerp: Copy {
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for [f32; 4] {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        std::array::from_fn(|i| self[i].lerp(other[i], t))
+    }
+}
+
+/// A value over a particle's life, from 0 when it spawns to 1 when it dies. Blends linearly
+/// between its keys and holds the first and last ones before and after them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Curve<T> {
+    keys: Vec<(f32, T)>,
+}
+
+impl<T: Lerp> Curve<T> {
+    pub fn constant(value: T) -> Self {
+        Self {
+            keys: vec![(0.0, value)],
+        }
+    }
+
+    /// From `start` to `end` over the whole life
+    pub fn linear(start: T, end: T) -> Self {
+        Self {
+            keys: vec![(0.0, start), (1.0, end)],
+        }
+    }
+
+    /// Add a key at `t`, between 0 and 1. Keys can be added in any order.
+    pub fn with_key(mut self, t: f32, value: T) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let index = self.keys.partition_point(|(key, _)| *key <= t);
+        self.keys.insert(index, (t, value));
+        self
+    }
+
+    pub fn sample(&self, t: f32) -> T {
+        let index = self.keys.partition_point(|(key, _)| *key <= t);
+        match (
+            index.checked_sub(1).map(|i| self.keys[i]),
+            self.keys.get(index),
+        ) {
+            (Some((start, from)), Some(&(end, to))) => from.lerp(to, (t - start) / (end - start)),
+            (Some((_, value)), None) | (None, Some(&(_, value))) => value,
+            (None, None) => unreachable!("curves always have a key"),
+        }
+    }
+}
+
+/// Where particles live once they're spawned
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SimulationSpace {
+    /// Particles stay where they were spawned, leaving a trail behind a moving emitter
+    #[default]
+    World,
+    /// Particles move along with the emitter
+    Local,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Particle {
+    // In world units, relative to the emitter in local space
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    velocity: (f32, f32),
+    age: f32,
+    lifetime: f32,
+}
+
+impl Particle {
+    /// How far through its life it is, 0 to 1
+    pub(crate) fn progress(&self) -> f32 {
+        (self.age / self.lifetime).min(1.0)
+    }
+}
+
+/// Spawns particles from the entity's center, continuously at `rate` and in bursts. Angles are
+/// in radians, with 0 pointing right and positive angles turning down.
+#[derive(Component, Clone, Debug)]
+pub struct ParticleEmitter {
+    /// Particles per second, 0 for bursts only
+    pub rate: f32,
+    /// Set to false to stop spawning, particles already out live on
+    pub emitting: bool,
+    /// Seconds, picked at random between the two
+    pub lifetime: (f32, f32),
+    /// World units per second, picked at random between the two
+    pub speed: (f32, f32),
+    /// Middle of the cone particles are launched in
+    pub direction: f32,
+    /// Width of the cone, `TAU` launches them every way
+    pub spread: f32,
+    /// Acceleration in world units per second squared
+    pub gravity: (f32, f32),
+    /// How much of its velocity a particle loses per second
+    pub drag: f32,
+    pub color: Curve<[f32; 4]>,
+    /// Width and height in world units
+    pub size: Curve<f32>,
+    pub space: SimulationSpace,
+    /// Tinted by `color`, plain squares when `None`
+    pub texture: Option<Handle<Texture>>,
+    /// Most particles this emitter has alive at once
+    pub max_particles: usize,
+    pub(crate) particles: Vec<Particle>,
+    // Fraction of a particle left over from last frame's `rate`
+    spawn_debt: f32,
+    pending_burst: usize,
+    rng: u32,
+}
+
+impl ParticleEmitter {
+    /// Small white particles shooting out every way, `rate` a second
+    pub fn new(rate: f32) -> Self {
+        // Every emitter gets its own sequence, so two at the same spot don't overlap exactly
+        static SEED: AtomicU32 = AtomicU32::new(0x9e37_79b9);
+        Self {
+            rate,
+            emitting: true,
+            lifetime: (1.0, 1.0),
+            speed: (50.0, 100.0),
+            direction: 0.0,
+            spread: TAU,
+            gravity: (0.0, 0.0),
+            drag: 0.0,
+            color: Curve::constant(WHITE),
+            size: Curve::constant(4.0),
+            space: SimulationSpace::World,
+            texture: None,
+            max_particles: 1000,
+            particles: Vec::new(),
+            spawn_debt: 0.0,
+            pending_burst: 0,
+            rng: SEED.fetch_add(0x9e37_79b9, Ordering::Relaxed) | 1,
+        }
+    }
+
+    /// Only spawns particles through `burst`
+    pub fn bursts() -> Self {
+        Self::new(0.0)
+    }
+
+    /// Spawn `count` particles all at once on the next update
+    pub fn burst(&mut self, count: usize) {
+        self.pending_burst += count;
+    }
+
+    pub fn with_burst(mut self, count: usize) -> Self {
+        self.burst(count);
+        self
+    }
+
+    pub fn with_lifetime(mut self, min: f32, max: f32) -> Self {
+        self.lifetime = (min, max);
+        self
+    }
+
+    pub fn with_speed(mut self, min: f32, max: f32) -> Self {
+        self.speed = (min, max);
+        self
+    }
+
+    pub fn with_cone(mut self, direction: f32, spread: f32) -> Self {
+        self.direction = direction;
+        self.spread = spread;
+        self
+    }
+
+    pub fn with_gravity(mut self, x: f32, y: f32) -> Self {
+        self.gravity = (x, y);
+        self
+    }
+
+    pub fn with_drag(mut self, drag: f32) -> Self {
+        self.drag = drag;
+        self
+    }
+
+    pub fn with_color(mut self, color: Curve<[f32; 4]>) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_size(mut self, size: Curve<f32>) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_space(mut self, space: SimulationSpace) -> Self {
+        self.space = space;
+        self
+    }
+
+    pub fn with_texture(mut self, texture: Handle<Texture>) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    pub fn with_max_particles(mut self, max_particles: usize) -> Self {
+        self.max_particles = max_particles;
+        self
+    }
+
+    /// Particles currently alive
+    pub fn particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    /// Remove every particle and any bursts that haven't gone off yet
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.pending_burst = 0;
+        self.spawn_debt = 0.0;
+    }
+
+    /// Age, move and spawn particles. `origin` is where new ones start, `budget` how many more
+    /// can be spawned before hitting `MAX_PARTICLES`. Returns how many were spawned.
+    pub(crate) fn update(&mut self, delta_time: f32, origin: (f32, f32), budget: usize) -> usize {
+        let drag = (1.0 - self.drag * delta_time).max(0.0);
+        self.particles.retain_mut(|particle| {
+            particle.age += delta_time;
+            particle.velocity.0 = (particle.velocity.0 + self.gravity.0 * delta_time) * drag;
+            particle.velocity.1 = (particle.velocity.1 + self.gravity.1 * delta_time) * drag;
+            particle.x += particle.velocity.0 * delta_time;
+            particle.y += particle.velocity.1 * delta_time;
+            particle.age < particle.lifetime
+        });
+
+        let mut count = std::mem::take(&mut self.pending_burst);
+        if self.emitting && self.rate > 0.0 {
+            self.spawn_debt += self.rate * delta_time;
+            count += self.spawn_debt as usize;
+            self.spawn_debt = self.spawn_debt.fract();
+        }
+        let count = count
+            .min(budget)
+            .min(self.max_particles.saturating_sub(self.particles.len()));
+
+        let origin = match self.space {
+            SimulationSpace::World => origin,
+            SimulationSpace::Local => (0.0, 0.0),
+        };
+        for _ in 0..count {
+            let angle = self.direction + (self.random() - 0.5) * self.spread;
+            let speed = self.random_between(self.speed);
+            let lifetime = self.random_between(self.lifetime).max(f32::EPSILON);
+            self.particles.push(Particle {
+                x: origin.0,
+                y: origin.1,
+                velocity: (angle.cos() * speed, angle.sin() * speed),
+                age: 0.0,
+                lifetime,
+            });
+        }
+        count
+    }
+
+    // xorshift, particles don't need anything better
+    fn random(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn random_between(&mut self, (min, max): (f32, f32)) -> f32 {
+        min + (max - min) * self.random()
+    }
+}
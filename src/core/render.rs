@@ -7,4 +7,40 @@ pub mod colors {
     pub const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
     pub const GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
     pub const BLUE: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+    pub const YELLOW: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+    pub const CYAN: [f32; 4] = [0.0, 1.0, 1.0, 1.0];
+    pub const MAGENTA: [f32; 4] = [1.0, 0.0, 1.0, 1.0];
+    pub const GRAY: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+    /// Look a color up by its lowercase name, e.g. "red"
+    pub fn by_name(name: &str) -> Option<[f32; 4]> {
+        match name {
+            "white" => Some(WHITE),
+            "black" => Some(BLACK),
+            "red" => Some(RED),
+            "green" => Some(GREEN),
+            "blue" => Some(BLUE),
+            "yellow" => Some(YELLOW),
+            "cyan" => Some(CYAN),
+            "magenta" => Some(MAGENTA),
+            "gray" | "grey" => Some(GRAY),
+            _ => None,
+        }
+    }
+
+    /// Parse `#rrggbb` or `#rrggbbaa`
+    pub fn from_hex(hex: &str) -> Option<[f32; 4]> {
+        let hex = hex.strip_prefix('#')?;
+        if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+            return None;
+        }
+
+        let channel = |i: usize| {
+            hex.get(i * 2..i * 2 + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+                .map(|c| c as f32 / 255.0)
+        };
+        let alpha = if hex.len() == 8 { channel(3)? } else { 1.0 };
+        Some([channel(0)?, channel(1)?, channel(2)?, alpha])
+    }
 }
\ No newline at end of file
@@ -3,36 +3,799 @@
 //! Systems are functions that query and iterate over ECS entities
 //! to perform operations like rendering or physics updates.
 
+use std::collections::HashSet;
+
+use bevy_ecs::prelude::{Entity, Messages, Mut, With};
+use wgpu_renderer::{
+    FULL_UV, FontId, Light, MaterialId, NineSlice, QUAD_INDICES, TextSection, TextSpan, TextureId,
+    Vertex,
+};
+
 use crate::{
-    core::world::{Position, Renderable, Size},
+    audio::spatial::{ActiveEmitters, AudioEmitter, AudioListener},
+    core::{
+        animation::SpriteAnimation,
+        collision::{ActiveTriggers, Collider, TriggerEvent, TriggerKind},
+        lighting::{AmbientLight, Occluder, PointLight},
+        particles::{MAX_PARTICLES, ParticleEmitter, SimulationSpace},
+        render::colors::WHITE,
+        tilemap::{CHUNK_SIZE, Tilemap, TilemapChunks},
+        world::{
+            BitmapText, Bounds, Layer, Material, NineSliceSprite, Position, Renderable, Size,
+            Sprite, Text, TextSpace,
+        },
+    },
     engine::context::Engine,
+    text::{
+        bitmap::QuadSource,
+        markup::{self, RichSpan},
+    },
 };
 
-/// Render system that queries all entities with (Position, Size, Renderable) components
-/// and draws them to the renderer
+// Icons of a rich text section, by the index of the placeholder span each one is drawn over
+type IconSpans = Vec<(usize, String)>;
+
+// Something the render system draws, collected first so it can be sorted by layer
+enum DrawCommand {
+    Rectangle {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        color: [f32; 4],
+    },
+    Sprite {
+        texture: TextureId,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        uv: [f32; 4],
+        color: [f32; 4],
+    },
+    NineSlice {
+        texture: TextureId,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        slice: NineSlice,
+        color: [f32; 4],
+    },
+}
+
+/// Render system that queries all entities with (Position, Size, Renderable),
+/// (Position, Size, Sprite), (Position, Size, NineSliceSprite), (Position, BitmapText) or
+/// (Position, Text) components and draws them to the renderer.
+/// Everything is drawn in order of its `Layer`, shapes and sprites through the camera, and
+/// with its `Material` if it has one.
 pub fn render_system(engine: &mut Engine) {
-    // Collect render data from the world first to avoid simultaneous mutable borrows
-    let render_data: Vec<(f32, f32, f32, f32, [f32; 4])> = {
-        let world = engine.world_mut();
-        let mut query = world.query::<(&Position, &Size, &Renderable)>();
+    let elapsed = engine.time().elapsed_time();
+
+    // The world, assets and renderer are separate fields so they can be borrowed together
+    let world = &mut engine.world;
+    let renderer = &mut engine.renderer;
+    let assets = &engine.assets;
+    let camera = engine.camera;
+    let text_icons = &engine.text_icons;
+    renderer.set_time(elapsed);
+
+    let mut commands: Vec<(Layer, MaterialId, DrawCommand)> = Vec::new();
+
+    let mut query = world.query::<(
+        &Position,
+        &Size,
+        &Renderable,
+        Option<&Layer>,
+        Option<&Material>,
+    )>();
+    for (position, size, renderable, layer, material) in query.iter(world) {
+        let (x, y) = camera.world_to_screen(position.x, position.y);
+        commands.push((
+            layer.copied().unwrap_or_default(),
+            material.map_or(MaterialId::DEFAULT, |material| material.0),
+            DrawCommand::Rectangle {
+                x,
+                y,
+                width: size.width * camera.zoom,
+                height: size.height * camera.zoom,
+                color: renderable.color,
+            },
+        ));
+    }
+
+    let mut query = world.query::<(&Position, &Size, &Sprite, Option<&Layer>, Option<&Material>)>();
+    for (position, size, sprite, layer, material) in query.iter(world) {
+        // Textures that haven't made it to the GPU yet are skipped
+        let Some(texture) = assets.get(&sprite.texture) else {
+            continue;
+        };
+        let Some(texture_id) = texture.gpu_id() else {
+            continue;
+        };
+
+        let (x, y) = camera.world_to_screen(position.x, position.y);
+        commands.push((
+            layer.copied().unwrap_or_default(),
+            material.map_or(MaterialId::DEFAULT, |material| material.0),
+            DrawCommand::Sprite {
+                texture: texture_id,
+                x,
+                y,
+                width: size.width * camera.zoom,
+                height: size.height * camera.zoom,
+                uv: texture.uv(sprite.region.as_ref()),
+                color: sprite.color,
+            },
+        ));
+    }
+
+    let mut query = world.query::<(
+        &Position,
+        &Size,
+        &NineSliceSprite,
+        Option<&Layer>,
+        Option<&Material>,
+    )>();
+    for (position, size, sprite, layer, material) in query.iter(world) {
+        let Some(texture) = assets.get(&sprite.texture) else {
+            continue;
+        };
+        let Some(texture_id) = texture.gpu_id() else {
+            continue;
+        };
+
+        let region_size = sprite.region.map_or(
+            (texture.width() as f32, texture.height() as f32),
+            |region| (region.width, region.height),
+        );
+        let mut slice = NineSlice::new(
+            texture.uv(sprite.region.as_ref()),
+            region_size,
+            sprite.insets,
+        );
+        slice.scale = sprite.scale * camera.zoom;
+        slice.edges = sprite.edges;
+        slice.center = sprite.center;
+
+        let (x, y) = camera.world_to_screen(position.x, position.y);
+        commands.push((
+            layer.copied().unwrap_or_default(),
+            material.map_or(MaterialId::DEFAULT, |material| material.0),
+            DrawCommand::NineSlice {
+                texture: texture_id,
+                x,
+                y,
+                width: size.width * camera.zoom,
+                height: size.height * camera.zoom,
+                slice,
+                color: sprite.color,
+            },
+        ));
+    }
+
+    let mut query = world.query::<(&Position, &BitmapText, Option<&Layer>, Option<&Material>)>();
+    for (position, text, layer, material) in query.iter(world) {
+        if !text.visible {
+            continue;
+        }
+        let Some(font) = assets.get(&text.font) else {
+            continue;
+        };
+
+        let layer = layer.copied().unwrap_or_default();
+        let material = material.map_or(MaterialId::DEFAULT, |material| material.0);
+        let spans = rich_spans(&text.text, text.markup);
+        let quads = font.layout(&spans, text.scale, text.color, text.h_align, text.v_align);
+
+        // Scaled around the text's position, so alignment still holds when zoomed
+        let (origin, zoom) = match text.space {
+            TextSpace::Screen => ((position.x, position.y), 1.0),
+            TextSpace::World => (camera.world_to_screen(position.x, position.y), camera.zoom),
+        };
+
+        for quad in quads {
+            let (texture, region) = match &quad.source {
+                QuadSource::Glyph { page, region } => (font.pages.get(*page), Some(region)),
+                QuadSource::Icon(name) => match text_icons.get(name) {
+                    Some(icon) => (Some(&icon.texture), icon.region.as_ref()),
+                    None => continue,
+                },
+            };
+            // Pages that haven't been uploaded yet are skipped, like sprites
+            let Some(texture) = texture.and_then(|texture| assets.get(texture)) else {
+                continue;
+            };
+            let Some(texture_id) = texture.gpu_id() else {
+                continue;
+            };
+
+            commands.push((
+                layer,
+                material,
+                DrawCommand::Sprite {
+                    texture: texture_id,
+                    x: origin.0 + quad.x * zoom,
+                    y: origin.1 + quad.y * zoom,
+                    width: quad.width * zoom,
+                    height: quad.height * zoom,
+                    uv: texture.uv(region),
+                    color: quad.color,
+                },
+            ));
+        }
+    }
+
+    // Stable, so entities on the same layer keep drawing in the same order as before layers
+    commands.sort_by_key(|(layer, _, _)| *layer);
+    for (_, material, command) in commands {
+        renderer.set_material(material);
+        match command {
+            DrawCommand::Rectangle {
+                x,
+                y,
+                width,
+                height,
+                color,
+            } => renderer.queue_rectangle(x, y, width, height, color),
+            DrawCommand::Sprite {
+                texture,
+                x,
+                y,
+                width,
+                height,
+                uv,
+                color,
+            } => renderer.queue_sprite(texture, x, y, width, height, uv, color),
+            DrawCommand::NineSlice {
+                texture,
+                x,
+                y,
+                width,
+                height,
+                slice,
+                color,
+            } => renderer.queue_nine_slice(texture, x, y, width, height, &slice, color),
+        }
+    }
+    renderer.set_material(MaterialId::DEFAULT);
+
+    let mut sections: Vec<(Layer, TextSection, IconSpans)> = Vec::new();
+    let mut query = world.query::<(&Position, &Text, Option<&Layer>)>();
+    for (position, text, layer) in query.iter(world) {
+        if !text.visible {
+            continue;
+        }
+
+        let font = match &text.font {
+            // Like sprites, text waits until its font has made it to the renderer
+            Some(font) => match assets.get(font).and_then(|font| font.font_id()) {
+                Some(font) => font,
+                None => continue,
+            },
+            None => FontId::DEFAULT,
+        };
+
+        let (position, scale, wrap_width) = match text.space {
+            TextSpace::Screen => ((position.x, position.y), 1.0, text.wrap_width),
+            TextSpace::World => (
+                camera.world_to_screen(position.x, position.y),
+                camera.zoom,
+                text.wrap_width.map(|width| width * camera.zoom),
+            ),
+        };
+
+        let mut section = TextSection::new(position).with_align(text.h_align, text.v_align);
+        section.wrap_width = wrap_width;
+
+        // Icons get an invisible placeholder span to make room, and are drawn where it ends up
+        let mut icons = Vec::new();
+        for span in rich_spans(&text.text, text.markup) {
+            let span = match span {
+                RichSpan::Text {
+                    text: content,
+                    color,
+                    size,
+                } => TextSpan::new(content)
+                    .with_color(color.unwrap_or(text.color))
+                    .with_size(size.unwrap_or(text.size) * scale),
+                RichSpan::Icon(name) => {
+                    icons.push((section.spans.len(), name));
+                    TextSpan::new("M")
+                        .with_color([0.0; 4])
+                        .with_size(text.size * scale)
+                }
+            };
+            section.spans.push(span.with_font(font));
+        }
+
+        sections.push((layer.copied().unwrap_or_default(), section, icons));
+    }
+
+    sections.sort_by_key(|(layer, _, _)| *layer);
+    for (_, section, icons) in &sections {
+        if !icons.is_empty() {
+            let positions = renderer.span_positions(section);
+            for (index, name) in icons {
+                let (Some(Some((x, y))), Some(icon)) =
+                    (positions.get(*index), text_icons.get(name))
+                else {
+                    continue;
+                };
+                let Some(texture) = assets.get(&icon.texture) else {
+                    continue;
+                };
+                let Some(texture_id) = texture.gpu_id() else {
+                    continue;
+                };
+
+                let size = section.spans[*index].size;
+                let uv = texture.uv(icon.region.as_ref());
+                renderer.queue_sprite(texture_id, *x, *y, size, size, uv, WHITE);
+            }
+        }
+        renderer.queue_section(section);
+    }
+}
+
+// Plain text is a single span, so `[` doesn't need escaping outside of markup
+fn rich_spans(text: &str, markup: bool) -> Vec<RichSpan> {
+    if markup {
+        markup::parse(text)
+    } else {
+        vec![RichSpan::Text {
+            text: text.to_string(),
+            color: None,
+            size: None,
+        }]
+    }
+}
+
+/// Trigger system that checks every sensor collider against every other collider
+/// and emits `TriggerEvent`s for enters, stays and exits
+pub fn trigger_system(engine: &mut Engine) {
+    let world = engine.world_mut();
+
+    // Swap the message buffers so events from two frames ago get dropped
+    world.resource_mut::<Messages<TriggerEvent>>().update();
+
+    let colliders: Vec<(Entity, Bounds, bool)> = {
+        let mut query = world.query::<(Entity, &Position, &Collider, Option<&Size>)>();
+
+        query
+            .iter(world)
+            .filter_map(|(entity, position, collider, size)| {
+                collider
+                    .bounds(*position, size)
+                    .map(|bounds| (entity, bounds, collider.is_sensor))
+            })
+            .collect()
+    };
+
+    // Brute force for now. Fine for the handful of colliders we have, needs a broadphase later.
+    let mut overlapping = HashSet::new();
+    for (sensor, sensor_bounds, is_sensor) in &colliders {
+        if !is_sensor {
+            continue;
+        }
+        for (other, other_bounds, _) in &colliders {
+            if sensor != other && sensor_bounds.intersects(other_bounds) {
+                overlapping.insert((*sensor, *other));
+            }
+        }
+    }
+
+    let previous = std::mem::replace(
+        &mut world.resource_mut::<ActiveTriggers>().pairs,
+        overlapping.clone(),
+    );
+
+    let mut messages = world.resource_mut::<Messages<TriggerEvent>>();
+    for &(sensor, other) in &overlapping {
+        let kind = if previous.contains(&(sensor, other)) {
+            TriggerKind::Stay
+        } else {
+            TriggerKind::Enter
+        };
+        messages.write(TriggerEvent {
+            kind,
+            sensor,
+            other,
+        });
+    }
 
+    // Anything that overlapped last frame but not this one has left (or was despawned)
+    for &(sensor, other) in previous.difference(&overlapping) {
+        messages.write(TriggerEvent {
+            kind: TriggerKind::Exit,
+            sensor,
+            other,
+        });
+    }
+}
+
+/// Tilemap system that draws every `Tilemap` chunk in view of the camera, building or rebuilding
+/// chunk meshes that are new or had tiles change, and freeing the meshes of tilemaps that are
+/// gone. Runs before `render_system` so tilemaps end up below everything else.
+pub fn tilemap_system(engine: &mut Engine) {
+    let world = &mut engine.world;
+    let renderer = &mut engine.renderer;
+    let assets = &engine.assets;
+    let camera = engine.camera;
+
+    // What the camera can see, in world units
+    let (view_x, view_y) = camera.screen_to_world(0.0, 0.0);
+    let (view_right, view_bottom) = camera.screen_to_world(renderer.width(), renderer.height());
+
+    world.resource_scope(|world, mut chunks: Mut<TilemapChunks>| {
+        let mut alive = HashSet::new();
+        let mut query = world.query::<(Entity, &Position, &mut Tilemap)>();
+        for (entity, position, mut tilemap) in query.iter_mut(world) {
+            alive.insert(entity);
+
+            // Like sprites, nothing is drawn until the tileset is on the GPU
+            let Some(texture) = assets.get(&tilemap.tileset().texture) else {
+                continue;
+            };
+            let Some(texture_id) = texture.gpu_id() else {
+                continue;
+            };
+            // A reloaded tileset can change size, which moves every tile's UVs
+            let texture_size = (texture.width(), texture.height());
+            if tilemap.texture_size != Some(texture_size) {
+                tilemap.mark_all_dirty();
+                tilemap.texture_size = Some(texture_size);
+            }
+
+            let (columns, rows) = tilemap.chunks();
+            let (tile_width, tile_height) = tilemap.tile_size();
+            let chunk_width = tile_width * CHUNK_SIZE as f32;
+            let chunk_height = tile_height * CHUNK_SIZE as f32;
+
+            // The map can be replaced with one of a different size, so make room for every chunk
+            // and free the meshes of chunks that are gone. A new map starts out all dirty, so
+            // slots that now hold a different chunk get rebuilt below.
+            let (layer_count, chunk_count) = (tilemap.layers().len(), (columns * rows) as usize);
+            let meshes = chunks.meshes.entry(entity).or_default();
+            for mesh in meshes.iter().skip(layer_count).flatten().flatten() {
+                renderer.remove_static_mesh(*mesh);
+            }
+            meshes.resize(layer_count, Vec::new());
+            for layer_meshes in meshes.iter_mut() {
+                for mesh in layer_meshes.iter().skip(chunk_count).flatten() {
+                    renderer.remove_static_mesh(*mesh);
+                }
+                layer_meshes.resize(chunk_count, None);
+            }
+            for (layer, layer_meshes) in meshes.iter_mut().enumerate() {
+                if !tilemap.layers()[layer].visible {
+                    continue;
+                }
+
+                for chunk_y in 0..rows {
+                    for chunk_x in 0..columns {
+                        let x = position.x + chunk_x as f32 * chunk_width;
+                        let y = position.y + chunk_y as f32 * chunk_height;
+                        if x > view_right
+                            || y > view_bottom
+                            || x + chunk_width < view_x
+                            || y + chunk_height < view_y
+                        {
+                            continue;
+                        }
+
+                        let index = tilemap.chunk_index(chunk_x, chunk_y);
+                        let mesh = match layer_meshes[index] {
+                            Some(mesh) if !tilemap.dirty[layer][index] => mesh,
+                            existing => {
+                                let (vertices, indices) =
+                                    tilemap.chunk_mesh(texture, layer, chunk_x, chunk_y);
+                                tilemap.dirty[layer][index] = false;
+                                match existing {
+                                    Some(mesh) => {
+                                        renderer.update_static_mesh(
+                                            mesh, texture_id, &vertices, &indices,
+                                        );
+                                        mesh
+                                    }
+                                    None => {
+                                        let mesh = renderer
+                                            .create_static_mesh(texture_id, &vertices, &indices);
+                                        layer_meshes[index] = Some(mesh);
+                                        mesh
+                                    }
+                                }
+                            }
+                        };
+
+                        let (x, y) = camera.world_to_screen(x, y);
+                        renderer.queue_static_mesh(mesh, x, y, camera.zoom);
+                    }
+                }
+            }
+        }
+
+        chunks.meshes.retain(|entity, layers| {
+            if alive.contains(entity) {
+                return true;
+            }
+            for mesh in layers.iter().flatten().flatten() {
+                renderer.remove_static_mesh(*mesh);
+            }
+            false
+        });
+    });
+}
+
+/// Particle system that ages, moves and spawns the particles of every `ParticleEmitter`, then
+/// draws them all through the camera. Runs after `render_system` so particles end up on top of
+/// shapes and sprites.
+pub fn particle_system(engine: &mut Engine) {
+    let delta_time = engine.time().delta_time();
+    let world = &mut engine.world;
+    let renderer = &mut engine.renderer;
+    let assets = &engine.assets;
+    let camera = engine.camera;
+
+    let mut alive: usize = world
+        .query::<&ParticleEmitter>()
+        .iter(world)
+        .map(|emitter| emitter.particle_count())
+        .sum();
+
+    // One mesh per texture, which is a single draw for everything untextured
+    let mut meshes: Vec<(TextureId, Vec<Vertex>, Vec<u32>)> = Vec::new();
+    let mut query = world.query::<(&Position, Option<&Size>, &mut ParticleEmitter)>();
+    for (position, size, mut emitter) in query.iter_mut(world) {
+        // Centered on the entity, like lights
+        let origin = match size {
+            Some(size) => (
+                position.x + size.width / 2.0,
+                position.y + size.height / 2.0,
+            ),
+            None => (position.x, position.y),
+        };
+        alive += emitter.update(delta_time, origin, MAX_PARTICLES.saturating_sub(alive));
+
+        // Particles with a texture that isn't on the GPU yet aren't drawn, like sprites
+        let (texture, uv) = match &emitter.texture {
+            Some(handle) => match assets.get(handle) {
+                Some(texture) => match texture.gpu_id() {
+                    Some(id) => (id, texture.uv(None)),
+                    None => continue,
+                },
+                None => continue,
+            },
+            None => (TextureId::WHITE, FULL_UV),
+        };
+        let index = match meshes.iter().position(|(id, _, _)| *id == texture) {
+            Some(index) => index,
+            None => {
+                meshes.push((texture, Vec::new(), Vec::new()));
+                meshes.len() - 1
+            }
+        };
+        let (_, vertices, indices) = &mut meshes[index];
+
+        let offset = match emitter.space {
+            SimulationSpace::World => (0.0, 0.0),
+            SimulationSpace::Local => origin,
+        };
+        for particle in &emitter.particles {
+            let progress = particle.progress();
+            let color = emitter.color.sample(progress);
+            let half = emitter.size.sample(progress) / 2.0;
+            let (x, y) = camera.world_to_screen(particle.x + offset.0, particle.y + offset.1);
+            let (left, top) = (x - half * camera.zoom, y - half * camera.zoom);
+            let (right, bottom) = (x + half * camera.zoom, y + half * camera.zoom);
+
+            let first = vertices.len() as u32;
+            vertices.extend([
+                Vertex::with_tex_coords(left, top, uv[0], uv[1], color),
+                Vertex::with_tex_coords(right, top, uv[2], uv[1], color),
+                Vertex::with_tex_coords(right, bottom, uv[2], uv[3], color),
+                Vertex::with_tex_coords(left, bottom, uv[0], uv[3], color),
+            ]);
+            indices.extend(QUAD_INDICES.map(|i| first + i));
+        }
+    }
+
+    for (texture, vertices, indices) in meshes {
+        if !vertices.is_empty() {
+            renderer.queue_mesh(texture, &vertices, &indices);
+        }
+    }
+}
+
+/// Lighting system that turns the renderer's lighting on when there's an `AmbientLight` or
+/// `PointLight` in the world, and queues a light for every `PointLight` and a shadow caster for
+/// every `Occluder` through the camera
+pub fn lighting_system(engine: &mut Engine) {
+    let world = &mut engine.world;
+    let renderer = &mut engine.renderer;
+    let camera = engine.camera;
+
+    let ambient = world
+        .query::<&AmbientLight>()
+        .iter(world)
+        .next()
+        .map(|ambient| ambient.color);
+
+    let mut occluders = world.query::<(&Position, Option<&Size>, &Occluder)>();
+    for (position, size, occluder) in occluders.iter(world) {
+        let points = match (occluder, size) {
+            (Occluder::Rect, Some(size)) => vec![
+                (position.x, position.y),
+                (position.x + size.width, position.y),
+                (position.x + size.width, position.y + size.height),
+                (position.x, position.y + size.height),
+            ],
+            (Occluder::Rect, None) => continue,
+            (Occluder::Polygon(points), _) => points
+                .iter()
+                .map(|&(x, y)| (position.x + x, position.y + y))
+                .collect(),
+        };
+        let points: Vec<(f32, f32)> = points
+            .into_iter()
+            .map(|(x, y)| camera.world_to_screen(x, y))
+            .collect();
+        renderer.queue_occluder(&points);
+    }
+
+    let mut lights = 0;
+    let mut query = world.query::<(&Position, Option<&Size>, &PointLight)>();
+    for (position, size, light) in query.iter(world) {
+        // Centered on the entity, like sounds
+        let (x, y) = match size {
+            Some(size) => (
+                position.x + size.width / 2.0,
+                position.y + size.height / 2.0,
+            ),
+            None => (position.x, position.y),
+        };
+        let (x, y) = camera.world_to_screen(x, y);
+        renderer.queue_light(
+            &Light::new(x, y, light.radius * camera.zoom)
+                .with_color(light.color)
+                .with_intensity(light.intensity)
+                .with_shadows(light.shadows)
+                .with_softness(light.softness * camera.zoom),
+        );
+        lights += 1;
+    }
+
+    renderer.set_ambient_light(match ambient {
+        Some(color) => Some(color),
+        None if lights > 0 => Some(AmbientLight::default().color),
+        None => None,
+    });
+}
+
+/// Spatial audio system that starts sounds for new `AudioEmitter`s, updates their volume and pan
+/// from where they are relative to the `AudioListener`, and stops sounds whose emitter is gone
+pub fn spatial_audio_system(engine: &mut Engine) {
+    let world = &mut engine.world;
+
+    // Sounds are positioned from the center of an entity, not its top left corner
+    let center = |position: &Position, size: Option<&Size>| match size {
+        Some(size) => (
+            position.x + size.width / 2.0,
+            position.y + size.height / 2.0,
+        ),
+        None => (position.x, position.y),
+    };
+
+    let listener = {
+        let mut query = world.query_filtered::<(&Position, Option<&Size>), With<AudioListener>>();
+        query
+            .iter(world)
+            .next()
+            .map(|(position, size)| center(position, size))
+    };
+
+    let emitters: Vec<(Entity, AudioEmitter, f32, f32)> = {
+        let mut query = world.query::<(Entity, &AudioEmitter, &Position, Option<&Size>)>();
         query
             .iter(world)
-            .map(|(position, size, renderable)| {
-                (
-                    position.x,
-                    position.y,
-                    size.width,
-                    size.height,
-                    renderable.color,
-                )
+            .map(|(entity, emitter, position, size)| {
+                // Without a listener everything plays as if it were right next to us
+                let (volume, pan) = match listener {
+                    Some(listener) => emitter.attenuate(center(position, size), listener),
+                    None => (emitter.volume, 0.0),
+                };
+                (entity, emitter.clone(), volume, pan)
             })
             .collect()
     };
 
-    // Now queue the rectangles with the renderer (this is only able to do rectangles for now lol)
-    let renderer = engine.renderer_mut();
-    for (x, y, width, height, color) in render_data {
-        renderer.queue_rectangle(x, y, width, height, color);
+    let audio = &engine.audio;
+    let mut active = world.resource_mut::<ActiveEmitters>();
+
+    // Stop sounds whose emitter was removed or despawned
+    active.instances.retain(|entity, instance| {
+        let alive = emitters.iter().any(|(emitter, ..)| emitter == entity);
+        if !alive {
+            audio.stop(*instance);
+        }
+        alive
+    });
+
+    for (entity, emitter, volume, pan) in emitters {
+        match active.instances.get(&entity) {
+            Some(&instance) => {
+                audio.set_volume(instance, volume);
+                audio.set_pan(instance, pan);
+            }
+            None => {
+                let instance =
+                    audio.play_with(&emitter.sound, emitter.playback_settings(volume, pan));
+                active.instances.insert(entity, instance);
+            }
+        }
+    }
+}
+
+/// Material reload system that recompiles materials registered with `Engine::watch_material`
+/// when their shader file was reloaded. A shader that doesn't compile is logged and the material
+/// keeps its last working version.
+pub fn material_reload_system(engine: &mut Engine) {
+    let Engine {
+        renderer,
+        assets,
+        watched_materials,
+        ..
+    } = engine;
+
+    for (material, handle, generation) in watched_materials.iter_mut() {
+        let Some(shader) = assets.get(handle) else {
+            continue;
+        };
+        if shader.generation == *generation {
+            continue;
+        }
+        // Don't retry the same broken file every frame
+        *generation = shader.generation;
+
+        if let Err(e) = renderer.update_material_fragment(*material, shader.code()) {
+            log::error!("{e}");
+        }
+    }
+}
+
+/// Animation system that steps every `SpriteAnimation` and points its entity's `Sprite` at the
+/// current frame, adding a `Sprite` to entities that don't have one yet
+pub fn animation_system(engine: &mut Engine) {
+    let delta_time = engine.time().delta_time();
+    let world = &mut engine.world;
+    let assets = &engine.assets;
+
+    let mut new_sprites = Vec::new();
+    let mut query = world.query::<(Entity, &mut SpriteAnimation, Option<&mut Sprite>)>();
+    for (entity, mut animation, sprite) in query.iter_mut(world) {
+        let Some(sheet) = assets.get(&animation.sheet) else {
+            continue;
+        };
+        let Some(texture) = &sheet.texture else {
+            continue;
+        };
+        let Some(frame) = animation.advance(sheet, delta_time) else {
+            continue;
+        };
+
+        let region = sheet.frames[frame].region;
+        match sprite {
+            Some(mut sprite) => {
+                if sprite.texture != *texture {
+                    sprite.texture = texture.clone();
+                }
+                sprite.region = Some(region);
+            }
+            None => new_sprites.push((entity, Sprite::new(texture.clone()).with_region(region))),
+        }
+    }
+
+    for (entity, sprite) in new_sprites {
+        world.entity_mut(entity).insert(sprite);
     }
 }
@@ -0,0 +1,291 @@
+//! Tilemaps, drawn by `tilemap_system`.
+//!
+//! The map is split into square chunks of `CHUNK_SIZE` tiles, each uploaded to the GPU as a
+//! static mesh the first time it's drawn and only rebuilt when one of its tiles changes.
+//! Chunks outside the camera's view aren't drawn at all. Tilemaps are drawn before anything
+//! else, so everything else ends up on top of them.
+
+use std::collections::HashMap;
+
+use bevy_ecs::prelude::{Component, Entity, Resource, World};
+use wgpu_renderer::{QUAD_INDICES, StaticMeshId, Vertex};
+
+use crate::{
+    assets::{Handle, Texture, TextureRegion},
+    core::render::colors::WHITE,
+};
+
+/// Width and height of a chunk, in tiles
+pub const CHUNK_SIZE: u32 = 16;
+
+/// A texture atlas of equally sized tiles, numbered from 0 left to right and then top to bottom
+#[derive(Clone, Debug)]
+pub struct Tileset {
+    pub texture: Handle<Texture>,
+    /// Size of one tile in the texture, in pixels
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Tiles per row
+    pub columns: u32,
+    /// Pixels around the edge of the texture before the first tile
+    pub margin: u32,
+    /// Pixels between tiles
+    pub spacing: u32,
+}
+
+impl Tileset {
+    pub fn new(texture: Handle<Texture>, tile_width: u32, tile_height: u32, columns: u32) -> Self {
+        Self {
+            texture,
+            tile_width,
+            tile_height,
+            columns: columns.max(1),
+            margin: 0,
+            spacing: 0,
+        }
+    }
+
+    pub fn with_margin(mut self, margin: u32) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    pub fn with_spacing(mut self, spacing: u32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Where a tile is in the texture
+    pub fn region(&self, tile: u32) -> TextureRegion {
+        let column = tile % self.columns;
+        let row = tile / self.columns;
+        TextureRegion::new(
+            (self.margin + column * (self.tile_width + self.spacing)) as f32,
+            (self.margin + row * (self.tile_height + self.spacing)) as f32,
+            self.tile_width as f32,
+            self.tile_height as f32,
+        )
+    }
+}
+
+/// One grid of tiles. Layers are drawn in order, so later ones go on top.
+#[derive(Clone, Debug)]
+pub struct TileLayer {
+    pub name: String,
+    /// Hidden layers are skipped without losing their chunks
+    pub visible: bool,
+    /// Tint multiplied with every tile, e.g. to fade a layer out
+    pub color: [f32; 4],
+    // Row by row, None for empty cells
+    tiles: Vec<Option<u32>>,
+}
+
+/// A grid of tiles from one tileset, drawn from the entity's `Position`
+#[derive(Component, Clone, Debug)]
+pub struct Tilemap {
+    width: u32,
+    height: u32,
+    tile_width: f32,
+    tile_height: f32,
+    tileset: Tileset,
+    layers: Vec<TileLayer>,
+    // One flag per chunk per layer, set when the chunk needs its mesh rebuilt
+    pub(crate) dirty: Vec<Vec<bool>>,
+    // Size of the tileset texture the chunk meshes were built for, their UVs depend on it
+    pub(crate) texture_size: Option<(u32, u32)>,
+}
+
+impl Tilemap {
+    /// An empty map with no layers. Tiles are drawn at their size in the tileset until
+    /// `with_tile_size` says otherwise.
+    pub fn new(width: u32, height: u32, tileset: Tileset) -> Self {
+        Self {
+            width,
+            height,
+            tile_width: tileset.tile_width as f32,
+            tile_height: tileset.tile_height as f32,
+            tileset,
+            layers: Vec::new(),
+            dirty: Vec::new(),
+            texture_size: None,
+        }
+    }
+
+    pub fn with_tile_size(mut self, width: f32, height: f32) -> Self {
+        self.tile_width = width;
+        self.tile_height = height;
+        self
+    }
+
+    pub fn with_layer(mut self, name: impl Into<String>) -> Self {
+        self.add_layer(name);
+        self
+    }
+
+    /// Add an empty layer on top of the others, returning its index
+    pub fn add_layer(&mut self, name: impl Into<String>) -> usize {
+        self.layers.push(TileLayer {
+            name: name.into(),
+            visible: true,
+            color: WHITE,
+            tiles: vec![None; (self.width * self.height) as usize],
+        });
+        self.dirty.push(vec![true; self.chunk_count()]);
+        self.layers.len() - 1
+    }
+
+    /// Size of the map, in tiles
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Size of a tile in the world
+    pub fn tile_size(&self) -> (f32, f32) {
+        (self.tile_width, self.tile_height)
+    }
+
+    pub fn tileset(&self) -> &Tileset {
+        &self.tileset
+    }
+
+    /// Swap the tileset, e.g. for a different season. Rebuilds every chunk.
+    pub fn set_tileset(&mut self, tileset: Tileset) {
+        self.tileset = tileset;
+        self.mark_all_dirty();
+    }
+
+    pub fn layers(&self) -> &[TileLayer] {
+        &self.layers
+    }
+
+    /// Change a layer's name, visibility or color. Use `set_tile` for its tiles.
+    pub fn layer_mut(&mut self, layer: usize) -> Option<&mut TileLayer> {
+        // The color is part of the chunks, so they have to be rebuilt in case it changes
+        if let Some(chunks) = self.dirty.get_mut(layer) {
+            chunks.fill(true);
+        }
+        self.layers.get_mut(layer)
+    }
+
+    /// Index of the first layer with this name
+    pub fn layer_index(&self, name: &str) -> Option<usize> {
+        self.layers.iter().position(|layer| layer.name == name)
+    }
+
+    /// The tile at a cell, `None` when it's empty or out of bounds
+    pub fn tile(&self, layer: usize, x: u32, y: u32) -> Option<u32> {
+        let index = self.cell_index(x, y)?;
+        self.layers.get(layer)?.tiles[index]
+    }
+
+    /// Set or clear a cell. Only the chunk it's in gets rebuilt.
+    pub fn set_tile(&mut self, layer: usize, x: u32, y: u32, tile: Option<u32>) {
+        let Some(index) = self.cell_index(x, y) else {
+            return;
+        };
+        let chunk = self.chunk_index(x / CHUNK_SIZE, y / CHUNK_SIZE);
+        if let Some(cells) = self.layers.get_mut(layer)
+            && cells.tiles[index] != tile
+        {
+            cells.tiles[index] = tile;
+            self.dirty[layer][chunk] = true;
+        }
+    }
+
+    /// Set a whole layer at once, row by row. Cells past the end of `tiles` are cleared.
+    pub fn set_tiles(&mut self, layer: usize, tiles: impl IntoIterator<Item = Option<u32>>) {
+        let size = (self.width * self.height) as usize;
+        if let Some(cells) = self.layers.get_mut(layer) {
+            cells.tiles = tiles
+                .into_iter()
+                .chain(std::iter::repeat(None))
+                .take(size)
+                .collect();
+            self.dirty[layer].fill(true);
+        }
+    }
+
+    /// Which cell a point in the world falls in, given where the map is
+    pub fn cell_at(&self, position: (f32, f32), x: f32, y: f32) -> Option<(u32, u32)> {
+        let column = ((x - position.0) / self.tile_width).floor();
+        let row = ((y - position.1) / self.tile_height).floor();
+        (column >= 0.0 && row >= 0.0 && column < self.width as f32 && row < self.height as f32)
+            .then_some((column as u32, row as u32))
+    }
+
+    /// Chunks across and down
+    pub(crate) fn chunks(&self) -> (u32, u32) {
+        (
+            self.width.div_ceil(CHUNK_SIZE),
+            self.height.div_ceil(CHUNK_SIZE),
+        )
+    }
+
+    pub(crate) fn chunk_index(&self, x: u32, y: u32) -> usize {
+        (y * self.chunks().0 + x) as usize
+    }
+
+    pub(crate) fn mark_all_dirty(&mut self) {
+        for chunks in &mut self.dirty {
+            chunks.fill(true);
+        }
+    }
+
+    fn chunk_count(&self) -> usize {
+        let (columns, rows) = self.chunks();
+        (columns * rows) as usize
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> Option<usize> {
+        (x < self.width && y < self.height).then_some((y * self.width + x) as usize)
+    }
+
+    /// Quads for one chunk of a layer, with positions relative to the chunk's top left corner
+    pub(crate) fn chunk_mesh(
+        &self,
+        texture: &Texture,
+        layer: usize,
+        chunk_x: u32,
+        chunk_y: u32,
+    ) -> (Vec<Vertex>, Vec<u32>) {
+        let layer = &self.layers[layer];
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+
+        let (start_x, start_y) = (chunk_x * CHUNK_SIZE, chunk_y * CHUNK_SIZE);
+        let end_x = (start_x + CHUNK_SIZE).min(self.width);
+        let end_y = (start_y + CHUNK_SIZE).min(self.height);
+        for y in start_y..end_y {
+            for x in start_x..end_x {
+                let Some(tile) = layer.tiles[(y * self.width + x) as usize] else {
+                    continue;
+                };
+                let uv = texture.uv(Some(&self.tileset.region(tile)));
+                let left = (x - start_x) as f32 * self.tile_width;
+                let top = (y - start_y) as f32 * self.tile_height;
+                let (right, bottom) = (left + self.tile_width, top + self.tile_height);
+
+                let first = vertices.len() as u32;
+                vertices.extend([
+                    Vertex::with_tex_coords(left, top, uv[0], uv[1], layer.color),
+                    Vertex::with_tex_coords(right, top, uv[2], uv[1], layer.color),
+                    Vertex::with_tex_coords(right, bottom, uv[2], uv[3], layer.color),
+                    Vertex::with_tex_coords(left, bottom, uv[0], uv[3], layer.color),
+                ]);
+                indices.extend(QUAD_INDICES.map(|i| first + i));
+            }
+        }
+        (vertices, indices)
+    }
+}
+
+/// GPU meshes for every tilemap's chunks, indexed by layer and then chunk
+#[derive(Resource, Default)]
+pub(crate) struct TilemapChunks {
+    pub(crate) meshes: HashMap<Entity, Vec<Vec<Option<StaticMeshId>>>>,
+}
+
+/// Registers the resources the tilemap system needs
+pub(crate) fn init(world: &mut World) {
+    world.init_resource::<TilemapChunks>();
+}
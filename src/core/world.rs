@@ -2,7 +2,13 @@
 
 use bevy_ecs::component::Component;
 
-use crate::core::render::colors::{BLACK, BLUE, GREEN, RED, WHITE};
+use wgpu_renderer::{HorizontalAlign, MaterialId, SliceFill, VerticalAlign};
+
+use crate::{
+    assets::{Aseprite, Font, Handle, Texture, TextureRegion},
+    core::render::colors::{BLACK, BLUE, GREEN, RED, WHITE},
+    text::BitmapFont,
+};
 
 /// A 2D position component
 #[derive(Component, Clone, Copy, Debug, PartialEq)]
@@ -89,6 +95,307 @@ impl Renderable {
     }
 }
 
+/// A textured quad drawn at the entity's `Position`, stretched to its `Size`
+#[derive(Component, Clone, Debug)]
+pub struct Sprite {
+    pub texture: Handle<Texture>,
+    /// Part of the texture to draw, `None` draws the whole thing
+    pub region: Option<TextureRegion>,
+    /// Tint multiplied with the texture, white leaves it untouched
+    pub color: [f32; 4],
+}
+
+impl Sprite {
+    pub fn new(texture: Handle<Texture>) -> Self {
+        Self {
+            texture,
+            region: None,
+            color: WHITE,
+        }
+    }
+
+    pub fn with_region(mut self, region: TextureRegion) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+/// A sprite cut into nine parts by its borders, stretched to the entity's `Size` without
+/// stretching the corners. For panels, buttons and dialog boxes.
+#[derive(Component, Clone, Debug)]
+pub struct NineSliceSprite {
+    pub texture: Handle<Texture>,
+    /// Part of the texture to draw, `None` uses the whole thing
+    pub region: Option<TextureRegion>,
+    /// Border widths in texture pixels: left, top, right, bottom
+    pub insets: [f32; 4],
+    /// World units per texture pixel for the borders, e.g. 4 for chunky pixel art
+    pub scale: f32,
+    pub edges: SliceFill,
+    pub center: SliceFill,
+    pub color: [f32; 4],
+}
+
+impl NineSliceSprite {
+    pub fn new(texture: Handle<Texture>, insets: [f32; 4]) -> Self {
+        Self {
+            texture,
+            region: None,
+            insets,
+            scale: 1.0,
+            edges: SliceFill::Stretch,
+            center: SliceFill::Stretch,
+            color: WHITE,
+        }
+    }
+
+    /// The same border width on every side
+    pub fn uniform(texture: Handle<Texture>, border: f32) -> Self {
+        Self::new(texture, [border; 4])
+    }
+
+    /// A slice with a nine-slice center from an Aseprite sheet, on one of its frames. `None`
+    /// until the sheet is loaded or if the slice has no center.
+    pub fn from_aseprite(sheet: &Aseprite, slice: &str, frame: usize) -> Option<Self> {
+        let region = sheet.slice_region(slice, frame)?;
+        let key = sheet.slice(slice)?.key(frame)?;
+        let center = key.center?;
+        Some(
+            Self::new(
+                sheet.texture.clone()?,
+                [
+                    center.x,
+                    center.y,
+                    key.bounds.width - center.x - center.width,
+                    key.bounds.height - center.y - center.height,
+                ],
+            )
+            .with_region(region),
+        )
+    }
+
+    pub fn with_region(mut self, region: TextureRegion) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Tile the edges and center instead of stretching them
+    pub fn tiled(mut self) -> Self {
+        self.edges = SliceFill::Tile;
+        self.center = SliceFill::Tile;
+        self
+    }
+
+    pub fn with_edges(mut self, edges: SliceFill) -> Self {
+        self.edges = edges;
+        self
+    }
+
+    pub fn with_center(mut self, center: SliceFill) -> Self {
+        self.center = center;
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+/// Draw order. Higher layers are drawn on top, entities without one are on layer 0.
+///
+/// Text is always drawn over shapes and sprites, layers only order text among itself.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Layer(pub i32);
+
+/// Draws the entity's rectangle, sprite, nine-slice sprite or bitmap text with a custom shader,
+/// made with `Renderer::create_material`
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Material(pub MaterialId);
+
+/// Whether something moves with the camera
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextSpace {
+    /// Fixed on screen, for HUDs
+    #[default]
+    Screen,
+    /// Part of the world, moves and zooms with the camera
+    World,
+}
+
+/// Text drawn at the entity's `Position` every frame by the engine
+#[derive(Component, Clone, Debug)]
+pub struct Text {
+    pub text: String,
+    pub size: f32,
+    pub color: [f32; 4],
+    /// `None` uses the built-in font
+    pub font: Option<Handle<Font>>,
+    pub h_align: HorizontalAlign,
+    pub v_align: VerticalAlign,
+    pub wrap_width: Option<f32>,
+    pub space: TextSpace,
+    pub visible: bool,
+    /// Parse `text` as markup, see `text::markup`
+    pub markup: bool,
+}
+
+impl Text {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            size: 16.0,
+            color: WHITE,
+            font: None,
+            h_align: HorizontalAlign::Left,
+            v_align: VerticalAlign::Top,
+            wrap_width: None,
+            space: TextSpace::Screen,
+            visible: true,
+            markup: false,
+        }
+    }
+
+    /// Text with inline `[color=..]`, `[size=..]` and `[icon=..]` tags
+    pub fn rich(markup: impl Into<String>) -> Self {
+        Self {
+            markup: true,
+            ..Self::new(markup)
+        }
+    }
+
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_font(mut self, font: Handle<Font>) -> Self {
+        self.font = Some(font);
+        self
+    }
+
+    /// How the text sits around its position, see `TextSection::with_align`
+    pub fn with_align(mut self, h_align: HorizontalAlign, v_align: VerticalAlign) -> Self {
+        self.h_align = h_align;
+        self.v_align = v_align;
+        self
+    }
+
+    /// Center the text on its position
+    pub fn centered(self) -> Self {
+        self.with_align(HorizontalAlign::Center, VerticalAlign::Center)
+    }
+
+    pub fn with_wrap(mut self, max_width: f32) -> Self {
+        self.wrap_width = Some(max_width);
+        self
+    }
+
+    pub fn in_world(mut self) -> Self {
+        self.space = TextSpace::World;
+        self
+    }
+
+    pub fn hidden(mut self) -> Self {
+        self.visible = false;
+        self
+    }
+
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+    }
+}
+
+/// Text drawn with a `BitmapFont` at the entity's `Position`, through the sprite pipeline.
+/// Unlike `Text`, it's sorted by `Layer` together with shapes and sprites.
+#[derive(Component, Clone, Debug)]
+pub struct BitmapText {
+    pub font: Handle<BitmapFont>,
+    pub text: String,
+    /// Whole number multiplier of the font's pixel size, so pixels stay square
+    pub scale: u32,
+    pub color: [f32; 4],
+    pub h_align: HorizontalAlign,
+    pub v_align: VerticalAlign,
+    pub space: TextSpace,
+    pub visible: bool,
+    /// Parse `text` as markup, see `text::markup`
+    pub markup: bool,
+}
+
+impl BitmapText {
+    pub fn new(font: Handle<BitmapFont>, text: impl Into<String>) -> Self {
+        Self {
+            font,
+            text: text.into(),
+            scale: 1,
+            color: WHITE,
+            h_align: HorizontalAlign::Left,
+            v_align: VerticalAlign::Top,
+            space: TextSpace::Screen,
+            visible: true,
+            markup: false,
+        }
+    }
+
+    /// Text with inline `[color=..]`, `[size=..]` and `[icon=..]` tags
+    pub fn rich(font: Handle<BitmapFont>, markup: impl Into<String>) -> Self {
+        Self {
+            markup: true,
+            ..Self::new(font, markup)
+        }
+    }
+
+    pub fn with_scale(mut self, scale: u32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_align(mut self, h_align: HorizontalAlign, v_align: VerticalAlign) -> Self {
+        self.h_align = h_align;
+        self.v_align = v_align;
+        self
+    }
+
+    pub fn centered(self) -> Self {
+        self.with_align(HorizontalAlign::Center, VerticalAlign::Center)
+    }
+
+    pub fn in_world(mut self) -> Self {
+        self.space = TextSpace::World;
+        self
+    }
+
+    pub fn hidden(mut self) -> Self {
+        self.visible = false;
+        self
+    }
+
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+    }
+}
+
 /// A collision/bounds component
 #[derive(Component, Clone, Copy, Debug)]
 pub struct Bounds {
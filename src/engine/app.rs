@@ -54,13 +54,36 @@ impl<G: Game> ApplicationHandler for EngineApp<G> {
             window.request_redraw();
             self.window = Some(window);
 
+            let mut world = bevy_ecs::world::World::new();
+            crate::core::collision::init(&mut world);
+            crate::audio::spatial::init(&mut world);
+            crate::core::tilemap::init(&mut world);
+
+            let audio = match self.config.audio_backend.take() {
+                Some(backend) => crate::audio::Audio::with_backend(backend),
+                None => crate::audio::Audio::with_default_backend(),
+            };
+
             let mut engine = Engine {
                 renderer,
-                world: bevy_ecs::world::World::new(),
+                world,
                 input: crate::core::input::Input::new(),
                 time: crate::core::time::Time::new(),
+                audio,
+                assets: crate::assets::Assets::new(self.config.asset_root.clone()),
+                camera: crate::core::camera::Camera::new(),
+                text_icons: crate::text::TextIcons::new(),
+                watched_materials: Vec::new(),
             };
 
+            // Only a development aid, so a failing watcher shouldn't stop the game
+            #[cfg(feature = "hot-reload")]
+            if let Some(mode) = self.config.hot_reload
+                && let Err(e) = engine.assets.watch(mode)
+            {
+                log::error!("{e}");
+            }
+
             // Initialize the game once
             self.game.init(&mut engine);
             self.engine = Some(engine);
@@ -126,9 +149,33 @@ impl<G: Game> ApplicationHandler for EngineApp<G> {
                     // Call user's update logic - MAKE THIS ECS BASED IN THE FUTURE
                     self.game.update(engine, delta_time);
 
+                    // Detect sensor overlaps so the game can react to them next frame
+                    crate::core::systems::trigger_system(engine);
+
+                    // Move positional sounds along with their entities
+                    crate::core::systems::spatial_audio_system(engine);
+
+                    // Upload newly loaded textures before anything tries to draw them
+                    engine.assets.update(&mut engine.renderer);
+
+                    // Rebuild materials whose shader files changed
+                    crate::core::systems::material_reload_system(engine);
+
+                    // Show the current frame of every sprite animation
+                    crate::core::systems::animation_system(engine);
+
+                    // Queue lights before the frame is drawn
+                    crate::core::systems::lighting_system(engine);
+
+                    // Tilemaps go below everything else
+                    crate::core::systems::tilemap_system(engine);
+
                     // Run built-in rendering system to draw ECS entities
                     crate::core::systems::render_system(engine);
 
+                    // Particles go on top of everything the render system drew
+                    crate::core::systems::particle_system(engine);
+
                     // Render
                     let _ = engine.renderer_mut().render_frame();
                 }
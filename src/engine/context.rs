@@ -1,8 +1,13 @@
-use bevy_ecs::world::World;
-use wgpu_renderer::Renderer;
+use bevy_ecs::{message::Messages, world::World};
+use wgpu_renderer::{MaterialId, PostProcess, Renderer};
 use winit::keyboard::KeyCode;
 
-use crate::core::{input::Input, time::Time};
+use crate::{
+    assets::{Assets, Handle, Shader},
+    audio::Audio,
+    core::{camera::Camera, collision::TriggerEvent, input::Input, time::Time},
+    text::TextIcons,
+};
 
 // Public facing engine
 pub struct Engine {
@@ -10,6 +15,12 @@ pub struct Engine {
     pub(crate) world: World,
     pub(crate) input: Input<KeyCode>,
     pub(crate) time: Time,
+    pub(crate) audio: Audio,
+    pub(crate) assets: Assets,
+    pub(crate) camera: Camera,
+    pub(crate) text_icons: TextIcons,
+    // Materials rebuilt when their shader file changes, with the shader generation they're on
+    pub(crate) watched_materials: Vec<(MaterialId, Handle<Shader>, u32)>,
 }
 
 impl Engine {
@@ -44,4 +55,66 @@ impl Engine {
     pub(crate) fn time_mut(&mut self) -> &mut Time {
         &mut self.time
     }
+
+    pub fn audio(&self) -> &Audio {
+        &self.audio
+    }
+
+    pub fn audio_mut(&mut self) -> &mut Audio {
+        &mut self.audio
+    }
+
+    pub fn assets(&self) -> &Assets {
+        &self.assets
+    }
+
+    pub fn assets_mut(&mut self) -> &mut Assets {
+        &mut self.assets
+    }
+
+    /// Fullscreen effects like bloom and vignette, applied to every frame in order
+    pub fn post_process(&self) -> &PostProcess {
+        self.renderer.post_process()
+    }
+
+    pub fn post_process_mut(&mut self) -> &mut PostProcess {
+        self.renderer.post_process_mut()
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    pub fn text_icons(&self) -> &TextIcons {
+        &self.text_icons
+    }
+
+    /// Register icons for `[icon=name]` in rich text
+    pub fn text_icons_mut(&mut self) -> &mut TextIcons {
+        &mut self.text_icons
+    }
+
+    /// Recompile `material` with `shader` as its fragment shader whenever the file is reloaded,
+    /// keeping the material's vertex shader. If the new version doesn't compile, the error is
+    /// logged and the material keeps drawing with the old one.
+    pub fn watch_material(&mut self, material: MaterialId, shader: Handle<Shader>) {
+        let generation = self
+            .assets
+            .get(&shader)
+            .map_or(0, |shader| shader.generation);
+        self.watched_materials
+            .retain(|(watched, _, _)| *watched != material);
+        self.watched_materials.push((material, shader, generation));
+    }
+
+    /// Trigger events emitted by the engine's trigger system at the end of the previous frame
+    pub fn trigger_events(&self) -> impl Iterator<Item = &TriggerEvent> {
+        self.world
+            .resource::<Messages<TriggerEvent>>()
+            .iter_current_update_messages()
+    }
 }
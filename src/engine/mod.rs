@@ -4,13 +4,24 @@ pub mod game;
 
 pub use context::Engine;
 pub use game::Game;
+use std::path::PathBuf;
+
 use winit::event_loop::EventLoop;
 
+use crate::audio::AudioBackend;
+
 // Builder-style configuration struct
 pub struct EngineConfig {
     pub window_title: String,
     pub window_width: u32,
     pub window_height: u32,
+    /// Audio output to use instead of the system's default device
+    pub audio_backend: Option<Box<dyn AudioBackend>>,
+    /// Directory asset paths are resolved against
+    pub asset_root: PathBuf,
+    /// Watch the asset root and reload changed files
+    #[cfg(feature = "hot-reload")]
+    pub hot_reload: Option<crate::assets::HotReloadMode>,
     // Add more properties as needed!!!!!
 }
 
@@ -20,6 +31,10 @@ impl Default for EngineConfig {
             window_title: "Wakey 2D Engine".to_string(),
             window_width: 800,
             window_height: 600,
+            audio_backend: None,
+            asset_root: PathBuf::from("."),
+            #[cfg(feature = "hot-reload")]
+            hot_reload: None,
         }
     }
 }
@@ -39,6 +54,22 @@ impl EngineConfig {
         self.window_height = height;
         self
     }
+
+    pub fn with_asset_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.asset_root = root.into();
+        self
+    }
+
+    #[cfg(feature = "hot-reload")]
+    pub fn with_hot_reload(mut self, mode: crate::assets::HotReloadMode) -> Self {
+        self.hot_reload = Some(mode);
+        self
+    }
+
+    pub fn with_audio_backend(mut self, backend: impl AudioBackend + 'static) -> Self {
+        self.audio_backend = Some(Box::new(backend));
+        self
+    }
 }
 
 /// Entry point for running the engine with a user-defined game
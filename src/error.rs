@@ -1,2 +1,33 @@
-// Unused, I just used generic heap errors for now since time is limited :(
-// I will 100% be fleshing out this engine more in the near future and I plan to implement real error types ASAP.
+//! Engine error types. These are slowly replacing the generic boxed errors.
+
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum AudioError {
+    #[error("Failed to read audio file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to decode audio: {0}")]
+    DecodeError(String),
+    #[error("Unsupported audio format: {0}")]
+    UnsupportedFormat(String),
+    #[error("No audio output device available")]
+    NoOutputDevice,
+    #[error("Audio backend error: {0}")]
+    BackendError(String),
+}
+
+#[derive(Error, Debug)]
+pub enum AssetError {
+    #[error("Failed to read asset {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to decode asset {path}: {message}")]
+    DecodeError { path: PathBuf, message: String },
+    #[error("Failed to watch assets for changes: {0}")]
+    Watch(String),
+}
@@ -0,0 +1,781 @@
+//! Projects from the LDtk editor (`.ldtk`), including ones with multiple worlds or levels saved
+//! in separate `.ldtkl` files. Editing a level file reloads the project.
+//!
+//! Load the project through `Assets`, which also loads its tileset images. Levels are then
+//! spawned one at a time with `LdtkSpawner::spawn_level`, at their position in the world:
+//!
+//! - Tile and auto-layers become one `Tilemap` each. Cells that auto-layer rules stack several
+//!   tiles on are split over extra layers of that tilemap.
+//! - Int-grid cells become solid colliders, merged into as few rectangles as possible. Every
+//!   value but 0 is solid unless `LdtkSpawner::solid_values` says otherwise for that layer.
+//! - Entities get a `Position`, `Size`, a `Sprite` if they have a tile, and then whatever the
+//!   function registered for their identifier adds.
+//!
+//! `LdtkStreamer` keeps the level around a point and its neighbours spawned, despawning the
+//! rest as it moves. Tiles are drawn in the order their tilemaps were spawned, so layers of a
+//! level stack correctly but levels shouldn't overlap. Flipped tiles are drawn the right way
+//! round, and tile positions are snapped to their layer's grid.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use bevy_ecs::{entity::Entity, world::EntityWorldMut};
+use serde_json::Value;
+
+use crate::{
+    assets::{Assets, Handle, Texture, TextureRegion},
+    core::{
+        collision::Collider,
+        render::colors,
+        tilemap::{Tilemap, Tileset},
+        world::{Position, Size, Sprite},
+    },
+    engine::Engine,
+    level::{Properties, Property},
+};
+
+/// A project loaded from an `.ldtk` file
+#[derive(Clone, Debug)]
+pub struct LdtkProject {
+    /// Projects that don't use multiple worlds have a single unnamed one
+    pub worlds: Vec<LdtkWorld>,
+    pub tilesets: Vec<LdtkTileset>,
+}
+
+#[derive(Clone, Debug)]
+pub struct LdtkWorld {
+    pub identifier: String,
+    pub iid: String,
+    pub levels: Vec<LdtkLevel>,
+}
+
+#[derive(Clone, Debug)]
+pub struct LdtkTileset {
+    pub uid: i64,
+    pub identifier: String,
+    /// Relative to the project file. `None` for LDtk's built-in icons, which aren't supported.
+    pub image: Option<PathBuf>,
+    pub tile_size: u32,
+    pub columns: u32,
+    pub padding: u32,
+    pub spacing: u32,
+    /// Loaded along with the project
+    pub texture: Option<Handle<Texture>>,
+}
+
+impl LdtkTileset {
+    pub fn tileset(&self) -> Option<Tileset> {
+        Some(
+            Tileset::new(
+                self.texture.clone()?,
+                self.tile_size,
+                self.tile_size,
+                self.columns,
+            )
+            .with_margin(self.padding)
+            .with_spacing(self.spacing),
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LdtkLevel {
+    pub identifier: String,
+    pub iid: String,
+    /// Top left corner in the world, in pixels. Worked out from the order of the levels for
+    /// linear world layouts.
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub background: [f32; 4],
+    pub fields: Properties,
+    /// Bottom to top
+    pub layers: Vec<LdtkLayer>,
+    pub neighbours: Vec<LdtkNeighbour>,
+    /// The `.ldtkl` file relative to the project for levels saved separately
+    pub file: Option<PathBuf>,
+}
+
+impl LdtkLevel {
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LdtkNeighbour {
+    pub iid: String,
+    /// As LDtk writes it: `n`, `s`, `e`, `w`, the corners like `ne`, `o` for overlapping, or
+    /// `<` and `>` for levels below and above this one
+    pub direction: String,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LdtkLayerKind {
+    IntGrid,
+    Entities,
+    Tiles,
+    AutoLayer,
+}
+
+#[derive(Clone, Debug)]
+pub struct LdtkLayer {
+    pub identifier: String,
+    pub kind: LdtkLayerKind,
+    pub visible: bool,
+    pub opacity: f32,
+    /// Size of a cell in pixels
+    pub grid_size: u32,
+    /// Size in cells
+    pub width: u32,
+    pub height: u32,
+    /// Pixels from the level's top left corner
+    pub offset: (f32, f32),
+    /// Index into `LdtkProject::tilesets`
+    pub tileset: Option<usize>,
+    /// Int-grid values row by row, 0 for empty cells
+    pub int_grid: Vec<i32>,
+    /// Placed and auto-layer tiles, in the order they're drawn
+    pub tiles: Vec<LdtkTile>,
+    pub entities: Vec<LdtkEntity>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LdtkTile {
+    /// Top left corner relative to the layer, in pixels
+    pub x: f32,
+    pub y: f32,
+    /// Index in the layer's tileset
+    pub tile: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct LdtkEntity {
+    pub identifier: String,
+    pub iid: String,
+    /// Top left corner in the world, in pixels, with the pivot already taken out
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub tags: Vec<String>,
+    /// Tileset index and region of the entity's tile, if it has one
+    pub tile: Option<(usize, TextureRegion)>,
+    pub fields: Properties,
+}
+
+impl LdtkProject {
+    /// Parse a project. Levels saved in separate files are read from next to `path`.
+    pub fn parse(source: &str, path: &Path) -> Result<Self, String> {
+        let directory = path.parent().unwrap_or(Path::new(""));
+        let root: Value = serde_json::from_str(source).map_err(|e| e.to_string())?;
+
+        let tilesets: Vec<LdtkTileset> = array(root.get("defs"), "tilesets")
+            .map(|tileset| LdtkTileset {
+                uid: int(tileset, "uid"),
+                identifier: string(tileset, "identifier"),
+                image: tileset
+                    .get("relPath")
+                    .and_then(Value::as_str)
+                    .map(PathBuf::from),
+                tile_size: int(tileset, "tileGridSize").max(1) as u32,
+                columns: int(tileset, "__cWid").max(1) as u32,
+                padding: int(tileset, "padding") as u32,
+                spacing: int(tileset, "spacing") as u32,
+                texture: None,
+            })
+            .collect();
+
+        // Level fields don't have a layer to take the grid size from
+        let default_grid = int(&root, "defaultGridSize").max(1) as u32;
+
+        // Multi-world projects list their worlds, the rest keep their levels at the root
+        let world_values: Vec<&Value> = match root.get("worlds").and_then(Value::as_array) {
+            Some(worlds) if !worlds.is_empty() => worlds.iter().collect(),
+            _ => vec![&root],
+        };
+
+        let mut worlds = Vec::new();
+        for world in world_values {
+            let layout = world
+                .get("worldLayout")
+                .and_then(Value::as_str)
+                .unwrap_or("Free");
+            let mut levels = Vec::new();
+            let mut next = (0.0, 0.0);
+            for level in array(Some(world), "levels") {
+                let external = match level.get("externalRelPath").and_then(Value::as_str) {
+                    Some(file) if level.get("layerInstances").is_none_or(Value::is_null) => {
+                        let path = directory.join(file);
+                        let text = std::fs::read_to_string(&path)
+                            .map_err(|e| format!("can't read level {}: {e}", path.display()))?;
+                        let value = serde_json::from_str::<Value>(&text)
+                            .map_err(|e| format!("invalid level {}: {e}", path.display()))?;
+                        Some((PathBuf::from(file), value))
+                    }
+                    _ => None,
+                };
+                let (external_file, level) = match external {
+                    Some((file, ref value)) => (Some(file), value),
+                    None => (None, level),
+                };
+
+                let (width, height) = (float(level, "pxWid"), float(level, "pxHei"));
+                // Linear layouts leave the position at -1 and line levels up in order instead
+                let position = match layout {
+                    "LinearHorizontal" => {
+                        let position = next;
+                        next.0 += width;
+                        position
+                    }
+                    "LinearVertical" => {
+                        let position = next;
+                        next.1 += height;
+                        position
+                    }
+                    _ => (float(level, "worldX"), float(level, "worldY")),
+                };
+                let mut level =
+                    parse_level(level, position, (width, height), default_grid, &tilesets)?;
+                level.file = external_file;
+                levels.push(level);
+            }
+
+            worlds.push(LdtkWorld {
+                identifier: string(world, "identifier"),
+                iid: string(world, "iid"),
+                levels,
+            });
+        }
+
+        Ok(Self { worlds, tilesets })
+    }
+
+    /// Every level in every world
+    pub fn levels(&self) -> impl Iterator<Item = &LdtkLevel> {
+        self.worlds.iter().flat_map(|world| &world.levels)
+    }
+
+    pub fn level(&self, iid: &str) -> Option<&LdtkLevel> {
+        self.levels().find(|level| level.iid == iid)
+    }
+
+    pub fn level_by_identifier(&self, identifier: &str) -> Option<&LdtkLevel> {
+        self.levels().find(|level| level.identifier == identifier)
+    }
+
+    // Tileset images are relative to the project
+    pub(crate) fn load_textures(&mut self, assets: &mut Assets, path: &Path) {
+        let directory = path.parent().unwrap_or(Path::new(""));
+        for tileset in &mut self.tilesets {
+            tileset.texture = tileset
+                .image
+                .as_ref()
+                .map(|image| assets.load_async(directory.join(image)));
+        }
+    }
+}
+
+impl LdtkWorld {
+    /// The level a point in the world falls in
+    pub fn level_at(&self, x: f32, y: f32) -> Option<&LdtkLevel> {
+        self.levels.iter().find(|level| level.contains(x, y))
+    }
+}
+
+/// Everything `LdtkSpawner::spawn_level` created for one level
+#[derive(Clone, Debug, Default)]
+pub struct SpawnedLevel {
+    pub iid: String,
+    /// Tilemaps, colliders and entities
+    pub entities: Vec<Entity>,
+    /// LDtk entities by iid, for following entity reference fields
+    pub by_iid: HashMap<String, Entity>,
+}
+
+impl SpawnedLevel {
+    /// Despawn everything in the level that's still around
+    pub fn despawn(self, engine: &mut Engine) {
+        for entity in self.entities {
+            if let Ok(entity) = engine.world.get_entity_mut(entity) {
+                entity.despawn();
+            }
+        }
+    }
+}
+
+type EntitySpawner = Box<dyn Fn(&mut EntityWorldMut, &LdtkEntity) + Send + Sync>;
+
+/// Turns LDtk levels into entities, with game specific components added by entity identifier
+#[derive(Default)]
+pub struct LdtkSpawner {
+    spawners: HashMap<String, EntitySpawner>,
+    // Int-grid values that get colliders, by layer. Layers not in here collide on everything.
+    solid: HashMap<String, HashSet<i32>>,
+}
+
+impl LdtkSpawner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `spawn` for every entity with this identifier, after its position and size have
+    /// been added
+    pub fn register(
+        &mut self,
+        identifier: impl Into<String>,
+        spawn: impl Fn(&mut EntityWorldMut, &LdtkEntity) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.spawners.insert(identifier.into(), Box::new(spawn));
+        self
+    }
+
+    /// Only give these values of an int-grid layer colliders, e.g. walls but not water.
+    /// An empty list turns the layer's collision off.
+    pub fn solid_values(
+        &mut self,
+        layer: impl Into<String>,
+        values: impl IntoIterator<Item = i32>,
+    ) -> &mut Self {
+        self.solid
+            .insert(layer.into(), values.into_iter().collect());
+        self
+    }
+
+    /// Spawn a level at its place in the world. Returns `None` until the project has loaded or
+    /// if there's no level with this iid. Tiles show up once their tileset textures are ready.
+    pub fn spawn_level(
+        &self,
+        engine: &mut Engine,
+        project: &Handle<LdtkProject>,
+        iid: &str,
+    ) -> Option<SpawnedLevel> {
+        let project = engine.assets.get(project)?;
+        let level = project.level(iid)?;
+        let world = &mut engine.world;
+        let mut spawned = SpawnedLevel {
+            iid: iid.to_string(),
+            ..Default::default()
+        };
+
+        for layer in &level.layers {
+            let origin = (level.x + layer.offset.0, level.y + layer.offset.1);
+
+            if let Some(tilemap) = layer_tilemap(project, layer) {
+                spawned.entities.push(
+                    world
+                        .spawn((Position::new(origin.0, origin.1), tilemap))
+                        .id(),
+                );
+            }
+
+            if layer.kind == LdtkLayerKind::IntGrid {
+                let solid = self.solid.get(&layer.identifier);
+                let cells: Vec<bool> = layer
+                    .int_grid
+                    .iter()
+                    .map(|&value| value != 0 && solid.is_none_or(|solid| solid.contains(&value)))
+                    .collect();
+                let grid = layer.grid_size as f32;
+                for (x, y, width, height) in merge_cells(&cells, layer.width) {
+                    spawned.entities.push(
+                        world
+                            .spawn((
+                                Position::new(
+                                    origin.0 + x as f32 * grid,
+                                    origin.1 + y as f32 * grid,
+                                ),
+                                Size::new(width as f32 * grid, height as f32 * grid),
+                                Collider::new(),
+                            ))
+                            .id(),
+                    );
+                }
+            }
+
+            for entity in &layer.entities {
+                let mut spawned_entity = world.spawn(Position::new(entity.x, entity.y));
+                if entity.width > 0.0 && entity.height > 0.0 {
+                    spawned_entity.insert(Size::new(entity.width, entity.height));
+                }
+                if let Some((tileset, region)) = entity.tile
+                    && let Some(texture) = &project.tilesets[tileset].texture
+                {
+                    spawned_entity.insert(Sprite::new(texture.clone()).with_region(region));
+                }
+                if let Some(spawn) = self.spawners.get(&entity.identifier) {
+                    spawn(&mut spawned_entity, entity);
+                }
+                spawned.entities.push(spawned_entity.id());
+                spawned
+                    .by_iid
+                    .insert(entity.iid.clone(), spawned_entity.id());
+            }
+        }
+
+        Some(spawned)
+    }
+}
+
+/// Keeps one level and its neighbours spawned, following a point like the player or camera
+#[derive(Default)]
+pub struct LdtkStreamer {
+    world: usize,
+    current: Option<String>,
+    loaded: HashMap<String, SpawnedLevel>,
+}
+
+impl LdtkStreamer {
+    /// Streams levels from the project's first world
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Streams levels from another world of a multi-world project
+    pub fn with_world(mut self, world: usize) -> Self {
+        self.world = world;
+        self
+    }
+
+    /// Iid of the level `focus` was last in
+    pub fn current(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    pub fn loaded(&self) -> impl Iterator<Item = &SpawnedLevel> {
+        self.loaded.values()
+    }
+
+    /// Spawn the level `focus` is in and its neighbours, and despawn any others. Nothing
+    /// changes while `focus` is outside every level, e.g. in a gap between two of them.
+    pub fn update(
+        &mut self,
+        engine: &mut Engine,
+        spawner: &LdtkSpawner,
+        project: &Handle<LdtkProject>,
+        focus: (f32, f32),
+    ) {
+        let Some(wanted) = engine.assets.get(project).and_then(|project| {
+            let level = project.worlds.get(self.world)?.level_at(focus.0, focus.1)?;
+            let mut wanted = vec![level.iid.clone()];
+            wanted.extend(
+                level
+                    .neighbours
+                    .iter()
+                    .map(|neighbour| neighbour.iid.clone()),
+            );
+            Some(wanted)
+        }) else {
+            return;
+        };
+        if self.current.as_ref() == wanted.first() {
+            return;
+        }
+        self.current = wanted.first().cloned();
+
+        let stale: Vec<String> = self
+            .loaded
+            .keys()
+            .filter(|iid| !wanted.contains(iid))
+            .cloned()
+            .collect();
+        for iid in stale {
+            if let Some(level) = self.loaded.remove(&iid) {
+                level.despawn(engine);
+            }
+        }
+        for iid in wanted {
+            if !self.loaded.contains_key(&iid)
+                && let Some(level) = spawner.spawn_level(engine, project, &iid)
+            {
+                self.loaded.insert(iid, level);
+            }
+        }
+    }
+
+    /// Despawn every level, e.g. before switching worlds
+    pub fn clear(&mut self, engine: &mut Engine) {
+        self.current = None;
+        for (_, level) in self.loaded.drain() {
+            level.despawn(engine);
+        }
+    }
+}
+
+/// A tilemap of a layer's tiles, with extra layers where tiles stack on the same cell
+fn layer_tilemap(project: &LdtkProject, layer: &LdtkLayer) -> Option<Tilemap> {
+    if layer.tiles.is_empty() {
+        return None;
+    }
+    let tileset = project.tilesets.get(layer.tileset?)?.tileset()?;
+    let grid = layer.grid_size.max(1) as f32;
+    let mut tilemap = Tilemap::new(layer.width, layer.height, tileset).with_tile_size(grid, grid);
+
+    // How many tiles each cell has so far, which is also the layer the next one goes on
+    let mut depth = vec![0; (layer.width * layer.height) as usize];
+    for tile in &layer.tiles {
+        let (x, y) = ((tile.x / grid) as u32, (tile.y / grid) as u32);
+        if x >= layer.width || y >= layer.height {
+            continue;
+        }
+        let cell = &mut depth[(y * layer.width + x) as usize];
+        if *cell == tilemap.layers().len() {
+            let index = tilemap.add_layer(format!("{} {}", layer.identifier, *cell));
+            if let Some(tile_layer) = tilemap.layer_mut(index) {
+                tile_layer.visible = layer.visible;
+                tile_layer.color[3] = layer.opacity;
+            }
+        }
+        tilemap.set_tile(*cell, x, y, Some(tile.tile));
+        *cell += 1;
+    }
+    Some(tilemap)
+}
+
+/// Solid cells merged into rectangles of `(x, y, width, height)` in cells. Runs in each row
+/// are extended down while the rows below have a run with the same ends.
+fn merge_cells(cells: &[bool], width: u32) -> Vec<(u32, u32, u32, u32)> {
+    let mut done = Vec::new();
+    // Rectangles still growing, by the columns they span
+    let mut open: HashMap<(u32, u32), (u32, u32)> = HashMap::new();
+    for (y, row) in cells.chunks(width.max(1) as usize).enumerate() {
+        let y = y as u32;
+        let mut runs = Vec::new();
+        let mut x = 0;
+        while x < row.len() {
+            if !row[x] {
+                x += 1;
+                continue;
+            }
+            let start = x;
+            while x < row.len() && row[x] {
+                x += 1;
+            }
+            runs.push((start as u32, x as u32));
+        }
+
+        let mut next = HashMap::new();
+        for run in runs {
+            let (top, height) = open.remove(&run).unwrap_or((y, 0));
+            next.insert(run, (top, height + 1));
+        }
+        done.extend(
+            open.drain()
+                .map(|((start, end), (top, height))| (start, top, end - start, height)),
+        );
+        open = next;
+    }
+    done.extend(
+        open.drain()
+            .map(|((start, end), (top, height))| (start, top, end - start, height)),
+    );
+    done
+}
+
+fn parse_level(
+    level: &Value,
+    (x, y): (f32, f32),
+    (width, height): (f32, f32),
+    default_grid: u32,
+    tilesets: &[LdtkTileset],
+) -> Result<LdtkLevel, String> {
+    let mut layers = Vec::new();
+    // LDtk lists layers top to bottom
+    for layer in array(Some(level), "layerInstances").rev() {
+        let kind = match layer.get("__type").and_then(Value::as_str) {
+            Some("IntGrid") => LdtkLayerKind::IntGrid,
+            Some("Entities") => LdtkLayerKind::Entities,
+            Some("Tiles") => LdtkLayerKind::Tiles,
+            Some("AutoLayer") => LdtkLayerKind::AutoLayer,
+            other => return Err(format!("unknown layer type {other:?}")),
+        };
+        let grid_size = int(layer, "__gridSize").max(1) as u32;
+        let offset = (
+            float(layer, "__pxTotalOffsetX"),
+            float(layer, "__pxTotalOffsetY"),
+        );
+        let tileset_uid = layer.get("__tilesetDefUid").and_then(Value::as_i64);
+
+        let tiles = array(Some(layer), "gridTiles")
+            .chain(array(Some(layer), "autoLayerTiles"))
+            .map(|tile| {
+                let (x, y) = pair(tile.get("px"));
+                LdtkTile {
+                    x,
+                    y,
+                    tile: int(tile, "t").max(0) as u32,
+                }
+            })
+            .collect();
+
+        let entities = array(Some(layer), "entityInstances")
+            .map(|entity| {
+                let (px, py) = pair(entity.get("px"));
+                let (pivot_x, pivot_y) = pair(entity.get("__pivot"));
+                let (width, height) = (float(entity, "width"), float(entity, "height"));
+                LdtkEntity {
+                    identifier: string(entity, "__identifier"),
+                    iid: string(entity, "iid"),
+                    x: x + offset.0 + px - pivot_x * width,
+                    y: y + offset.1 + py - pivot_y * height,
+                    width,
+                    height,
+                    tags: array(Some(entity), "__tags")
+                        .filter_map(|tag| tag.as_str().map(str::to_string))
+                        .collect(),
+                    tile: entity
+                        .get("__tile")
+                        .and_then(|tile| tile_rect(tile, tilesets)),
+                    fields: parse_fields(entity, (x, y), grid_size),
+                }
+            })
+            .collect();
+
+        layers.push(LdtkLayer {
+            identifier: string(layer, "__identifier"),
+            kind,
+            visible: layer.get("visible").and_then(Value::as_bool) != Some(false),
+            opacity: layer
+                .get("__opacity")
+                .and_then(Value::as_f64)
+                .unwrap_or(1.0) as f32,
+            grid_size,
+            width: int(layer, "__cWid").max(0) as u32,
+            height: int(layer, "__cHei").max(0) as u32,
+            offset,
+            tileset: tileset_uid
+                .and_then(|uid| tilesets.iter().position(|tileset| tileset.uid == uid)),
+            int_grid: array(Some(layer), "intGridCsv")
+                .map(|value| value.as_i64().unwrap_or(0) as i32)
+                .collect(),
+            tiles,
+            entities,
+        });
+    }
+
+    Ok(LdtkLevel {
+        identifier: string(level, "identifier"),
+        iid: string(level, "iid"),
+        x,
+        y,
+        width,
+        height,
+        background: level
+            .get("__bgColor")
+            .and_then(Value::as_str)
+            .and_then(colors::from_hex)
+            .unwrap_or(colors::BLACK),
+        fields: parse_fields(level, (x, y), default_grid),
+        layers,
+        neighbours: array(Some(level), "__neighbours")
+            .map(|neighbour| LdtkNeighbour {
+                iid: string(neighbour, "levelIid"),
+                direction: string(neighbour, "dir"),
+            })
+            .collect(),
+        file: None,
+    })
+}
+
+/// Field instances as properties. Points are turned from grid cells into the world position of
+/// the cell's center.
+fn parse_fields(value: &Value, level: (f32, f32), grid_size: u32) -> Properties {
+    let mut properties = Properties::default();
+    for field in array(Some(value), "fieldInstances") {
+        let kind = field
+            .get("__type")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let value = field.get("__value").unwrap_or(&Value::Null);
+        let property = match kind
+            .strip_prefix("Array<")
+            .and_then(|kind| kind.strip_suffix('>'))
+        {
+            Some(kind) => Some(Property::Array(
+                value
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|value| parse_field(kind, value, level, grid_size))
+                    .collect(),
+            )),
+            None => parse_field(kind, value, level, grid_size),
+        };
+        if let Some(property) = property {
+            properties.insert(string(field, "__identifier"), property);
+        }
+    }
+    properties
+}
+
+fn parse_field(kind: &str, value: &Value, level: (f32, f32), grid_size: u32) -> Option<Property> {
+    Some(match kind {
+        "Int" => Property::Int(value.as_i64()?),
+        "Float" => Property::Float(value.as_f64()? as f32),
+        "Bool" => Property::Bool(value.as_bool()?),
+        "Color" => Property::Color(colors::from_hex(value.as_str()?)?),
+        "FilePath" => Property::File(value.as_str()?.to_string()),
+        "EntityRef" => Property::Entity(value.get("entityIid")?.as_str()?.to_string()),
+        "Point" => {
+            let grid = grid_size as f32;
+            Property::Point(
+                level.0 + (value.get("cx")?.as_f64()? as f32 + 0.5) * grid,
+                level.1 + (value.get("cy")?.as_f64()? as f32 + 0.5) * grid,
+            )
+        }
+        // Strings, multi-line strings and enum values
+        _ => Property::String(value.as_str()?.to_string()),
+    })
+}
+
+/// A tile rectangle of an entity's tile or a tile field
+fn tile_rect(value: &Value, tilesets: &[LdtkTileset]) -> Option<(usize, TextureRegion)> {
+    let uid = value.get("tilesetUid")?.as_i64()?;
+    let tileset = tilesets.iter().position(|tileset| tileset.uid == uid)?;
+    Some((
+        tileset,
+        TextureRegion::new(
+            float(value, "x"),
+            float(value, "y"),
+            float(value, "w"),
+            float(value, "h"),
+        ),
+    ))
+}
+
+fn array<'a>(value: Option<&'a Value>, name: &str) -> std::slice::Iter<'a, Value> {
+    value
+        .and_then(|value| value.get(name))
+        .and_then(Value::as_array)
+        .map(|values| values.iter())
+        .unwrap_or_default()
+}
+
+fn int(value: &Value, name: &str) -> i64 {
+    value.get(name).and_then(Value::as_i64).unwrap_or(0)
+}
+
+fn float(value: &Value, name: &str) -> f32 {
+    value.get(name).and_then(Value::as_f64).unwrap_or(0.0) as f32
+}
+
+fn string(value: &Value, name: &str) -> String {
+    value
+        .get(name)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn pair(value: Option<&Value>) -> (f32, f32) {
+    let number = |index| {
+        value
+            .and_then(|value| value.get(index))
+            .and_then(Value::as_f64)
+            .unwrap_or(0.0) as f32
+    };
+    (number(0), number(1))
+}
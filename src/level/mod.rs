@@ -0,0 +1,111 @@
+//! Levels made in external editors, loaded through `Assets` and spawned into the world
+
+pub mod ldtk;
+pub mod tiled;
+
+use std::collections::HashMap;
+
+pub use ldtk::{
+    LdtkEntity, LdtkLayer, LdtkLayerKind, LdtkLevel, LdtkNeighbour, LdtkProject, LdtkSpawner,
+    LdtkStreamer, LdtkTile, LdtkTileset, LdtkWorld, SpawnedLevel,
+};
+pub use tiled::{
+    SpawnedMap, TiledLayer, TiledMap, TiledObject, TiledObjectLayer, TiledShape, TiledSpawner,
+    TiledTileLayer, TiledTileset,
+};
+
+/// A custom property set on something in an editor
+#[derive(Clone, Debug, PartialEq)]
+pub enum Property {
+    String(String),
+    Int(i64),
+    Float(f32),
+    Bool(bool),
+    Color([f32; 4]),
+    /// Path as written in the editor, relative to the level file
+    File(String),
+    /// Id of another object in the same level
+    Object(u32),
+    /// Iid of another entity, possibly in another level
+    Entity(String),
+    /// A position in the world, in pixels
+    Point(f32, f32),
+    Array(Vec<Property>),
+}
+
+/// Custom properties by name
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Properties(HashMap<String, Property>);
+
+impl Properties {
+    pub fn get(&self, name: &str) -> Option<&Property> {
+        self.0.get(name)
+    }
+
+    pub fn string(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            Property::String(value) | Property::File(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn int(&self, name: &str) -> Option<i64> {
+        match self.get(name)? {
+            Property::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Ints are converted too, since editors don't always keep the two apart
+    pub fn float(&self, name: &str) -> Option<f32> {
+        match self.get(name)? {
+            Property::Float(value) => Some(*value),
+            Property::Int(value) => Some(*value as f32),
+            _ => None,
+        }
+    }
+
+    pub fn bool(&self, name: &str) -> Option<bool> {
+        match self.get(name)? {
+            Property::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn color(&self, name: &str) -> Option<[f32; 4]> {
+        match self.get(name)? {
+            Property::Color(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn point(&self, name: &str) -> Option<(f32, f32)> {
+        match self.get(name)? {
+            Property::Point(x, y) => Some((*x, *y)),
+            _ => None,
+        }
+    }
+
+    pub fn array(&self, name: &str) -> Option<&[Property]> {
+        match self.get(name)? {
+            Property::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Property)> {
+        self.0.iter().map(|(name, value)| (name.as_str(), value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn insert(&mut self, name: impl Into<String>, value: Property) {
+        self.0.insert(name.into(), value);
+    }
+}
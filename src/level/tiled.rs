@@ -0,0 +1,861 @@
+//! Maps from the Tiled editor, in either the XML (`.tmx`) or JSON (`.tmj`) format.
+//!
+//! Load the map through `Assets`. External tilesets (`.tsx`/`.tsj`) are read along with it,
+//! and editing one reloads the map, and tileset images are loaded as textures. Then `TiledSpawner::spawn` turns it into entities:
+//!
+//! - Tile layers become `Tilemap`s, one per tileset they use, drawn in layer order within each.
+//! - Rectangle and ellipse objects get a `Position`, `Size` and a `Collider` that matches their
+//!   bounds. Set a `collider` bool property to false to leave it out, or `sensor` to true to
+//!   make it a sensor.
+//! - Tile objects get a `Sprite` of their tile, and a collider only if `collider` is true.
+//!   Hidden ones, or ones on a hidden layer, are spawned without the sprite.
+//! - Points and polygons only get a `Position`.
+//!
+//! Every object is then handed to the function registered for its class, to add the game's
+//! own components. Infinite maps and image layers aren't supported yet, object rotation is
+//! ignored since nothing else in the engine rotates, and flipped tiles are drawn the right way
+//! round.
+
+use std::{
+    collections::HashMap,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use base64::Engine as _;
+use bevy_ecs::{entity::Entity, world::EntityWorldMut};
+use serde_json::Value;
+
+use crate::{
+    assets::{Assets, Handle, Texture},
+    core::{
+        collision::Collider,
+        render::colors,
+        tilemap::{Tilemap, Tileset},
+        world::{Position, Size, Sprite},
+    },
+    engine::Engine,
+    level::{Properties, Property},
+};
+
+// The top bits of a tile id are flip and rotation flags
+const TILE_ID_MASK: u32 = 0x0fff_ffff;
+
+/// A map loaded from a `.tmx` or `.tmj` file
+#[derive(Clone, Debug)]
+pub struct TiledMap {
+    /// Size in tiles
+    pub width: u32,
+    pub height: u32,
+    /// Size of a grid cell in pixels
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Sorted by `first_gid`
+    pub tilesets: Vec<TiledTileset>,
+    /// Bottom to top, with groups flattened into their layers
+    pub layers: Vec<TiledLayer>,
+    pub properties: Properties,
+}
+
+#[derive(Clone, Debug)]
+pub struct TiledTileset {
+    /// Global id of the first tile, tile ids in layers are this plus the tile's index
+    pub first_gid: u32,
+    pub name: String,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub columns: u32,
+    pub margin: u32,
+    pub spacing: u32,
+    pub tile_count: u32,
+    /// Relative to the map file. `None` for image collection tilesets, which aren't supported.
+    pub image: Option<PathBuf>,
+    /// Loaded along with the map
+    pub texture: Option<Handle<Texture>>,
+    /// The `.tsx`/`.tsj` file relative to the map for external tilesets, `None` for ones saved
+    /// in the map
+    pub source: Option<PathBuf>,
+    pub properties: Properties,
+}
+
+impl TiledTileset {
+    pub fn tileset(&self) -> Option<Tileset> {
+        Some(
+            Tileset::new(
+                self.texture.clone()?,
+                self.tile_width,
+                self.tile_height,
+                self.columns,
+            )
+            .with_margin(self.margin)
+            .with_spacing(self.spacing),
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum TiledLayer {
+    Tiles(TiledTileLayer),
+    Objects(TiledObjectLayer),
+}
+
+#[derive(Clone, Debug)]
+pub struct TiledTileLayer {
+    pub name: String,
+    pub visible: bool,
+    pub opacity: f32,
+    /// Global tile ids row by row, 0 for empty cells. Flip flags are already masked off.
+    pub tiles: Vec<u32>,
+    pub properties: Properties,
+}
+
+#[derive(Clone, Debug)]
+pub struct TiledObjectLayer {
+    pub name: String,
+    pub visible: bool,
+    pub objects: Vec<TiledObject>,
+    pub properties: Properties,
+}
+
+#[derive(Clone, Debug)]
+pub struct TiledObject {
+    pub id: u32,
+    pub name: String,
+    /// Called type before Tiled 1.9
+    pub class: String,
+    /// Top left corner in pixels, even for tile objects which Tiled stores by their bottom
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Degrees clockwise around the top left corner
+    pub rotation: f32,
+    pub visible: bool,
+    /// Global tile id for tile objects
+    pub gid: Option<u32>,
+    pub shape: TiledShape,
+    pub properties: Properties,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TiledShape {
+    Rectangle,
+    Ellipse,
+    Point,
+    /// Points relative to the object's position
+    Polygon(Vec<(f32, f32)>),
+    Polyline(Vec<(f32, f32)>),
+}
+
+impl TiledMap {
+    /// Parse a map, picking the format from the file extension. External tilesets are read
+    /// from next to `path`.
+    pub fn parse(source: &str, path: &Path) -> Result<Self, String> {
+        let directory = path.parent().unwrap_or(Path::new(""));
+        let mut map = match extension(path).as_str() {
+            "tmx" | "xml" => parse_tmx(source, directory)?,
+            "tmj" | "json" => parse_tmj(source, directory)?,
+            other => return Err(format!("unknown Tiled map format `{other}`")),
+        };
+        map.tilesets.sort_by_key(|tileset| tileset.first_gid);
+        Ok(map)
+    }
+
+    /// The tileset a global tile id belongs to, and the tile's index in it
+    pub fn tileset_for(&self, gid: u32) -> Option<(usize, u32)> {
+        let gid = gid & TILE_ID_MASK;
+        if gid == 0 {
+            return None;
+        }
+        let index = self
+            .tilesets
+            .iter()
+            .rposition(|tileset| tileset.first_gid <= gid)?;
+        Some((index, gid - self.tilesets[index].first_gid))
+    }
+
+    pub fn tile_layers(&self) -> impl Iterator<Item = &TiledTileLayer> {
+        self.layers.iter().filter_map(|layer| match layer {
+            TiledLayer::Tiles(layer) => Some(layer),
+            TiledLayer::Objects(_) => None,
+        })
+    }
+
+    pub fn object_layers(&self) -> impl Iterator<Item = &TiledObjectLayer> {
+        self.layers.iter().filter_map(|layer| match layer {
+            TiledLayer::Objects(layer) => Some(layer),
+            TiledLayer::Tiles(_) => None,
+        })
+    }
+
+    // Tileset images are relative to the map
+    pub(crate) fn load_textures(&mut self, assets: &mut Assets, path: &Path) {
+        let directory = path.parent().unwrap_or(Path::new(""));
+        for tileset in &mut self.tilesets {
+            tileset.texture = tileset
+                .image
+                .as_ref()
+                .map(|image| assets.load_async(directory.join(image)));
+        }
+    }
+}
+
+/// What `TiledSpawner::spawn` created
+#[derive(Clone, Debug, Default)]
+pub struct SpawnedMap {
+    pub tilemaps: Vec<Entity>,
+    /// By Tiled object id, for following object properties that point at other objects
+    pub objects: HashMap<u32, Entity>,
+}
+
+type ObjectSpawner = Box<dyn Fn(&mut EntityWorldMut, &TiledObject) + Send + Sync>;
+
+/// Turns a `TiledMap` into entities, with game specific components added by class
+#[derive(Default)]
+pub struct TiledSpawner {
+    spawners: HashMap<String, ObjectSpawner>,
+}
+
+impl TiledSpawner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `spawn` for every object of this class, after its position, size and collider
+    /// have been added
+    pub fn register(
+        &mut self,
+        class: impl Into<String>,
+        spawn: impl Fn(&mut EntityWorldMut, &TiledObject) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.spawners.insert(class.into(), Box::new(spawn));
+        self
+    }
+
+    /// Spawn the map with its top left corner at `origin`. Returns `None` until the map has
+    /// loaded. Its tileset textures can still be loading, tiles show up once they're ready.
+    pub fn spawn(
+        &self,
+        engine: &mut Engine,
+        map: &Handle<TiledMap>,
+        origin: (f32, f32),
+    ) -> Option<SpawnedMap> {
+        let map = engine.assets.get(map)?;
+        let world = &mut engine.world;
+        let mut spawned = SpawnedMap::default();
+
+        // One tilemap per tileset, since a tilemap draws from a single texture
+        for (index, tileset) in map.tilesets.iter().enumerate() {
+            let used = map.tile_layers().any(|layer| {
+                layer
+                    .tiles
+                    .iter()
+                    .any(|&gid| map.tileset_for(gid).is_some_and(|(i, _)| i == index))
+            });
+            if !used {
+                continue;
+            }
+            let Some(atlas) = tileset.tileset() else {
+                log::warn!(
+                    "Tileset `{}` has no single image, skipping it",
+                    tileset.name
+                );
+                continue;
+            };
+
+            let mut tilemap = Tilemap::new(map.width, map.height, atlas)
+                .with_tile_size(map.tile_width as f32, map.tile_height as f32);
+            for layer in map.tile_layers() {
+                let id = tilemap.add_layer(layer.name.clone());
+                if let Some(tile_layer) = tilemap.layer_mut(id) {
+                    tile_layer.visible = layer.visible;
+                    tile_layer.color[3] = layer.opacity;
+                }
+                tilemap.set_tiles(
+                    id,
+                    layer.tiles.iter().map(|&gid| match map.tileset_for(gid) {
+                        Some((i, tile)) if i == index => Some(tile),
+                        _ => None,
+                    }),
+                );
+            }
+            spawned.tilemaps.push(
+                world
+                    .spawn((Position::new(origin.0, origin.1), tilemap))
+                    .id(),
+            );
+        }
+
+        for layer in map.object_layers() {
+            for object in &layer.objects {
+                let mut entity =
+                    world.spawn(Position::new(origin.0 + object.x, origin.1 + object.y));
+
+                let sized = object.width > 0.0 && object.height > 0.0;
+                if sized {
+                    entity.insert(Size::new(object.width, object.height));
+                }
+
+                let solid = match (&object.shape, object.gid) {
+                    (_, Some(_)) => object.properties.bool("collider").unwrap_or(false),
+                    (TiledShape::Rectangle | TiledShape::Ellipse, None) => {
+                        object.properties.bool("collider").unwrap_or(true)
+                    }
+                    _ => false,
+                };
+                if solid && sized {
+                    let collider = Collider::new();
+                    entity.insert(if object.properties.bool("sensor").unwrap_or(false) {
+                        collider.sensor()
+                    } else {
+                        collider
+                    });
+                }
+
+                if let Some((tileset, tile)) = object.gid.and_then(|gid| map.tileset_for(gid))
+                    && layer.visible
+                    && object.visible
+                {
+                    let tileset = &map.tilesets[tileset];
+                    if let Some(atlas) = tileset.tileset() {
+                        entity.insert(
+                            Sprite::new(atlas.texture.clone()).with_region(atlas.region(tile)),
+                        );
+                    }
+                }
+
+                if let Some(spawn) = self.spawners.get(&object.class) {
+                    spawn(&mut entity, object);
+                }
+                spawned.objects.insert(object.id, entity.id());
+            }
+        }
+
+        Some(spawned)
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+/// Tiled writes colors as `#AARRGGBB` or `#RRGGBB`
+fn parse_color(value: &str) -> Option<[f32; 4]> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    match hex.len() {
+        6 => colors::from_hex(&format!("#{hex}")),
+        8 => colors::from_hex(&format!("#{}{}", hex.get(2..)?, hex.get(..2)?)),
+        _ => None,
+    }
+}
+
+fn parse_property(kind: &str, value: &str) -> Result<Property, String> {
+    let invalid = || format!("invalid {kind} property value `{value}`");
+    Ok(match kind {
+        "int" => Property::Int(value.parse().map_err(|_| invalid())?),
+        "float" => Property::Float(value.parse().map_err(|_| invalid())?),
+        "bool" => Property::Bool(value == "true"),
+        "color" => Property::Color(parse_color(value).unwrap_or([0.0; 4])),
+        "file" => Property::File(value.to_string()),
+        "object" => Property::Object(value.parse().map_err(|_| invalid())?),
+        _ => Property::String(value.to_string()),
+    })
+}
+
+/// Layer data in any of Tiled's encodings
+fn decode_tiles(encoding: &str, compression: &str, data: &str) -> Result<Vec<u32>, String> {
+    match encoding {
+        "csv" => data
+            .split(',')
+            .map(str::trim)
+            .filter(|tile| !tile.is_empty())
+            .map(|tile| {
+                tile.parse::<u32>()
+                    .map_err(|_| format!("invalid tile id `{tile}`"))
+            })
+            .collect(),
+        "base64" => {
+            let data: String = data.split_whitespace().collect();
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(data)
+                .map_err(|e| format!("invalid base64 layer data: {e}"))?;
+            let bytes = match compression {
+                "" => bytes,
+                "zlib" => inflate(flate2::read::ZlibDecoder::new(&bytes[..]))?,
+                "gzip" => inflate(flate2::read::GzDecoder::new(&bytes[..]))?,
+                other => return Err(format!("unsupported layer compression `{other}`")),
+            };
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|tile| u32::from_le_bytes([tile[0], tile[1], tile[2], tile[3]]))
+                .collect())
+        }
+        other => Err(format!("unsupported layer encoding `{other}`")),
+    }
+}
+
+fn inflate(mut reader: impl Read) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    reader
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("invalid compressed layer data: {e}"))?;
+    Ok(bytes)
+}
+
+/// An external tileset, with its image made relative to the map instead of itself
+fn read_external_tileset(
+    directory: &Path,
+    source: &str,
+    first_gid: u32,
+) -> Result<TiledTileset, String> {
+    let path = directory.join(source);
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| format!("can't read tileset {}: {e}", path.display()))?;
+    let mut tileset = match extension(&path).as_str() {
+        "tsx" | "xml" => {
+            let document = roxmltree::Document::parse(&text)
+                .map_err(|e| format!("invalid tileset {}: {e}", path.display()))?;
+            xml_tileset(document.root_element(), first_gid)?
+        }
+        _ => {
+            let json: Value = serde_json::from_str(&text)
+                .map_err(|e| format!("invalid tileset {}: {e}", path.display()))?;
+            json_tileset(&json, first_gid)?
+        }
+    };
+    let tileset_directory = Path::new(source).parent().unwrap_or(Path::new(""));
+    tileset.image = tileset.image.map(|image| tileset_directory.join(image));
+    tileset.source = Some(PathBuf::from(source));
+    Ok(tileset)
+}
+
+// TMX
+
+fn xml_attribute<T: std::str::FromStr>(
+    node: roxmltree::Node,
+    name: &str,
+) -> Result<Option<T>, String> {
+    node.attribute(name)
+        .map(|value| {
+            value.parse().map_err(|_| {
+                format!(
+                    "<{}> has an invalid `{name}`: `{value}`",
+                    node.tag_name().name()
+                )
+            })
+        })
+        .transpose()
+}
+
+fn xml_required<T: std::str::FromStr>(node: roxmltree::Node, name: &str) -> Result<T, String> {
+    xml_attribute(node, name)?
+        .ok_or_else(|| format!("<{}> is missing `{name}`", node.tag_name().name()))
+}
+
+fn xml_child<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    name: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+fn xml_properties(node: roxmltree::Node) -> Result<Properties, String> {
+    let mut properties = Properties::default();
+    let Some(list) = xml_child(node, "properties") else {
+        return Ok(properties);
+    };
+    for property in list
+        .children()
+        .filter(|child| child.has_tag_name("property"))
+    {
+        let name = property.attribute("name").unwrap_or_default();
+        // Multi-line strings are stored as the element's text instead
+        let value = property
+            .attribute("value")
+            .or_else(|| property.text())
+            .unwrap_or_default();
+        let kind = property.attribute("type").unwrap_or("string");
+        properties.insert(name, parse_property(kind, value)?);
+    }
+    Ok(properties)
+}
+
+fn parse_tmx(source: &str, directory: &Path) -> Result<TiledMap, String> {
+    let document = roxmltree::Document::parse(source).map_err(|e| e.to_string())?;
+    let root = document.root_element();
+    if !root.has_tag_name("map") {
+        return Err("not a Tiled map".to_string());
+    }
+    if root.attribute("infinite") == Some("1") {
+        return Err("infinite maps aren't supported".to_string());
+    }
+
+    let mut map = TiledMap {
+        width: xml_required(root, "width")?,
+        height: xml_required(root, "height")?,
+        tile_width: xml_required(root, "tilewidth")?,
+        tile_height: xml_required(root, "tileheight")?,
+        tilesets: Vec::new(),
+        layers: Vec::new(),
+        properties: xml_properties(root)?,
+    };
+
+    for tileset in root
+        .children()
+        .filter(|child| child.has_tag_name("tileset"))
+    {
+        let first_gid = xml_required(tileset, "firstgid")?;
+        map.tilesets.push(match tileset.attribute("source") {
+            Some(source) => read_external_tileset(directory, source, first_gid)?,
+            None => xml_tileset(tileset, first_gid)?,
+        });
+    }
+
+    xml_layers(root, true, 1.0, &mut map.layers)?;
+    Ok(map)
+}
+
+fn xml_tileset(node: roxmltree::Node, first_gid: u32) -> Result<TiledTileset, String> {
+    let image = xml_child(node, "image").and_then(|image| image.attribute("source"));
+    let image_width: Option<u32> =
+        xml_child(node, "image").map_or(Ok(None), |image| xml_attribute(image, "width"))?;
+    let tile_width = xml_required(node, "tilewidth")?;
+    let margin: u32 = xml_attribute(node, "margin")?.unwrap_or(0);
+    let spacing = xml_attribute(node, "spacing")?.unwrap_or(0);
+
+    Ok(TiledTileset {
+        first_gid,
+        name: node.attribute("name").unwrap_or_default().to_string(),
+        tile_width,
+        tile_height: xml_required(node, "tileheight")?,
+        // Old versions leave columns out, work it out from the image
+        columns: match xml_attribute(node, "columns")? {
+            Some(columns) => columns,
+            // Saturating, since a broken file can have margins wider than the image
+            None => image_width.map_or(1, |width| {
+                width
+                    .saturating_sub(margin.saturating_mul(2))
+                    .saturating_add(spacing)
+                    / tile_width.saturating_add(spacing).max(1)
+            }),
+        },
+        margin,
+        spacing,
+        tile_count: xml_attribute(node, "tilecount")?.unwrap_or(0),
+        image: image.map(PathBuf::from),
+        texture: None,
+        source: None,
+        properties: xml_properties(node)?,
+    })
+}
+
+/// Layers in order, with groups flattened and their visibility and opacity passed down
+fn xml_layers(
+    parent: roxmltree::Node,
+    visible: bool,
+    opacity: f32,
+    layers: &mut Vec<TiledLayer>,
+) -> Result<(), String> {
+    for node in parent.children().filter(|child| child.is_element()) {
+        let name = node.attribute("name").unwrap_or_default().to_string();
+        let visible = visible && node.attribute("visible") != Some("0");
+        let opacity = opacity * xml_attribute(node, "opacity")?.unwrap_or(1.0);
+
+        match node.tag_name().name() {
+            "layer" => {
+                let data = xml_child(node, "data").ok_or("tile layer without data")?;
+                let tiles = match data.attribute("encoding") {
+                    // Plain XML, one <tile> per cell
+                    None => data
+                        .children()
+                        .filter(|child| child.has_tag_name("tile"))
+                        .map(|tile| Ok(xml_attribute(tile, "gid")?.unwrap_or(0)))
+                        .collect::<Result<Vec<u32>, String>>()?,
+                    Some(encoding) => decode_tiles(
+                        encoding,
+                        data.attribute("compression").unwrap_or_default(),
+                        data.text().unwrap_or_default(),
+                    )?,
+                };
+                layers.push(TiledLayer::Tiles(TiledTileLayer {
+                    name,
+                    visible,
+                    opacity,
+                    tiles: tiles.into_iter().map(|gid| gid & TILE_ID_MASK).collect(),
+                    properties: xml_properties(node)?,
+                }));
+            }
+            "objectgroup" => {
+                let objects = node
+                    .children()
+                    .filter(|child| child.has_tag_name("object"))
+                    .map(xml_object)
+                    .collect::<Result<_, _>>()?;
+                layers.push(TiledLayer::Objects(TiledObjectLayer {
+                    name,
+                    visible,
+                    objects,
+                    properties: xml_properties(node)?,
+                }));
+            }
+            "group" => xml_layers(node, visible, opacity, layers)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn xml_points(node: roxmltree::Node) -> Result<Vec<(f32, f32)>, String> {
+    node.attribute("points")
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(|point| {
+            let (x, y) = point
+                .split_once(',')
+                .ok_or_else(|| format!("invalid point `{point}`"))?;
+            let number = |value: &str| {
+                value
+                    .parse::<f32>()
+                    .map_err(|_| format!("invalid point `{point}`"))
+            };
+            Ok((number(x)?, number(y)?))
+        })
+        .collect()
+}
+
+fn xml_object(node: roxmltree::Node) -> Result<TiledObject, String> {
+    let shape = if xml_child(node, "ellipse").is_some() {
+        TiledShape::Ellipse
+    } else if xml_child(node, "point").is_some() {
+        TiledShape::Point
+    } else if let Some(polygon) = xml_child(node, "polygon") {
+        TiledShape::Polygon(xml_points(polygon)?)
+    } else if let Some(polyline) = xml_child(node, "polyline") {
+        TiledShape::Polyline(xml_points(polyline)?)
+    } else {
+        TiledShape::Rectangle
+    };
+
+    let mut object = TiledObject {
+        id: xml_attribute(node, "id")?.unwrap_or(0),
+        name: node.attribute("name").unwrap_or_default().to_string(),
+        class: node
+            .attribute("class")
+            .or_else(|| node.attribute("type"))
+            .unwrap_or_default()
+            .to_string(),
+        x: xml_attribute(node, "x")?.unwrap_or(0.0),
+        y: xml_attribute(node, "y")?.unwrap_or(0.0),
+        width: xml_attribute(node, "width")?.unwrap_or(0.0),
+        height: xml_attribute(node, "height")?.unwrap_or(0.0),
+        rotation: xml_attribute(node, "rotation")?.unwrap_or(0.0),
+        visible: node.attribute("visible") != Some("0"),
+        gid: xml_attribute::<u32>(node, "gid")?.map(|gid| gid & TILE_ID_MASK),
+        shape,
+        properties: xml_properties(node)?,
+    };
+    if object.gid.is_some() {
+        object.y -= object.height;
+    }
+    Ok(object)
+}
+
+// TMJ
+
+fn json_u32(value: &Value, name: &str) -> Option<u32> {
+    value.get(name)?.as_u64().map(|number| number as u32)
+}
+
+fn json_f32(value: &Value, name: &str) -> Option<f32> {
+    value.get(name)?.as_f64().map(|number| number as f32)
+}
+
+fn json_str<'a>(value: &'a Value, name: &str) -> Option<&'a str> {
+    value.get(name)?.as_str()
+}
+
+fn json_required(value: &Value, name: &str) -> Result<u32, String> {
+    json_u32(value, name).ok_or_else(|| format!("missing or invalid `{name}`"))
+}
+
+fn json_properties(value: &Value) -> Result<Properties, String> {
+    let mut properties = Properties::default();
+    let Some(list) = value.get("properties").and_then(Value::as_array) else {
+        return Ok(properties);
+    };
+    for property in list {
+        let name = json_str(property, "name").unwrap_or_default();
+        let kind = json_str(property, "type").unwrap_or("string");
+        let value = match property.get("value") {
+            Some(Value::String(value)) => value.clone(),
+            Some(value) => value.to_string(),
+            None => String::new(),
+        };
+        properties.insert(name, parse_property(kind, &value)?);
+    }
+    Ok(properties)
+}
+
+fn parse_tmj(source: &str, directory: &Path) -> Result<TiledMap, String> {
+    let root: Value = serde_json::from_str(source).map_err(|e| e.to_string())?;
+    if root.get("infinite").and_then(Value::as_bool) == Some(true) {
+        return Err("infinite maps aren't supported".to_string());
+    }
+
+    let mut map = TiledMap {
+        width: json_required(&root, "width")?,
+        height: json_required(&root, "height")?,
+        tile_width: json_required(&root, "tilewidth")?,
+        tile_height: json_required(&root, "tileheight")?,
+        tilesets: Vec::new(),
+        layers: Vec::new(),
+        properties: json_properties(&root)?,
+    };
+
+    for tileset in root
+        .get("tilesets")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let first_gid = json_required(tileset, "firstgid")?;
+        map.tilesets.push(match json_str(tileset, "source") {
+            Some(source) => read_external_tileset(directory, source, first_gid)?,
+            None => json_tileset(tileset, first_gid)?,
+        });
+    }
+
+    json_layers(&root, true, 1.0, &mut map.layers)?;
+    Ok(map)
+}
+
+fn json_tileset(value: &Value, first_gid: u32) -> Result<TiledTileset, String> {
+    Ok(TiledTileset {
+        first_gid,
+        name: json_str(value, "name").unwrap_or_default().to_string(),
+        tile_width: json_required(value, "tilewidth")?,
+        tile_height: json_required(value, "tileheight")?,
+        columns: json_u32(value, "columns").unwrap_or(1),
+        margin: json_u32(value, "margin").unwrap_or(0),
+        spacing: json_u32(value, "spacing").unwrap_or(0),
+        tile_count: json_u32(value, "tilecount").unwrap_or(0),
+        image: json_str(value, "image").map(PathBuf::from),
+        texture: None,
+        source: None,
+        properties: json_properties(value)?,
+    })
+}
+
+fn json_layers(
+    parent: &Value,
+    visible: bool,
+    opacity: f32,
+    layers: &mut Vec<TiledLayer>,
+) -> Result<(), String> {
+    for layer in parent
+        .get("layers")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let name = json_str(layer, "name").unwrap_or_default().to_string();
+        let visible = visible && layer.get("visible").and_then(Value::as_bool) != Some(false);
+        let opacity = opacity * json_f32(layer, "opacity").unwrap_or(1.0);
+
+        match json_str(layer, "type").unwrap_or_default() {
+            "tilelayer" => {
+                let tiles = match layer.get("data") {
+                    Some(Value::Array(tiles)) => tiles
+                        .iter()
+                        .map(|tile| tile.as_u64().unwrap_or(0) as u32)
+                        .collect(),
+                    Some(Value::String(data)) => decode_tiles(
+                        json_str(layer, "encoding").unwrap_or("base64"),
+                        json_str(layer, "compression").unwrap_or_default(),
+                        data,
+                    )?,
+                    _ => return Err(format!("tile layer `{name}` without data")),
+                };
+                layers.push(TiledLayer::Tiles(TiledTileLayer {
+                    name,
+                    visible,
+                    opacity,
+                    tiles: tiles.into_iter().map(|gid| gid & TILE_ID_MASK).collect(),
+                    properties: json_properties(layer)?,
+                }));
+            }
+            "objectgroup" => {
+                let objects = layer
+                    .get("objects")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .map(json_object)
+                    .collect::<Result<_, _>>()?;
+                layers.push(TiledLayer::Objects(TiledObjectLayer {
+                    name,
+                    visible,
+                    objects,
+                    properties: json_properties(layer)?,
+                }));
+            }
+            "group" => json_layers(layer, visible, opacity, layers)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn json_points(value: &Value) -> Vec<(f32, f32)> {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|point| {
+            (
+                json_f32(point, "x").unwrap_or(0.0),
+                json_f32(point, "y").unwrap_or(0.0),
+            )
+        })
+        .collect()
+}
+
+fn json_object(value: &Value) -> Result<TiledObject, String> {
+    let flag = |name: &str| value.get(name).and_then(Value::as_bool) == Some(true);
+    let shape = if flag("ellipse") {
+        TiledShape::Ellipse
+    } else if flag("point") {
+        TiledShape::Point
+    } else if let Some(polygon) = value.get("polygon") {
+        TiledShape::Polygon(json_points(polygon))
+    } else if let Some(polyline) = value.get("polyline") {
+        TiledShape::Polyline(json_points(polyline))
+    } else {
+        TiledShape::Rectangle
+    };
+
+    let mut object = TiledObject {
+        id: json_u32(value, "id").unwrap_or(0),
+        name: json_str(value, "name").unwrap_or_default().to_string(),
+        class: json_str(value, "class")
+            .or_else(|| json_str(value, "type"))
+            .unwrap_or_default()
+            .to_string(),
+        x: json_f32(value, "x").unwrap_or(0.0),
+        y: json_f32(value, "y").unwrap_or(0.0),
+        width: json_f32(value, "width").unwrap_or(0.0),
+        height: json_f32(value, "height").unwrap_or(0.0),
+        rotation: json_f32(value, "rotation").unwrap_or(0.0),
+        visible: value.get("visible").and_then(Value::as_bool) != Some(false),
+        gid: json_u32(value, "gid").map(|gid| gid & TILE_ID_MASK),
+        shape,
+        properties: json_properties(value)?,
+    };
+    if object.gid.is_some() {
+        object.y -= object.height;
+    }
+    Ok(object)
+}
@@ -1,4 +1,15 @@
+pub mod assets;
+pub mod audio;
 pub mod core;
 pub mod engine;
 pub mod error;
+pub mod level;
 pub mod prelude;
+pub mod text;
+
+// Renderer types that show up in the engine's own API
+pub use wgpu_renderer::{
+    ClipRect, FontId, HorizontalAlign, MaskMode, MaterialDescriptor, MaterialId, NineSlice,
+    PostEffect, PostEffectId, PostProcess, ShaderCode, SliceFill, TextBounds, TextSection,
+    TextSpan, TextureFilter, VerticalAlign,
+};
@@ -49,6 +49,11 @@
 //! - **`Engine`**: The runtime context available in your game code.
 //!   - Access the renderer: `engine.renderer_mut()`
 //!   - Access the ECS world: `engine.world_mut()`
+//!   - Move the camera: `engine.camera_mut()`
+//!   - Add fullscreen effects: `engine.post_process_mut().push(PostEffect::bloom())`
+//!   - Play sounds and music: `engine.audio()`
+//!   - Load textures, fonts, sounds and data files: `engine.assets_mut().load(path)`
+//!   - Or in the background: `engine.assets_mut().load_async(path)`
 //! - **`Game`**: The trait you implement to define your game logic.
 //! - **`EngineConfig`**: Configuration for the engine (window title, size, etc.).
 
@@ -0,0 +1,281 @@
+//! Bitmap fonts in the AngelCode BMFont text format (`.fnt` plus atlas page images)
+
+use std::{collections::HashMap, path::PathBuf};
+
+use wgpu_renderer::{HorizontalAlign, VerticalAlign};
+
+use crate::{
+    assets::{Handle, Texture, TextureRegion},
+    text::markup::RichSpan,
+};
+
+/// Where a character sits in its atlas page and how it's placed relative to the pen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BitmapGlyph {
+    pub region: TextureRegion,
+    pub x_offset: f32,
+    pub y_offset: f32,
+    pub x_advance: f32,
+    pub page: usize,
+}
+
+/// A font made of pre-rendered characters, drawn as sprites.
+///
+/// Load the `.fnt` file through `Assets`, the page images next to it are loaded along with it.
+pub struct BitmapFont {
+    line_height: f32,
+    base: f32,
+    // Relative to the .fnt file, indexed by page id
+    page_files: Vec<PathBuf>,
+    pub(crate) pages: Vec<Handle<Texture>>,
+    glyphs: HashMap<char, BitmapGlyph>,
+    kerning: HashMap<(char, char), f32>,
+}
+
+/// One quad of laid out bitmap text, in pixels relative to the text's position
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct BitmapQuad {
+    pub(crate) source: QuadSource,
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    pub(crate) width: f32,
+    pub(crate) height: f32,
+    pub(crate) color: [f32; 4],
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum QuadSource {
+    Glyph { page: usize, region: TextureRegion },
+    Icon(String),
+}
+
+impl BitmapFont {
+    /// Parse the text variant of the BMFont format
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut font = Self {
+            line_height: 0.0,
+            base: 0.0,
+            page_files: Vec::new(),
+            pages: Vec::new(),
+            glyphs: HashMap::new(),
+            kerning: HashMap::new(),
+        };
+
+        for (line_number, line) in source.lines().enumerate() {
+            let Some((tag, attributes)) = parse_line(line) else {
+                continue;
+            };
+            let number = |key: &str| -> Result<f32, String> {
+                attributes
+                    .get(key)
+                    .ok_or_else(|| format!("line {}: missing `{key}`", line_number + 1))?
+                    .parse()
+                    .map_err(|_| format!("line {}: `{key}` isn't a number", line_number + 1))
+            };
+            let character = |key: &str| -> Result<char, String> {
+                char::from_u32(number(key)? as u32)
+                    .ok_or_else(|| format!("line {}: invalid character", line_number + 1))
+            };
+
+            match tag {
+                "common" => {
+                    font.line_height = number("lineHeight")?;
+                    font.base = number("base")?;
+                }
+                "page" => {
+                    let id = number("id")? as usize;
+                    let file = attributes
+                        .get("file")
+                        .ok_or_else(|| format!("line {}: page without a file", line_number + 1))?;
+                    if font.page_files.len() <= id {
+                        font.page_files.resize(id + 1, PathBuf::new());
+                    }
+                    font.page_files[id] = PathBuf::from(file);
+                }
+                "char" => {
+                    let glyph = BitmapGlyph {
+                        region: TextureRegion::new(
+                            number("x")?,
+                            number("y")?,
+                            number("width")?,
+                            number("height")?,
+                        ),
+                        x_offset: number("xoffset")?,
+                        y_offset: number("yoffset")?,
+                        x_advance: number("xadvance")?,
+                        page: number("page").unwrap_or(0.0) as usize,
+                    };
+                    font.glyphs.insert(character("id")?, glyph);
+                }
+                "kerning" => {
+                    font.kerning.insert(
+                        (character("first")?, character("second")?),
+                        number("amount")?,
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        if font.page_files.is_empty() {
+            return Err("no pages".to_string());
+        }
+        Ok(font)
+    }
+
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    pub fn glyph(&self, c: char) -> Option<&BitmapGlyph> {
+        self.glyphs.get(&c)
+    }
+
+    pub(crate) fn page_files(&self) -> &[PathBuf] {
+        &self.page_files
+    }
+
+    /// Lay out spans at whole pixel positions. `scale` is a whole number so the pixels stay
+    /// square, `[size=n]` markup picks the closest whole scale for a line height of `n`.
+    pub(crate) fn layout(
+        &self,
+        spans: &[RichSpan],
+        scale: u32,
+        color: [f32; 4],
+        h_align: HorizontalAlign,
+        v_align: VerticalAlign,
+    ) -> Vec<BitmapQuad> {
+        let default_scale = scale.max(1) as f32;
+
+        // (quads, pen position, tallest scale) of every line. The pen ends up at the line's width.
+        let mut lines: Vec<(Vec<BitmapQuad>, f32, f32)> = vec![(Vec::new(), 0.0, default_scale)];
+        let mut previous: Option<char> = None;
+
+        for span in spans {
+            let (text, span_color, span_scale) = match span {
+                RichSpan::Text {
+                    text,
+                    color: c,
+                    size,
+                } => (
+                    text.as_str(),
+                    c.unwrap_or(color),
+                    size.map_or(default_scale, |size| self.scale_for(size)),
+                ),
+                RichSpan::Icon(name) => {
+                    let (quads, pen, line_scale) = lines.last_mut().unwrap();
+                    let size = self.line_height * default_scale;
+                    quads.push(BitmapQuad {
+                        source: QuadSource::Icon(name.clone()),
+                        x: *pen,
+                        y: 0.0,
+                        width: size,
+                        height: size,
+                        color: [1.0; 4],
+                    });
+                    *pen += size;
+                    *line_scale = line_scale.max(default_scale);
+                    previous = None;
+                    continue;
+                }
+            };
+
+            for c in text.chars() {
+                if c == '\n' {
+                    lines.push((Vec::new(), 0.0, default_scale));
+                    previous = None;
+                    continue;
+                }
+                let Some(glyph) = self.glyphs.get(&c).or_else(|| self.glyphs.get(&'?')) else {
+                    continue;
+                };
+
+                let (quads, pen, line_scale) = lines.last_mut().unwrap();
+                if let Some(kerning) = previous.and_then(|p| self.kerning.get(&(p, c))) {
+                    *pen += kerning * span_scale;
+                }
+                *line_scale = line_scale.max(span_scale);
+
+                quads.push(BitmapQuad {
+                    source: QuadSource::Glyph {
+                        page: glyph.page,
+                        region: glyph.region,
+                    },
+                    x: *pen + glyph.x_offset * span_scale,
+                    // Moved down onto the line's baseline below, once the line's scale is known
+                    y: (glyph.y_offset - self.base) * span_scale,
+                    width: glyph.region.width * span_scale,
+                    height: glyph.region.height * span_scale,
+                    color: span_color,
+                });
+                *pen += glyph.x_advance * span_scale;
+                previous = Some(c);
+            }
+        }
+
+        let total_height: f32 = lines
+            .iter()
+            .map(|(_, _, scale)| self.line_height * scale)
+            .sum();
+        let y_start = match v_align {
+            VerticalAlign::Top => 0.0,
+            VerticalAlign::Center => -total_height / 2.0,
+            VerticalAlign::Bottom => -total_height,
+        };
+
+        let mut quads = Vec::new();
+        let mut line_top = y_start;
+        for (line, width, line_scale) in lines {
+            let x_start = match h_align {
+                HorizontalAlign::Left => 0.0,
+                HorizontalAlign::Center => -width / 2.0,
+                HorizontalAlign::Right => -width,
+            };
+            let baseline = line_top + self.base * line_scale;
+
+            quads.extend(line.into_iter().map(|mut quad| {
+                quad.x = (quad.x + x_start).round();
+                quad.y = match quad.source {
+                    QuadSource::Glyph { .. } => (baseline + quad.y).round(),
+                    QuadSource::Icon(_) => line_top.round(),
+                };
+                quad
+            }));
+            line_top += self.line_height * line_scale;
+        }
+        quads
+    }
+
+    fn scale_for(&self, size: f32) -> f32 {
+        if self.line_height <= 0.0 {
+            return 1.0;
+        }
+        (size / self.line_height).round().max(1.0)
+    }
+}
+
+// `tag key=value key="quoted value" ...`
+fn parse_line(line: &str) -> Option<(&str, HashMap<&str, &str>)> {
+    let line = line.trim();
+    let (tag, mut rest) = line.split_once(' ').unwrap_or((line, ""));
+    if tag.is_empty() {
+        return None;
+    }
+
+    let mut attributes = HashMap::new();
+    loop {
+        rest = rest.trim_start();
+        let Some((key, after)) = rest.split_once('=') else {
+            break;
+        };
+
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => after.split_once(' ').unwrap_or((after, "")),
+        };
+        attributes.insert(key, value);
+        rest = after;
+    }
+
+    Some((tag, attributes))
+}
@@ -0,0 +1,115 @@
+//! A small BBCode-ish markup for rich text.
+//!
+//! - `[color=red]...[/color]` or `[color=#ff8800]...[/color]` changes the color
+//! - `[size=24]...[/size]` changes the size
+//! - `[icon=heart]` draws an icon registered with `TextIcons`
+//! - `[[` is a literal `[`
+//!
+//! Tags nest, and anything that doesn't parse as a tag is kept as plain text.
+
+use crate::core::render::colors;
+
+/// A piece of parsed markup
+#[derive(Clone, Debug, PartialEq)]
+pub enum RichSpan {
+    /// Text with the color/size overrides active at that point, `None` means the default
+    Text {
+        text: String,
+        color: Option<[f32; 4]>,
+        size: Option<f32>,
+    },
+    /// An icon by name
+    Icon(String),
+}
+
+enum Tag<'a> {
+    Color([f32; 4]),
+    EndColor,
+    Size(f32),
+    EndSize,
+    Icon(&'a str),
+}
+
+/// Split markup into spans
+pub fn parse(markup: &str) -> Vec<RichSpan> {
+    let mut spans = Vec::new();
+    let mut colors: Vec<[f32; 4]> = Vec::new();
+    let mut sizes: Vec<f32> = Vec::new();
+    let mut text = String::new();
+
+    let mut rest = markup;
+    while let Some(start) = rest.find('[') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(after) = rest.strip_prefix("[[") {
+            text.push('[');
+            rest = after;
+            continue;
+        }
+
+        let tag = rest
+            .find(']')
+            .and_then(|end| Some((parse_tag(&rest[1..end])?, end)));
+        let Some((tag, end)) = tag else {
+            text.push('[');
+            rest = &rest[1..];
+            continue;
+        };
+        rest = &rest[end + 1..];
+
+        // Whatever came before the tag was styled by the old state
+        flush(&mut spans, &mut text, &colors, &sizes);
+        match tag {
+            Tag::Color(color) => colors.push(color),
+            Tag::EndColor => {
+                colors.pop();
+            }
+            Tag::Size(size) => sizes.push(size),
+            Tag::EndSize => {
+                sizes.pop();
+            }
+            Tag::Icon(name) => spans.push(RichSpan::Icon(name.to_string())),
+        }
+    }
+
+    text.push_str(rest);
+    flush(&mut spans, &mut text, &colors, &sizes);
+    spans
+}
+
+/// Markup with the tags stripped out, e.g. for measuring or accessibility
+pub fn plain_text(markup: &str) -> String {
+    parse(markup)
+        .into_iter()
+        .filter_map(|span| match span {
+            RichSpan::Text { text, .. } => Some(text),
+            RichSpan::Icon(_) => None,
+        })
+        .collect()
+}
+
+fn parse_tag(tag: &str) -> Option<Tag<'_>> {
+    match tag.split_once('=') {
+        Some(("color", value)) => colors::by_name(value)
+            .or_else(|| colors::from_hex(value))
+            .map(Tag::Color),
+        Some(("size", value)) => value.parse().ok().map(Tag::Size),
+        Some(("icon", name)) if !name.is_empty() => Some(Tag::Icon(name)),
+        None if tag == "/color" => Some(Tag::EndColor),
+        None if tag == "/size" => Some(Tag::EndSize),
+        _ => None,
+    }
+}
+
+fn flush(spans: &mut Vec<RichSpan>, text: &mut String, colors: &[[f32; 4]], sizes: &[f32]) {
+    if text.is_empty() {
+        return;
+    }
+
+    spans.push(RichSpan::Text {
+        text: std::mem::take(text),
+        color: colors.last().copied(),
+        size: sizes.last().copied(),
+    });
+}
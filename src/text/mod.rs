@@ -0,0 +1,49 @@
+//! Engine side text features on top of the renderer's TTF text: bitmap fonts, rich text
+//! markup and inline icons.
+
+pub mod bitmap;
+pub mod markup;
+
+use std::collections::HashMap;
+
+pub use bitmap::{BitmapFont, BitmapGlyph};
+pub use markup::RichSpan;
+
+use crate::assets::{Handle, Texture, TextureRegion};
+
+/// An image that can be dropped into rich text with `[icon=name]`
+#[derive(Clone, Debug)]
+pub struct TextIcon {
+    pub texture: Handle<Texture>,
+    /// Part of the texture to draw, `None` draws the whole thing
+    pub region: Option<TextureRegion>,
+}
+
+/// Icons available to rich text, by name. Available through `engine.text_icons_mut()`.
+#[derive(Default)]
+pub struct TextIcons {
+    icons: HashMap<String, TextIcon>,
+}
+
+impl TextIcons {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        texture: Handle<Texture>,
+        region: Option<TextureRegion>,
+    ) {
+        self.icons.insert(name.into(), TextIcon { texture, region });
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<TextIcon> {
+        self.icons.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TextIcon> {
+        self.icons.get(name)
+    }
+}
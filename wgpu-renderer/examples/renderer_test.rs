@@ -1,10 +1,14 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
-use wgpu_renderer::renderer::Renderer;
+use wgpu_renderer::{
+    FULL_UV, FillRule, Gradient, LineCap, LineJoin, MaterialDescriptor, MaterialId, PostEffect,
+    PostEffectId, ShapePath, StrokeStyle, TextureId, renderer::Renderer,
+};
 use winit::{
     application::ApplicationHandler,
-    event::WindowEvent,
+    event::{ElementState, KeyEvent, WindowEvent},
     event_loop::EventLoop,
+    keyboard::{Key, NamedKey},
     window::WindowAttributes,
 };
 
@@ -20,9 +24,30 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+// Stripes that scroll over time, tinted by the `tint` parameter
+const WAVE_SHADER: &str = r#"
+struct Globals {
+    time: f32,
+    _padding: f32,
+    screen_size: vec2<f32>,
+}
+
+@group(2) @binding(0) var<uniform> globals: Globals;
+@group(2) @binding(1) var<uniform> params: array<vec4<f32>, 16>;
+
+@fragment
+fn main(@location(0) color: vec4<f32>, @location(1) uv: vec2<f32>) -> @location(0) vec4<f32> {
+    let wave = 0.5 + 0.5 * sin(uv.x * 20.0 + globals.time * 4.0);
+    return vec4<f32>(params[0].rgb * wave, 1.0) * color;
+}
+"#;
+
 struct RenderApp {
     renderer: Option<Renderer>,
     window: Option<Arc<winit::window::Window>>,
+    wave: MaterialId,
+    started: Instant,
+    crt: Option<PostEffectId>,
 }
 
 impl RenderApp {
@@ -30,6 +55,9 @@ impl RenderApp {
         Self {
             renderer: None,
             window: None,
+            wave: MaterialId::DEFAULT,
+            started: Instant::now(),
+            crt: None,
         }
     }
 }
@@ -51,7 +79,21 @@ impl ApplicationHandler for RenderApp {
             );
 
             let size = window.inner_size();
-            let renderer = pollster::block_on(Renderer::new(window.clone(), size));
+            let mut renderer = pollster::block_on(Renderer::new(window.clone(), size));
+
+            let wave = MaterialDescriptor::wgsl(WAVE_SHADER).with_color("tint", [0.2, 0.8, 1.0, 1.0]);
+            match renderer.create_material(&wave) {
+                Ok(material) => self.wave = material,
+                Err(e) => eprintln!("{e}"),
+            }
+
+            // Space toggles the CRT effect
+            let post = renderer.post_process_mut();
+            post.push(PostEffect::bloom());
+            post.push(PostEffect::vignette());
+            let crt = post.push(PostEffect::crt());
+            post.set_enabled(crt, false);
+            self.crt = Some(crt);
 
             window.request_redraw();
             self.window = Some(window);
@@ -74,6 +116,21 @@ impl ApplicationHandler for RenderApp {
                     renderer.resize(new_size);
                 }
             }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Named(NamedKey::Space),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                if let (Some(renderer), Some(crt)) = (&mut self.renderer, self.crt) {
+                    let post = renderer.post_process_mut();
+                    post.set_enabled(crt, !post.is_enabled(crt));
+                }
+            }
             WindowEvent::RedrawRequested => {
                 if let Some(renderer) = &mut self.renderer {
                     // Render shapes
@@ -81,6 +138,45 @@ impl ApplicationHandler for RenderApp {
                     renderer.queue_square(300.0, 100.0, 60.0, [0.0, 1.0, 0.0, 1.0]);
                     renderer.queue_circle(600.0, 150.0, 40.0, [0.0, 0.0, 1.0, 1.0]);
 
+                    // Lines and outlines
+                    renderer.queue_line((50.0, 450.0), (250.0, 500.0), 4.0, [1.0, 1.0, 1.0, 1.0]);
+                    renderer.queue_polyline(
+                        &[(300.0, 500.0), (350.0, 430.0), (400.0, 500.0), (450.0, 430.0)],
+                        &StrokeStyle::new(8.0).with_join(LineJoin::Round).with_cap(LineCap::Round),
+                        false,
+                        [0.0, 1.0, 1.0, 1.0],
+                    );
+                    renderer.queue_rectangle_outline(500.0, 420.0, 100.0, 80.0, 3.0, [1.0, 0.0, 1.0, 1.0]);
+                    renderer.queue_circle_outline(700.0, 460.0, 40.0, 2.0, [1.0, 1.0, 0.0, 1.0]);
+
+                    // Polygons and paths
+                    renderer.queue_polygon(
+                        &[(50.0, 550.0), (150.0, 550.0), (100.0, 580.0), (150.0, 620.0), (50.0, 620.0)],
+                        [1.0, 0.5, 0.0, 1.0],
+                    );
+                    let mut hill = ShapePath::new();
+                    hill.move_to(200.0, 620.0)
+                        .cubic_to((250.0, 520.0), (320.0, 640.0), 380.0, 560.0)
+                        .line_to(380.0, 620.0)
+                        .close();
+                    // A hole, cut out by the even-odd rule
+                    hill.add_rounded_rectangle(300.0, 590.0, 40.0, 20.0, 6.0);
+                    renderer.queue_path(&hill, FillRule::EvenOdd, [0.2, 0.8, 0.3, 1.0]);
+                    renderer.queue_rounded_rectangle(420.0, 550.0, 120.0, 60.0, 12.0, [0.3, 0.3, 0.8, 1.0]);
+
+                    // Gradients
+                    let sky = Gradient::linear((0.0, 0.0), (0.0, 40.0), [0.1, 0.1, 0.4, 1.0], [0.9, 0.5, 0.2, 1.0])
+                        .with_stop(0.7, [0.8, 0.3, 0.5, 1.0]);
+                    renderer.queue_rectangle_gradient(0.0, 0.0, 800.0, 40.0, &sky);
+                    let glow = Gradient::radial((700.0, 300.0), 50.0, [1.0, 1.0, 0.8, 1.0], [1.0, 0.5, 0.0, 0.0]);
+                    renderer.queue_circle_gradient(700.0, 300.0, 50.0, &glow);
+
+                    // Custom material, the uv goes from 0 to 1 across the quad
+                    renderer.set_time(self.started.elapsed().as_secs_f32());
+                    renderer.set_material(self.wave);
+                    renderer.queue_sprite(TextureId::WHITE, 600.0, 50.0, 150.0, 50.0, FULL_UV, [1.0; 4]);
+                    renderer.set_material(MaterialId::DEFAULT);
+
                     // Queue text
                     renderer.queue_text("Hello, WGPU!", (100.0, 300.0), 32.0, [1.0, 1.0, 1.0, 1.0]);
                     renderer.queue_text("Rectangle | Square | Circle", (350.0, 350.0), 16.0, [1.0, 1.0, 0.0, 1.0]);
@@ -0,0 +1,10 @@
+// Drawn additively into the light map, one quad per light. The vertex color is the light's
+// color times its intensity.
+
+@fragment
+fn main(@location(0) color: vec4<f32>, @location(1) uv: vec2<f32>) -> @location(0) vec4<f32> {
+    let distance = length(uv * 2.0 - 1.0);
+    // Squared so it fades out smoothly instead of ending in a hard ring
+    let falloff = clamp(1.0 - distance, 0.0, 1.0);
+    return vec4<f32>(color.rgb * falloff * falloff, 1.0);
+}
@@ -0,0 +1,8 @@
+// params[0].x: intensity, extra: the blurred bright parts
+
+@fragment
+fn main(@location(1) uv: vec2<f32>) -> @location(0) vec4<f32> {
+    let color = textureSample(source, source_sampler, uv).rgb;
+    let glow = textureSample(extra, extra_sampler, uv).rgb;
+    return vec4<f32>(color + glow * params[0].x, 1.0);
+}
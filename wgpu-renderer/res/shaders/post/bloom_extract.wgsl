@@ -0,0 +1,10 @@
+// params[0].x: brightness where the glow starts
+
+@fragment
+fn main(@location(1) uv: vec2<f32>) -> @location(0) vec4<f32> {
+    let color = textureSample(source, source_sampler, uv).rgb;
+    let threshold = params[0].x;
+    // Soft knee so things don't pop in and out of the glow
+    let amount = smoothstep(threshold, threshold + 0.1, luminance(color));
+    return vec4<f32>(color * amount, 1.0);
+}
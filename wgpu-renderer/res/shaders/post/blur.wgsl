@@ -0,0 +1,17 @@
+// params[0].xy: direction, params[0].z: how far apart the samples are in texels
+
+const WEIGHTS = array<f32, 5>(0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+@fragment
+fn main(@location(1) uv: vec2<f32>) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source));
+    let step = params[0].xy * params[0].z * texel;
+
+    var color = textureSample(source, source_sampler, uv).rgb * WEIGHTS[0];
+    for (var i = 1; i < 5; i++) {
+        let offset = step * f32(i);
+        color += textureSample(source, source_sampler, uv + offset).rgb * WEIGHTS[i];
+        color += textureSample(source, source_sampler, uv - offset).rgb * WEIGHTS[i];
+    }
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,11 @@
+// params[0].x: how far red and blue are pushed apart at the edges, in pixels
+
+@fragment
+fn main(@location(1) uv: vec2<f32>) -> @location(0) vec4<f32> {
+    // Stronger towards the edges, like a cheap lens
+    let offset = (uv - 0.5) * 2.0 * params[0].x / globals.screen_size;
+    let red = textureSample(source, source_sampler, uv + offset).r;
+    let green = textureSample(source, source_sampler, uv).g;
+    let blue = textureSample(source, source_sampler, uv - offset).b;
+    return vec4<f32>(red, green, blue, 1.0);
+}
@@ -0,0 +1,30 @@
+// params[0].x: intensity, extra: the lookup table, N*N wide and N tall, with blue picking the
+// N by N slice and red and green the position in it
+
+fn to_srgb(linear: vec3<f32>) -> vec3<f32> {
+    let low = linear * 12.92;
+    let high = 1.055 * pow(linear, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, linear <= vec3<f32>(0.0031308));
+}
+
+@fragment
+fn main(@location(1) uv: vec2<f32>) -> @location(0) vec4<f32> {
+    let color = textureSample(source, source_sampler, uv).rgb;
+
+    // Tables are made in sRGB, so look up by the sRGB color
+    let lookup = clamp(to_srgb(color), vec3<f32>(0.0), vec3<f32>(1.0));
+    let size = f32(textureDimensions(extra).y);
+    let blue = lookup.b * (size - 1.0);
+    let slice = floor(blue);
+    let x = (lookup.r * (size - 1.0) + 0.5) / (size * size);
+    let y = (lookup.g * (size - 1.0) + 0.5) / size;
+    let first = textureSample(extra, extra_sampler, vec2<f32>(x + slice / size, y)).rgb;
+    let second = textureSample(
+        extra,
+        extra_sampler,
+        vec2<f32>(x + min(slice + 1.0, size - 1.0) / size, y),
+    ).rgb;
+    let graded = mix(first, second, blue - slice);
+
+    return vec4<f32>(mix(color, graded, params[0].x), 1.0);
+}
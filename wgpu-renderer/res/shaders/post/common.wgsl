@@ -0,0 +1,20 @@
+// Prepended to every built-in post effect. The frame so far is `source`, and `extra` is a
+// second texture some effects need, like the color grading lookup table.
+
+struct Globals {
+    time: f32,
+    _padding: f32,
+    screen_size: vec2<f32>,
+}
+
+@group(1) @binding(0) var source: texture_2d<f32>;
+@group(1) @binding(1) var source_sampler: sampler;
+
+@group(2) @binding(0) var<uniform> globals: Globals;
+@group(2) @binding(1) var<uniform> params: array<vec4<f32>, 16>;
+@group(2) @binding(2) var extra: texture_2d<f32>;
+@group(2) @binding(6) var extra_sampler: sampler;
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
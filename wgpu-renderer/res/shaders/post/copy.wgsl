@@ -0,0 +1,4 @@
+@fragment
+fn main(@location(1) uv: vec2<f32>) -> @location(0) vec4<f32> {
+    return vec4<f32>(textureSample(source, source_sampler, uv).rgb, 1.0);
+}
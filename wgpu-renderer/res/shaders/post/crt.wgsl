@@ -0,0 +1,19 @@
+// params[0].x: scanline darkness, params[0].y: screen curvature
+
+@fragment
+fn main(@location(1) uv: vec2<f32>) -> @location(0) vec4<f32> {
+    // Bulge the picture out from the center like a tube screen
+    let centered = uv * 2.0 - 1.0;
+    let bent = centered * (1.0 + params[0].y * dot(centered.yx, centered.yx));
+    let curved = bent * 0.5 + 0.5;
+    // Sampled before the bounds check so it stays in uniform control flow
+    var color = textureSample(source, source_sampler, curved).rgb;
+    if (any(curved < vec2<f32>(0.0)) || any(curved > vec2<f32>(1.0))) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+
+    // One dark line every other pixel row
+    let scanline = 0.5 + 0.5 * cos(curved.y * globals.screen_size.y * 3.14159265);
+    color *= 1.0 - params[0].x * scanline;
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,8 @@
+// extra: the light map
+
+@fragment
+fn main(@location(1) uv: vec2<f32>) -> @location(0) vec4<f32> {
+    let color = textureSample(source, source_sampler, uv).rgb;
+    let light = textureSample(extra, extra_sampler, uv).rgb;
+    return vec4<f32>(color * light, 1.0);
+}
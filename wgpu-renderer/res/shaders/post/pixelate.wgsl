@@ -0,0 +1,11 @@
+// params[0].x: size of the blocks in pixels
+
+@fragment
+fn main(@location(1) uv: vec2<f32>) -> @location(0) vec4<f32> {
+    let size = max(params[0].x, 1.0);
+    let dimensions = vec2<f32>(textureDimensions(source));
+    // Every pixel in a block takes the color at the block's center
+    let block = floor(uv * dimensions / size) * size + size * 0.5;
+    let pixel = vec2<i32>(min(block, dimensions - 1.0));
+    return vec4<f32>(textureLoad(source, pixel, 0).rgb, 1.0);
+}
@@ -0,0 +1,12 @@
+// params[0].x: intensity, params[0].y: radius, params[0].z: softness
+
+@fragment
+fn main(@location(1) uv: vec2<f32>) -> @location(0) vec4<f32> {
+    let color = textureSample(source, source_sampler, uv).rgb;
+    // Round on screen rather than stretched with the aspect ratio
+    let aspect = globals.screen_size.x / max(globals.screen_size.y, 1.0);
+    let distance = length((uv - 0.5) * vec2<f32>(aspect, 1.0));
+    let radius = params[0].y;
+    let shade = 1.0 - smoothstep(radius - max(params[0].z, 0.001), radius, distance);
+    return vec4<f32>(color * mix(1.0, shade, params[0].x), 1.0);
+}
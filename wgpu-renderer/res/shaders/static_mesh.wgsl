@@ -0,0 +1,37 @@
+// Vertex shader for static meshes. Their vertices are in their own units, placed on screen
+// with a transform that's updated whenever the mesh is queued instead of rebuilding it.
+
+struct Transform {
+    screen_size: vec2<f32>,
+    // In pixels, where the mesh's origin ends up
+    offset: vec2<f32>,
+    scale: vec2<f32>,
+}
+
+@group(0) @binding(0) var<uniform> transform: Transform;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) tex_coords: vec2<f32>,
+}
+
+@vertex
+fn main(
+    @location(0) position: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) tex_coords: vec2<f32>,
+) -> VertexOutput {
+    let pixel = position * transform.scale + transform.offset;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(
+        pixel.x / transform.screen_size.x * 2.0 - 1.0,
+        1.0 - pixel.y / transform.screen_size.y * 2.0,
+        0.0,
+        1.0,
+    );
+    out.color = color;
+    out.tex_coords = tex_coords;
+    return out;
+}
@@ -1,9 +1,14 @@
 #version 450
 
 layout(location=0) in vec4 vColor;
+layout(location=1) in vec2 vTexCoords;
+
+layout(set=1, binding=0) uniform texture2D tDiffuse;
+layout(set=1, binding=1) uniform sampler sDiffuse;
 
 layout(location=0) out vec4 fColor;
 
 void main() {
-    fColor = vColor;
+    // Untextured shapes sample a 1x1 white texture, so this is just vColor for them
+    fColor = vColor * texture(sampler2D(tDiffuse, sDiffuse), vTexCoords);
 }
\ No newline at end of file
@@ -6,8 +6,10 @@ layout(binding = 0) uniform ScreenSize {
 
 layout(location = 0) in vec2 aPosition;
 layout(location = 1) in vec4 aColor;
+layout(location = 2) in vec2 aTexCoords;
 
 layout(location = 0) out vec4 vColor;
+layout(location = 1) out vec2 vTexCoords;
 
 void main() {
     float ndc_x = (aPosition.x / screen_size.x) * 2.0 - 1.0;
@@ -15,4 +17,5 @@ void main() {
     
     gl_Position = vec4(ndc_x, ndc_y, 0.0, 1.0);
     vColor = aColor;
+    vTexCoords = aTexCoords;
 }
\ No newline at end of file
@@ -0,0 +1,140 @@
+//! Restricting drawing to part of the screen, for scrollable panels, minimaps and the like.
+//!
+//! Clip rectangles are pushed and popped on the renderer and become scissor rects when the
+//! frame is drawn. Masks are for any other shape: geometry queued between `begin_mask` and
+//! `apply_mask` is written to the stencil buffer instead of the screen, and later draws only
+//! show up inside (or outside) of it.
+
+use wgpu::{
+    CompareFunction, DepthStencilState, Device, StencilFaceState, StencilOperation, StencilState,
+    TextureFormat, TextureView,
+};
+
+pub(crate) const STENCIL_FORMAT: TextureFormat = TextureFormat::Stencil8;
+
+/// A rectangle in screen pixels that drawing is restricted to
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ClipRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl ClipRect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The area both rectangles cover, zero sized if they don't overlap
+    pub fn intersect(&self, other: &ClipRect) -> ClipRect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        ClipRect::new(x, y, (right - x).max(0.0), (bottom - y).max(0.0))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0.0 || self.height <= 0.0
+    }
+
+    /// Whole pixels inside a target of the given size as (x, y, width, height), `None` if
+    /// nothing is left
+    pub(crate) fn scissor(&self, target_width: u32, target_height: u32) -> Option<[u32; 4]> {
+        let x = (self.x.round().max(0.0) as u32).min(target_width);
+        let y = (self.y.round().max(0.0) as u32).min(target_height);
+        let right = ((self.x + self.width).round().max(0.0) as u32).min(target_width);
+        let bottom = ((self.y + self.height).round().max(0.0) as u32).min(target_height);
+        (right > x && bottom > y).then_some([x, y, right - x, bottom - y])
+    }
+}
+
+/// Which side of the mask draws show up on, see `Renderer::apply_mask`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MaskMode {
+    #[default]
+    Inside,
+    Outside,
+}
+
+// How a pipeline uses the stencil buffer. Every pipeline in a pass has to match its
+// attachments, so unmasked draws in a frame with masks need a pipeline that ignores it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Stencil {
+    Ignore,
+    Write,
+    Test(MaskMode),
+}
+
+impl Stencil {
+    pub(crate) fn state(self) -> DepthStencilState {
+        let (compare, pass_op) = match self {
+            Stencil::Ignore => (CompareFunction::Always, StencilOperation::Keep),
+            Stencil::Write => (CompareFunction::Always, StencilOperation::Replace),
+            Stencil::Test(MaskMode::Inside) => (CompareFunction::Equal, StencilOperation::Keep),
+            Stencil::Test(MaskMode::Outside) => (CompareFunction::NotEqual, StencilOperation::Keep),
+        };
+        let face = StencilFaceState {
+            compare,
+            fail_op: StencilOperation::Keep,
+            depth_fail_op: StencilOperation::Keep,
+            pass_op,
+        };
+        DepthStencilState {
+            format: STENCIL_FORMAT,
+            depth_write_enabled: false,
+            depth_compare: CompareFunction::Always,
+            stencil: StencilState {
+                front: face,
+                back: face,
+                read_mask: 0xff,
+                write_mask: 0xff,
+            },
+            bias: Default::default(),
+        }
+    }
+
+    /// Mask shapes only touch the stencil buffer
+    pub(crate) fn writes_color(self) -> bool {
+        self != Stencil::Write
+    }
+}
+
+/// Stencil buffer for the main pass, only made once a frame uses a mask
+pub(crate) struct StencilTarget {
+    pub(crate) view: TextureView,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+impl StencilTarget {
+    pub(crate) fn new(device: &Device, width: u32, height: u32) -> Self {
+        let texture = device.create_texture(
+            &(wgpu::TextureDescriptor {
+                label: Some("Stencil Texture"),
+                size: wgpu::Extent3d {
+                    width: width.max(1),
+                    height: height.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: STENCIL_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            }),
+        );
+        Self {
+            view: texture.create_view(&Default::default()),
+            width,
+            height,
+        }
+    }
+}
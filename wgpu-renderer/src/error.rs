@@ -1,3 +1,5 @@
+use std::{fmt, path::PathBuf};
+
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -11,7 +13,53 @@ pub enum RenderError {
     #[error("Render pass error: {0}")]
     RenderPassError(String),
     #[error("Shader compilation error: {0}")]
-    ShaderError(String),
+    ShaderError(ShaderDiagnostic),
     #[error("Resource creation error: {0}")]
     ResourceError(String),
 }
+
+/// What went wrong compiling a shader, and where
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShaderDiagnostic {
+    pub message: String,
+    /// The shader's file, if it was loaded from one
+    pub path: Option<PathBuf>,
+    /// 1-based, `None` for errors that aren't about a specific line
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    /// The full compiler output, with the offending code underlined
+    pub report: String,
+}
+
+// path:line:column: message, like most compilers
+impl fmt::Display for ShaderDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "{}:", path.display())?;
+        }
+        if let Some(line) = self.line {
+            write!(f, "{line}:")?;
+            if let Some(column) = self.column {
+                write!(f, "{column}:")?;
+            }
+        }
+        if self.path.is_some() || self.line.is_some() {
+            write!(f, " ")?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl ShaderDiagnostic {
+    /// An error that has nothing to point at, like a pipeline that doesn't fit its shaders
+    pub(crate) fn new(message: impl Into<String>) -> Self {
+        let message = message.into();
+        Self {
+            report: message.clone(),
+            message,
+            path: None,
+            line: None,
+            column: None,
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! Linear and radial gradient fills.
+//!
+//! Colors are interpolated between vertices by the GPU, which is only linear across each
+//! triangle. So shapes are cut up along every color stop before coloring them, and radial
+//! gradients are also cut into thin wedges that each get a straight gradient.
+
+use std::f32::consts::TAU;
+
+use crate::{shapes::CurveQuality, types::Vertex};
+
+type Point = (f32, f32);
+
+#[derive(Clone, Debug, PartialEq)]
+enum GradientKind {
+    Linear { start: Point, end: Point },
+    Radial { center: Point, radius: f32 },
+}
+
+/// Colors that blend across a shape, for the `queue_*_gradient` methods
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    kind: GradientKind,
+    // (offset, color), sorted by offset
+    stops: Vec<(f32, [f32; 4])>,
+}
+
+impl Gradient {
+    /// Blends from `from` at `start` to `to` at `end`. Positions are in pixels, like the
+    /// shapes themselves, and everything past either end gets that end's color.
+    pub fn linear(start: Point, end: Point, from: [f32; 4], to: [f32; 4]) -> Self {
+        Self {
+            kind: GradientKind::Linear { start, end },
+            stops: vec![(0.0, from), (1.0, to)],
+        }
+    }
+
+    /// Blends from `inner` at `center` to `outer` at `radius` pixels away
+    pub fn radial(center: Point, radius: f32, inner: [f32; 4], outer: [f32; 4]) -> Self {
+        Self {
+            kind: GradientKind::Radial {
+                center,
+                radius: radius.max(f32::EPSILON),
+            },
+            stops: vec![(0.0, inner), (1.0, outer)],
+        }
+    }
+
+    /// Extra color at `offset`, 0 being the start (or center) and 1 the end (or radius)
+    pub fn with_stop(mut self, offset: f32, color: [f32; 4]) -> Self {
+        let offset = offset.clamp(0.0, 1.0);
+        let index = self.stops.partition_point(|&(o, _)| o <= offset);
+        self.stops.insert(index, (offset, color));
+        self
+    }
+
+    /// The color at a point
+    pub fn color_at(&self, x: f32, y: f32) -> [f32; 4] {
+        let offset = match self.kind {
+            GradientKind::Linear { .. } => self.linear_offset((x, y)),
+            GradientKind::Radial { center, radius } => {
+                let (dx, dy) = (x - center.0, y - center.1);
+                (dx * dx + dy * dy).sqrt() / radius
+            }
+        };
+        self.color_at_offset(offset)
+    }
+
+    fn color_at_offset(&self, offset: f32) -> [f32; 4] {
+        let index = self.stops.partition_point(|&(o, _)| o <= offset);
+        match (
+            index.checked_sub(1).map(|i| self.stops[i]),
+            self.stops.get(index),
+        ) {
+            (Some((a, from)), Some(&(b, to))) if b > a => {
+                let t = (offset - a) / (b - a);
+                std::array::from_fn(|i| from[i] + (to[i] - from[i]) * t)
+            }
+            (Some((_, color)), _) | (None, Some(&(_, color))) => color,
+            (None, None) => [1.0; 4],
+        }
+    }
+
+    fn linear_offset(&self, p: Point) -> f32 {
+        let GradientKind::Linear { start, end } = self.kind else {
+            return 0.0;
+        };
+        let axis = scaled_axis(start, end);
+        (p.0 - start.0) * axis.0 + (p.1 - start.1) * axis.1
+    }
+
+    /// Cut up triangles so every piece is colored exactly by its corners
+    pub(crate) fn fill(
+        &self,
+        positions: &[Point],
+        indices: &[u32],
+        quality: &CurveQuality,
+    ) -> (Vec<Vertex>, Vec<u32>) {
+        let mut vertices = Vec::new();
+        let mut out_indices = Vec::new();
+
+        // Each region is a wedge to clip to plus the straight gradient used inside it
+        let regions: Vec<Region> = match self.kind {
+            GradientKind::Linear { start, end } => vec![Region {
+                bounds: Vec::new(),
+                origin: start,
+                axis: scaled_axis(start, end),
+            }],
+            GradientKind::Radial { center, radius } => {
+                let count = quality.segments(radius, TAU).max(3);
+                let step = TAU / count as f32;
+                (0..count)
+                    .map(|i| {
+                        let (from, to) = (i as f32 * step, (i + 1) as f32 * step);
+                        let middle = (from + to) / 2.0;
+                        // Measured along the wedge's middle, so the gradient runs straight
+                        // across it and matches its neighbours where they meet
+                        let axis = (middle.cos() / radius, middle.sin() / radius);
+                        Region {
+                            bounds: vec![
+                                HalfPlane::through(center, (-from.sin(), from.cos())),
+                                HalfPlane::through(center, (to.sin(), -to.cos())),
+                            ],
+                            origin: center,
+                            axis,
+                        }
+                    })
+                    .collect()
+            }
+        };
+
+        for triangle in indices.chunks_exact(3) {
+            let corners: Vec<Point> = triangle.iter().map(|&i| positions[i as usize]).collect();
+
+            for region in &regions {
+                let Some(piece) = region
+                    .bounds
+                    .iter()
+                    .try_fold(corners.clone(), |polygon, plane| clip(&polygon, plane))
+                else {
+                    continue;
+                };
+
+                // One band between each pair of stops, plus the solid ends. A gradient with
+                // no length is a single solid band.
+                let offset_planes: Vec<HalfPlane> = if region.axis == (0.0, 0.0) {
+                    Vec::new()
+                } else {
+                    self.stops
+                        .iter()
+                        .map(|&(offset, _)| region.offset_plane(offset))
+                        .collect()
+                };
+                for band in 0..=offset_planes.len() {
+                    let mut polygon = Some(piece.clone());
+                    if band > 0 {
+                        polygon = polygon.and_then(|p| clip(&p, &offset_planes[band - 1]));
+                    }
+                    if band < offset_planes.len() {
+                        polygon = polygon.and_then(|p| clip(&p, &offset_planes[band].flipped()));
+                    }
+                    let Some(polygon) = polygon else {
+                        continue;
+                    };
+
+                    let first = vertices.len() as u32;
+                    vertices.extend(polygon.iter().map(|&(x, y)| {
+                        Vertex::with_color(x, y, self.color_at_offset(region.offset((x, y))))
+                    }));
+                    for i in 1..polygon.len() as u32 - 1 {
+                        out_indices.extend([first, first + i, first + i + 1]);
+                    }
+                }
+            }
+        }
+
+        (vertices, out_indices)
+    }
+}
+
+// Inside where a * x + b * y + c >= 0
+#[derive(Clone, Copy, Debug)]
+struct HalfPlane {
+    a: f32,
+    b: f32,
+    c: f32,
+}
+
+impl HalfPlane {
+    // The side of the line through `point` that `normal` points to
+    fn through(point: Point, normal: Point) -> Self {
+        Self {
+            a: normal.0,
+            b: normal.1,
+            c: -(normal.0 * point.0 + normal.1 * point.1),
+        }
+    }
+
+    fn flipped(self) -> Self {
+        Self {
+            a: -self.a,
+            b: -self.b,
+            c: -self.c,
+        }
+    }
+
+    fn distance(&self, (x, y): Point) -> f32 {
+        self.a * x + self.b * y + self.c
+    }
+}
+
+struct Region {
+    bounds: Vec<HalfPlane>,
+    origin: Point,
+    // Scaled so the dot product with (point - origin) is the gradient offset
+    axis: Point,
+}
+
+impl Region {
+    fn offset(&self, p: Point) -> f32 {
+        (p.0 - self.origin.0) * self.axis.0 + (p.1 - self.origin.1) * self.axis.1
+    }
+
+    // Where the offset is at least `offset`
+    fn offset_plane(&self, offset: f32) -> HalfPlane {
+        HalfPlane {
+            a: self.axis.0,
+            b: self.axis.1,
+            c: -(self.origin.0 * self.axis.0 + self.origin.1 * self.axis.1) - offset,
+        }
+    }
+}
+
+fn scaled_axis(start: Point, end: Point) -> Point {
+    let along = (end.0 - start.0, end.1 - start.1);
+    let length_squared = along.0 * along.0 + along.1 * along.1;
+    if length_squared <= f32::EPSILON {
+        return (0.0, 0.0);
+    }
+    (along.0 / length_squared, along.1 / length_squared)
+}
+
+// Sutherland-Hodgman against a single plane. None when less than a triangle is left.
+fn clip(polygon: &[Point], plane: &HalfPlane) -> Option<Vec<Point>> {
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for (i, &current) in polygon.iter().enumerate() {
+        let next = polygon[(i + 1) % polygon.len()];
+        let (d_current, d_next) = (plane.distance(current), plane.distance(next));
+
+        if d_current >= 0.0 {
+            clipped.push(current);
+        }
+        if (d_current >= 0.0) != (d_next >= 0.0) {
+            let t = d_current / (d_current - d_next);
+            clipped.push((
+                current.0 + (next.0 - current.0) * t,
+                current.1 + (next.1 - current.1) * t,
+            ));
+        }
+    }
+    (clipped.len() >= 3).then_some(clipped)
+}
@@ -2,23 +2,31 @@
 
 use std::sync::Arc;
 
+use naga::ShaderStage;
 #[cfg(not(target_arch = "wasm32"))]
 use wgpu::Backends;
 use wgpu::{
-    Adapter, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
-    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, BlendComponent, BlendState,
-    Buffer, BufferBindingType, BufferDescriptor, BufferUsages, ColorTargetState, ColorWrites,
-    Device, DeviceDescriptor, Features, FragmentState, FrontFace, Instance, InstanceDescriptor,
-    MultisampleState, PipelineLayout, PipelineLayoutDescriptor, PolygonMode, PowerPreference,
-    PrimitiveState, PrimitiveTopology, Queue, RenderPipeline, RenderPipelineDescriptor,
-    RequestAdapterOptions, ShaderModule, ShaderModuleDescriptor, ShaderSource, ShaderStages,
-    Surface, SurfaceConfiguration, TextureFormat, TextureUsages, VertexBufferLayout, VertexState,
+    Adapter, AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, BlendState, Buffer,
+    BufferAddress, BufferBindingType, BufferDescriptor, BufferUsages, ColorTargetState,
+    ColorWrites, Device, DeviceDescriptor, Features, FilterMode, FragmentState, FrontFace,
+    Instance, InstanceDescriptor, MultisampleState, PipelineLayout, PipelineLayoutDescriptor,
+    PolygonMode, PowerPreference, PrimitiveState, PrimitiveTopology, Queue, RenderPipeline,
+    RenderPipelineDescriptor, RequestAdapterOptions, Sampler, SamplerBindingType,
+    SamplerDescriptor, ShaderModule, ShaderModuleDescriptor, ShaderSource, ShaderStages, Surface,
+    SurfaceConfiguration, TextureFormat, TextureSampleType, TextureUsages, TextureViewDimension,
+    VertexBufferLayout, VertexState,
     util::{BufferInitDescriptor, DeviceExt},
 };
 use wgpu_glyph::ab_glyph;
 use winit::{dpi::PhysicalSize, window::Window};
 
-use crate::types::{U32_SIZE, Vertex};
+use crate::{
+    clip::Stencil,
+    material::MAX_MATERIAL_TEXTURES,
+    shader::{self, ShaderCode},
+    types::{TextureFilter, U32_SIZE, Vertex},
+};
 
 const FONT_BYTES: &[u8] = include_bytes!("../res/fonts/PressStart2P-Regular.ttf");
 
@@ -112,37 +120,153 @@ pub(crate) fn create_bind_group_layout(device: &Device) -> BindGroupLayout {
     )
 }
 
+/// Layout for group 1: a texture and the sampler it's read with
+pub(crate) fn create_texture_bind_group_layout(device: &Device) -> BindGroupLayout {
+    device.create_bind_group_layout(
+        &(BindGroupLayoutDescriptor {
+            label: Some("Texture BGL"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        }),
+    )
+}
+
+pub(crate) fn create_sampler(device: &Device, filter: TextureFilter) -> Sampler {
+    let filter_mode = match filter {
+        TextureFilter::Linear => FilterMode::Linear,
+        TextureFilter::Nearest => FilterMode::Nearest,
+    };
+
+    device.create_sampler(
+        &(SamplerDescriptor {
+            label: Some("Texture Sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: filter_mode,
+            min_filter: filter_mode,
+            mipmap_filter: FilterMode::Nearest,
+            ..Default::default()
+        }),
+    )
+}
+
 pub(crate) fn create_pipeline_layout(
     device: &Device,
-    bind_group_layout: &BindGroupLayout,
+    bind_group_layouts: &[&BindGroupLayout],
 ) -> PipelineLayout {
     device.create_pipeline_layout(
         &(PipelineLayoutDescriptor {
-            bind_group_layouts: &[bind_group_layout],
+            bind_group_layouts,
             push_constant_ranges: &[],
             label: Some("Pipeline Layout"),
         }),
     )
 }
 
+/// The built-in shaders, compiled from GLSL the same way user shaders are
 pub(crate) fn create_shader_modules(device: &Device) -> (ShaderModule, ShaderModule) {
-    let vert_shader = device.create_shader_module(ShaderModuleDescriptor {
-        label: Some("vertex shader"),
-        source: ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!(
-            "../res/shaders/textured.vert.wgsl"
-        ))),
-    });
+    let compile = |source: &str, stage| {
+        shader::compile(&ShaderCode::glsl(source), stage)
+            .unwrap_or_else(|e| panic!("Built-in shader doesn't compile: {e}"))
+            .wgsl
+    };
 
-    let frag_shader = device.create_shader_module(ShaderModuleDescriptor {
-        label: Some("fragment shader"),
-        source: ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!(
-            "../res/shaders/textured.frag.wgsl"
-        ))),
-    });
+    let vert_shader = create_wgsl_module(
+        device,
+        "vertex shader",
+        &compile(
+            include_str!("../res/shaders/textured.vert"),
+            ShaderStage::Vertex,
+        ),
+    );
+    let frag_shader = create_wgsl_module(
+        device,
+        "fragment shader",
+        &compile(
+            include_str!("../res/shaders/textured.frag"),
+            ShaderStage::Fragment,
+        ),
+    );
 
     (vert_shader, frag_shader)
 }
 
+pub(crate) fn create_wgsl_module(device: &Device, label: &str, source: &str) -> ShaderModule {
+    device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(label),
+        source: ShaderSource::Wgsl(std::borrow::Cow::Owned(source.to_string())),
+    })
+}
+
+/// Layout for group 2 of material pipelines, see the `material` module for what's in it
+pub(crate) fn create_material_bind_group_layout(device: &Device) -> BindGroupLayout {
+    let uniform = |binding| BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::VERTEX_FRAGMENT,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    };
+    let texture = |binding| BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::FRAGMENT,
+        ty: BindingType::Texture {
+            sample_type: TextureSampleType::Float { filterable: true },
+            view_dimension: TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    };
+
+    let mut entries = vec![uniform(0), uniform(1)];
+    entries.extend((0..MAX_MATERIAL_TEXTURES as u32).map(|i| texture(2 + i)));
+    entries.push(BindGroupLayoutEntry {
+        binding: 2 + MAX_MATERIAL_TEXTURES as u32,
+        visibility: ShaderStages::FRAGMENT,
+        ty: BindingType::Sampler(SamplerBindingType::Filtering),
+        count: None,
+    });
+
+    device.create_bind_group_layout(
+        &(BindGroupLayoutDescriptor {
+            label: Some("Material BGL"),
+            entries: &entries,
+        }),
+    )
+}
+
+/// Time and screen size for material shaders, rewritten every frame
+pub(crate) fn create_globals_buffer(device: &Device) -> Buffer {
+    device.create_buffer(
+        &(BufferDescriptor {
+            label: Some("Material Globals Buffer"),
+            size: 4 * 4,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }),
+    )
+}
+
 pub(crate) fn create_screen_size_buffer(device: &Device, size: PhysicalSize<u32>) -> Buffer {
     device.create_buffer_init(
         &(BufferInitDescriptor {
@@ -154,25 +278,32 @@ pub(crate) fn create_screen_size_buffer(device: &Device, size: PhysicalSize<u32>
 }
 
 pub(crate) fn create_vertex_and_index_buffers(device: &Device) -> (Buffer, Buffer) {
-    let vertex_buffer = device.create_buffer(
+    (
+        create_vertex_buffer(device, Vertex::SIZE * 256),
+        create_index_buffer(device, U32_SIZE * 512),
+    )
+}
+
+pub(crate) fn create_vertex_buffer(device: &Device, size: BufferAddress) -> Buffer {
+    device.create_buffer(
         &(BufferDescriptor {
             label: None,
-            size: Vertex::SIZE * 256,
+            size,
             usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
             mapped_at_creation: false,
         }),
-    );
+    )
+}
 
-    let index_buffer = device.create_buffer(
+pub(crate) fn create_index_buffer(device: &Device, size: BufferAddress) -> Buffer {
+    device.create_buffer(
         &(BufferDescriptor {
             label: None,
-            size: U32_SIZE * 512,
+            size,
             usage: BufferUsages::INDEX | BufferUsages::COPY_DST,
             mapped_at_creation: false,
         }),
-    );
-
-    (vertex_buffer, index_buffer)
+    )
 }
 
 pub(crate) fn create_bind_group(
@@ -192,34 +323,41 @@ pub(crate) fn create_bind_group(
     )
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn create_render_pipeline(
     device: &Device,
     pipeline_layout: &PipelineLayout,
     surface_format: TextureFormat,
     vertex_layouts: &[VertexBufferLayout],
-    vert_shader: ShaderModule,
-    frag_shader: ShaderModule,
+    vert_shader: &ShaderModule,
+    vert_entry_point: &str,
+    frag_shader: &ShaderModule,
+    frag_entry_point: &str,
+    blend: BlendState,
+    // None for passes without a stencil buffer
+    stencil: Option<Stencil>,
 ) -> RenderPipeline {
     device.create_render_pipeline(
         &(RenderPipelineDescriptor {
             label: Some("Render Pipeline"),
             layout: Some(pipeline_layout),
             vertex: VertexState {
-                module: &vert_shader,
-                entry_point: Some("main"),
+                module: vert_shader,
+                entry_point: Some(vert_entry_point),
                 buffers: vertex_layouts,
                 compilation_options: Default::default(),
             },
             fragment: Some(FragmentState {
-                module: &frag_shader,
-                entry_point: Some("main"),
+                module: frag_shader,
+                entry_point: Some(frag_entry_point),
                 targets: &[Some(ColorTargetState {
                     format: surface_format,
-                    blend: Some(BlendState {
-                        alpha: BlendComponent::REPLACE,
-                        color: BlendComponent::REPLACE,
-                    }),
-                    write_mask: ColorWrites::ALL,
+                    blend: Some(blend),
+                    write_mask: if stencil.is_none_or(Stencil::writes_color) {
+                        ColorWrites::ALL
+                    } else {
+                        ColorWrites::empty()
+                    },
                 })],
                 compilation_options: Default::default(),
             }),
@@ -232,7 +370,7 @@ pub(crate) fn create_render_pipeline(
                 unclipped_depth: false,
                 conservative: false,
             },
-            depth_stencil: None,
+            depth_stencil: stencil.map(Stencil::state),
             multisample: MultisampleState {
                 count: 1,
                 mask: !0,
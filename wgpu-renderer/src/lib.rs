@@ -1,8 +1,32 @@
+pub mod clip;
 pub mod error;
+pub mod gradient;
+pub mod lighting;
+pub mod material;
+pub mod mesh;
+pub mod nine_slice;
+pub mod path;
+pub mod postprocess;
 pub mod renderer;
+pub mod shader;
+pub mod shapes;
+pub mod text;
 pub mod types;
 pub(crate) mod init;
+pub(crate) mod texture;
 
-pub use error::RenderError;
-pub use renderer::Renderer;
+pub use clip::{ClipRect, MaskMode};
+pub use error::{RenderError, ShaderDiagnostic};
+pub use gradient::Gradient;
+pub use lighting::Light;
+pub use material::{MaterialDescriptor, MaterialId};
+pub use mesh::StaticMeshId;
+pub use nine_slice::{NineSlice, SliceFill};
+pub use path::{FillRule, ShapePath};
+pub use postprocess::{PostEffect, PostEffectId, PostProcess};
+pub use renderer::{QUAD_INDICES, Renderer};
+pub use shader::{ShaderCode, ShaderLanguage};
+pub use shapes::{LineCap, LineJoin, StrokeStyle};
+pub use text::{FontId, HorizontalAlign, TextBounds, TextSection, TextSpan, VerticalAlign};
 pub use types::*;
+pub use wgpu_glyph::ab_glyph::FontArc;
@@ -0,0 +1,301 @@
+//! 2D lighting.
+//!
+//! Lights are drawn additively into a light map that starts out as the ambient color, then the
+//! frame is multiplied by it. Text is drawn after that, so it's never darkened. Lighting is off
+//! until `Renderer::set_ambient_light` is given a color.
+//!
+//! Shadows are worked out on the CPU. Rays are cast from the light towards every occluder
+//! corner, and the light is drawn as the polygon they outline instead of a plain quad.
+
+use std::f32::consts::TAU;
+
+use wgpu::{BlendComponent, BlendFactor, BlendOperation, BlendState, TextureFormat};
+
+use crate::{
+    path::{self, FillRule, ShapePath},
+    types::Vertex,
+};
+
+/// How many times a light with soft shadows is drawn, from points spread around its center
+const SOFT_SHADOW_SAMPLES: usize = 8;
+/// Rays are cast this far, in radians, to either side of occluder corners to see past them
+const CORNER_OFFSET: f32 = 0.0001;
+
+/// Float so lights can add up past white without clipping
+pub(crate) const LIGHT_MAP_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+pub(crate) const ADDITIVE_BLENDING: BlendState = BlendState {
+    color: BlendComponent {
+        src_factor: BlendFactor::One,
+        dst_factor: BlendFactor::One,
+        operation: BlendOperation::Add,
+    },
+    alpha: BlendComponent {
+        src_factor: BlendFactor::One,
+        dst_factor: BlendFactor::One,
+        operation: BlendOperation::Add,
+    },
+};
+
+/// A round light for `Renderer::queue_light`, fading out from its center to `radius`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Light {
+    /// Center in pixels
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    /// Alpha is ignored, use `intensity` to dim a light
+    pub color: [f32; 4],
+    /// Brightness at the center. Above 1 lights things brighter than their own color.
+    pub intensity: f32,
+    /// Whether occluders block this light
+    pub shadows: bool,
+    /// Size of the light itself in pixels. Bigger lights cast blurrier shadow edges, 0 gives
+    /// sharp ones.
+    pub softness: f32,
+}
+
+impl Light {
+    /// A white light casting sharp shadows
+    pub fn new(x: f32, y: f32, radius: f32) -> Self {
+        Self {
+            x,
+            y,
+            radius,
+            color: [1.0; 4],
+            intensity: 1.0,
+            shadows: true,
+            softness: 0.0,
+        }
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn with_shadows(mut self, shadows: bool) -> Self {
+        self.shadows = shadows;
+        self
+    }
+
+    pub fn with_softness(mut self, softness: f32) -> Self {
+        self.softness = softness.max(0.0);
+        self
+    }
+
+    /// Triangles lighting up everything the light can see, with texture coordinates going from
+    /// 0 to 1 across its full radius
+    pub(crate) fn vertices(&self, occluders: &[Occluder], vertices: &mut Vec<Vertex>) {
+        // Occluders around the light itself are ignored, or it couldn't shine at all
+        let reach = self.radius + self.softness;
+        let occluders: Vec<&Occluder> = occluders
+            .iter()
+            .filter(|_| self.shadows)
+            .filter(|occluder| occluder.overlaps(self.x, self.y, reach))
+            .filter(|occluder| !occluder.contains(self.x, self.y))
+            .collect();
+
+        let color = |scale: f32| {
+            [
+                self.color[0] * self.intensity * scale,
+                self.color[1] * self.intensity * scale,
+                self.color[2] * self.intensity * scale,
+                1.0,
+            ]
+        };
+        let vertex = |(x, y): (f32, f32), color: [f32; 4]| {
+            let u = (x - self.x) / (self.radius * 2.0) + 0.5;
+            let v = (y - self.y) / (self.radius * 2.0) + 0.5;
+            Vertex::with_tex_coords(x, y, u, v, color)
+        };
+
+        if occluders.is_empty() {
+            let color = color(1.0);
+            let (left, top) = (self.x - self.radius, self.y - self.radius);
+            let (right, bottom) = (self.x + self.radius, self.y + self.radius);
+            for corner in [
+                (left, top),
+                (right, top),
+                (right, bottom),
+                (left, top),
+                (right, bottom),
+                (left, bottom),
+            ] {
+                vertices.push(vertex(corner, color));
+            }
+            return;
+        }
+
+        let mut segments: Vec<Segment> = occluders
+            .iter()
+            .flat_map(|occluder| occluder.edges())
+            .collect();
+
+        // Soft shadows are the light seen from a ring of points, each adding its share
+        let samples = if self.softness > 0.0 {
+            SOFT_SHADOW_SAMPLES
+        } else {
+            1
+        };
+        let sample_color = color(1.0 / samples as f32);
+        for sample in 0..samples {
+            let angle = sample as f32 / samples as f32 * TAU;
+            let origin = (
+                self.x + self.softness * angle.cos(),
+                self.y + self.softness * angle.sin(),
+            );
+
+            let polygon = visibility_polygon(origin, reach, &mut segments);
+            for (i, &point) in polygon.iter().enumerate() {
+                let next = polygon[(i + 1) % polygon.len()];
+                vertices.push(vertex(origin, sample_color));
+                vertices.push(vertex(point, sample_color));
+                vertices.push(vertex(next, sample_color));
+            }
+        }
+
+        // The rays stop at an occluder's edge, so light its inside too or it'd be left dark
+        let color = color(1.0);
+        for occluder in occluders {
+            vertices.extend(occluder.triangles.iter().map(|&point| vertex(point, color)));
+        }
+    }
+}
+
+type Segment = ((f32, f32), (f32, f32));
+
+/// A shape that casts shadows, queued with `Renderer::queue_occluder`
+pub(crate) struct Occluder {
+    points: Vec<(f32, f32)>,
+    // The outline filled in, three points per triangle
+    triangles: Vec<(f32, f32)>,
+    min: (f32, f32),
+    max: (f32, f32),
+}
+
+impl Occluder {
+    pub(crate) fn new(points: &[(f32, f32)], tolerance: f32) -> Self {
+        let buffers = path::fill(
+            &ShapePath::polygon(points),
+            FillRule::default(),
+            tolerance,
+            [1.0; 4],
+        );
+        let triangles = buffers
+            .indices
+            .iter()
+            .map(|&index| {
+                let position = buffers.vertices[index as usize].position;
+                (position[0], position[1])
+            })
+            .collect();
+
+        let min = points.iter().fold((f32::MAX, f32::MAX), |min, &(x, y)| {
+            (min.0.min(x), min.1.min(y))
+        });
+        let max = points.iter().fold((f32::MIN, f32::MIN), |max, &(x, y)| {
+            (max.0.max(x), max.1.max(y))
+        });
+
+        Self {
+            points: points.to_vec(),
+            triangles,
+            min,
+            max,
+        }
+    }
+
+    fn overlaps(&self, x: f32, y: f32, radius: f32) -> bool {
+        self.min.0 < x + radius
+            && self.max.0 > x - radius
+            && self.min.1 < y + radius
+            && self.max.1 > y - radius
+    }
+
+    /// Even-odd, same as the fill
+    fn contains(&self, x: f32, y: f32) -> bool {
+        let mut inside = false;
+        for ((x1, y1), (x2, y2)) in self.edges() {
+            if (y1 > y) != (y2 > y) && x < x1 + (y - y1) / (y2 - y1) * (x2 - x1) {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
+    fn edges(&self) -> impl Iterator<Item = Segment> + '_ {
+        self.points
+            .iter()
+            .enumerate()
+            .map(|(i, &point)| (point, self.points[(i + 1) % self.points.len()]))
+    }
+}
+
+/// The area seen from `origin` within a square reaching `reach` out, as points going around it.
+/// The square's sides are added to `segments` while casting and taken back off after.
+fn visibility_polygon(
+    origin: (f32, f32),
+    reach: f32,
+    segments: &mut Vec<Segment>,
+) -> Vec<(f32, f32)> {
+    let (left, top) = (origin.0 - reach, origin.1 - reach);
+    let (right, bottom) = (origin.0 + reach, origin.1 + reach);
+    let bounds = [
+        ((left, top), (right, top)),
+        ((right, top), (right, bottom)),
+        ((right, bottom), (left, bottom)),
+        ((left, bottom), (left, top)),
+    ];
+    segments.extend(bounds);
+
+    let mut angles: Vec<f32> = segments
+        .iter()
+        .flat_map(|&(start, _)| {
+            let angle = (start.1 - origin.1).atan2(start.0 - origin.0);
+            [angle - CORNER_OFFSET, angle, angle + CORNER_OFFSET]
+        })
+        .collect();
+    angles.sort_by(f32::total_cmp);
+
+    let polygon = angles
+        .into_iter()
+        .filter_map(|angle| {
+            let direction = (angle.cos(), angle.sin());
+            segments
+                .iter()
+                .filter_map(|&segment| cast_ray(origin, direction, segment))
+                .min_by(f32::total_cmp)
+                .map(|distance| {
+                    (
+                        origin.0 + direction.0 * distance,
+                        origin.1 + direction.1 * distance,
+                    )
+                })
+        })
+        .collect();
+
+    segments.truncate(segments.len() - bounds.len());
+    polygon
+}
+
+/// How far along the ray it hits the segment, if it does
+fn cast_ray(origin: (f32, f32), direction: (f32, f32), (start, end): Segment) -> Option<f32> {
+    let cross = |a: (f32, f32), b: (f32, f32)| a.0 * b.1 - a.1 * b.0;
+
+    let edge = (end.0 - start.0, end.1 - start.1);
+    let denominator = cross(direction, edge);
+    if denominator.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let to_start = (start.0 - origin.0, start.1 - origin.1);
+    let distance = cross(to_start, edge) / denominator;
+    let along = cross(to_start, direction) / denominator;
+    (distance >= 0.0 && (0.0..=1.0).contains(&along)).then_some(distance)
+}
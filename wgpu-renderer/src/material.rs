@@ -0,0 +1,148 @@
+//! Materials: custom shaders drawn through the same vertex format and batching as everything
+//! else. Set one with `Renderer::set_material` and whatever is queued after it uses it.
+//!
+//! A material's fragment shader gets the same inputs as the built-in one, a color at
+//! `@location(0)` and texture coordinates at `@location(1)`, with the draw call's texture and
+//! sampler at group 1, bindings 0 and 1. The material itself is group 2:
+//!
+//! - binding 0: `struct Globals { time: f32, _padding: f32, screen_size: vec2<f32> }`
+//! - binding 1: `array<vec4<f32>, 16>`, the parameters in the order they were added. Floats
+//!   are in `.x`.
+//! - bindings 2 to 5: the material's textures, plain white when not set
+//! - binding 6: a linear sampler for them, whatever filter the textures were created with
+//!
+//! Shaders can leave out any binding they don't use. A custom vertex shader gets the vertex
+//! attributes at locations 0 to 2 (position in pixels, color, texture coordinates) and the
+//! screen size at group 0, binding 0.
+
+use std::{collections::HashMap, sync::Arc};
+
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindingResource, Buffer,
+    Device, RenderPipeline, Sampler, TextureView,
+};
+
+use crate::{clip::Stencil, shader::ShaderCode, types::TextureId};
+
+/// How many parameters a material can have
+pub const MAX_MATERIAL_PARAMS: usize = 16;
+/// How many textures a material can have on top of the draw call's own
+pub const MAX_MATERIAL_TEXTURES: usize = 4;
+
+/// Handle to a material created with `Renderer::create_material`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MaterialId(pub(crate) usize);
+
+impl MaterialId {
+    /// The built-in shader: vertex color times texture
+    pub const DEFAULT: MaterialId = MaterialId(0);
+}
+
+/// Everything needed to create a material
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaterialDescriptor {
+    pub fragment: ShaderCode,
+    /// `None` uses the built-in vertex shader
+    pub vertex: Option<ShaderCode>,
+    /// Named parameters with their starting values
+    pub params: Vec<(String, [f32; 4])>,
+    pub textures: Vec<TextureId>,
+}
+
+impl MaterialDescriptor {
+    pub fn new(fragment: ShaderCode) -> Self {
+        Self {
+            fragment,
+            vertex: None,
+            params: Vec::new(),
+            textures: Vec::new(),
+        }
+    }
+
+    pub fn wgsl(fragment: impl Into<String>) -> Self {
+        Self::new(ShaderCode::wgsl(fragment))
+    }
+
+    pub fn glsl(fragment: impl Into<String>) -> Self {
+        Self::new(ShaderCode::glsl(fragment))
+    }
+
+    pub fn with_vertex(mut self, vertex: ShaderCode) -> Self {
+        self.vertex = Some(vertex);
+        self
+    }
+
+    pub fn with_float(self, name: impl Into<String>, value: f32) -> Self {
+        self.with_param(name, [value, 0.0, 0.0, 0.0])
+    }
+
+    pub fn with_color(self, name: impl Into<String>, color: [f32; 4]) -> Self {
+        self.with_param(name, color)
+    }
+
+    pub fn with_param(mut self, name: impl Into<String>, value: [f32; 4]) -> Self {
+        self.params.push((name.into(), value));
+        self
+    }
+
+    /// Next texture slot, starting at binding 2
+    pub fn with_texture(mut self, texture: TextureId) -> Self {
+        self.textures.push(texture);
+        self
+    }
+}
+
+pub(crate) struct GpuMaterial {
+    // Shared between every material made from the same shaders
+    pub(crate) pipeline: Arc<RenderPipeline>,
+    // Kept to make the variants masks need
+    pub(crate) shaders: (Option<ShaderCode>, ShaderCode),
+    // Made the first time the material is drawn in a frame with a mask
+    pub(crate) masked: HashMap<Stencil, Arc<RenderPipeline>>,
+    pub(crate) bind_group: BindGroup,
+    pub(crate) params_buffer: Buffer,
+    pub(crate) param_names: Vec<String>,
+    pub(crate) textures: [TextureId; MAX_MATERIAL_TEXTURES],
+}
+
+impl GpuMaterial {
+    pub(crate) fn param_index(&self, name: &str) -> Option<usize> {
+        self.param_names.iter().position(|param| param == name)
+    }
+}
+
+pub(crate) fn create_material_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    globals: &Buffer,
+    params: &Buffer,
+    textures: [&TextureView; MAX_MATERIAL_TEXTURES],
+    sampler: &Sampler,
+) -> BindGroup {
+    let mut entries = vec![
+        BindGroupEntry {
+            binding: 0,
+            resource: globals.as_entire_binding(),
+        },
+        BindGroupEntry {
+            binding: 1,
+            resource: params.as_entire_binding(),
+        },
+    ];
+    entries.extend(textures.iter().enumerate().map(|(i, view)| BindGroupEntry {
+        binding: 2 + i as u32,
+        resource: BindingResource::TextureView(view),
+    }));
+    entries.push(BindGroupEntry {
+        binding: 2 + MAX_MATERIAL_TEXTURES as u32,
+        resource: BindingResource::Sampler(sampler),
+    });
+
+    device.create_bind_group(
+        &(BindGroupDescriptor {
+            label: Some("Material BG"),
+            layout,
+            entries: &entries,
+        }),
+    )
+}
@@ -0,0 +1,118 @@
+//! Static meshes: geometry uploaded once and kept on the GPU, for things like tilemaps that
+//! would otherwise re-queue thousands of quads every frame.
+//!
+//! A static mesh is drawn in order with everything else queued that frame, but always with the
+//! built-in shader. It's moved and scaled as a whole when queued, so the vertices never need
+//! touching again unless the geometry itself changes.
+
+use wgpu::{BindGroup, BindGroupLayout, Buffer, BufferUsages, Device, Queue, util::DeviceExt};
+
+use crate::{init::create_bind_group, types::*};
+
+/// Handle to a mesh created with `Renderer::create_static_mesh`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StaticMeshId(pub(crate) usize);
+
+pub(crate) struct GpuMesh {
+    pub(crate) vertices: Buffer,
+    pub(crate) indices: Buffer,
+    pub(crate) index_count: u32,
+    pub(crate) texture: TextureId,
+    // Screen size, offset and scale, rewritten every time the mesh is queued
+    pub(crate) transform: Buffer,
+    pub(crate) bind_group: BindGroup,
+}
+
+impl GpuMesh {
+    pub(crate) fn new(
+        device: &Device,
+        layout: &BindGroupLayout,
+        texture: TextureId,
+        vertices: &[Vertex],
+        indices: &[u32],
+    ) -> Self {
+        let transform = device.create_buffer(
+            &(wgpu::BufferDescriptor {
+                label: Some("Static Mesh Transform"),
+                size: 4 * 6,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        );
+        let bind_group = create_bind_group(device, layout, &transform);
+
+        Self {
+            vertices: create_buffer(
+                device,
+                "Static Mesh Vertices",
+                bytemuck::cast_slice(vertices),
+                BufferUsages::VERTEX,
+            ),
+            indices: create_buffer(
+                device,
+                "Static Mesh Indices",
+                bytemuck::cast_slice(indices),
+                BufferUsages::INDEX,
+            ),
+            index_count: indices.len() as u32,
+            texture,
+            transform,
+            bind_group,
+        }
+    }
+
+    /// Replace the geometry, writing into the existing buffers and only making bigger ones
+    /// when it doesn't fit
+    pub(crate) fn update(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        texture: TextureId,
+        vertices: &[Vertex],
+        indices: &[u32],
+    ) {
+        write_buffer(
+            device,
+            queue,
+            &mut self.vertices,
+            "Static Mesh Vertices",
+            bytemuck::cast_slice(vertices),
+            BufferUsages::VERTEX,
+        );
+        write_buffer(
+            device,
+            queue,
+            &mut self.indices,
+            "Static Mesh Indices",
+            bytemuck::cast_slice(indices),
+            BufferUsages::INDEX,
+        );
+        self.index_count = indices.len() as u32;
+        self.texture = texture;
+    }
+}
+
+fn create_buffer(device: &Device, label: &str, contents: &[u8], usage: BufferUsages) -> Buffer {
+    device.create_buffer_init(
+        &(wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents,
+            usage: usage | BufferUsages::COPY_DST,
+        }),
+    )
+}
+
+fn write_buffer(
+    device: &Device,
+    queue: &Queue,
+    buffer: &mut Buffer,
+    label: &str,
+    contents: &[u8],
+    usage: BufferUsages,
+) {
+    if contents.len() as u64 <= buffer.size() {
+        queue.write_buffer(buffer, 0, contents);
+    } else {
+        *buffer = create_buffer(device, label, contents, usage);
+    }
+}
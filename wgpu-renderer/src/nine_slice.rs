@@ -0,0 +1,164 @@
+//! Nine-slice sprites, for panels and buttons that can be any size without blurring or
+//! stretching their corners.
+//!
+//! The texture region is cut into a 3x3 grid by its border insets. Corners are drawn at their
+//! own size, edges fill the space between them along one axis and the center fills the rest,
+//! either stretched or tiled. Everything is queued as one mesh, so panels batch like sprites.
+
+use crate::{renderer::QUAD_INDICES, types::Vertex};
+
+// Start, end, start uv and end uv along one axis
+type Span = (f32, f32, f32, f32);
+
+/// How the edges or the center of a `NineSlice` fill their space
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SliceFill {
+    #[default]
+    Stretch,
+    /// Repeated at their size in the texture, the last copy cut off where it runs out
+    Tile,
+}
+
+/// A texture region and its borders, for `Renderer::queue_nine_slice`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NineSlice {
+    /// The whole region, (min u, min v, max u, max v)
+    pub uv: [f32; 4],
+    /// Size of the region in texture pixels
+    pub size: (f32, f32),
+    /// Border widths in texture pixels: left, top, right, bottom
+    pub insets: [f32; 4],
+    /// Screen pixels per texture pixel for the borders and tiles
+    pub scale: f32,
+    pub edges: SliceFill,
+    pub center: SliceFill,
+}
+
+impl NineSlice {
+    pub fn new(uv: [f32; 4], size: (f32, f32), insets: [f32; 4]) -> Self {
+        Self {
+            uv,
+            size,
+            insets,
+            scale: 1.0,
+            edges: SliceFill::Stretch,
+            center: SliceFill::Stretch,
+        }
+    }
+
+    /// Quads covering a rectangle on screen
+    pub(crate) fn mesh(
+        &self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        color: [f32; 4],
+    ) -> (Vec<Vertex>, Vec<u32>) {
+        let [left, top, right, bottom] = self.insets.map(|inset| inset.max(0.0));
+        let (columns, tile_width) = Self::axis(
+            (x, width),
+            (self.size.0, left, right),
+            self.scale,
+            (self.uv[0], self.uv[2]),
+        );
+        let (rows, tile_height) = Self::axis(
+            (y, height),
+            (self.size.1, top, bottom),
+            self.scale,
+            (self.uv[1], self.uv[3]),
+        );
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for (row, &(y0, y1, v0, v1)) in rows.iter().enumerate() {
+            for (column, &(x0, x1, u0, u1)) in columns.iter().enumerate() {
+                // Only the middle row and column ever fill, and corners never do
+                let fill = match (column == 1, row == 1) {
+                    (true, true) => self.center,
+                    (false, false) => SliceFill::Stretch,
+                    _ => self.edges,
+                };
+                // Tiling only repeats along the axis a part is stretched on
+                let tiles_x =
+                    Self::tiles((x0, x1, u0, u1), fill, (column == 1).then_some(tile_width));
+                let tiles_y =
+                    Self::tiles((y0, y1, v0, v1), fill, (row == 1).then_some(tile_height));
+                for &(y0, y1, v0, v1) in &tiles_y {
+                    for &(x0, x1, u0, u1) in &tiles_x {
+                        let first = vertices.len() as u32;
+                        vertices.extend([
+                            Vertex::with_tex_coords(x0, y0, u0, v0, color),
+                            Vertex::with_tex_coords(x1, y0, u1, v0, color),
+                            Vertex::with_tex_coords(x1, y1, u1, v1, color),
+                            Vertex::with_tex_coords(x0, y1, u0, v1, color),
+                        ]);
+                        indices.extend(QUAD_INDICES.map(|i| first + i));
+                    }
+                }
+            }
+        }
+        (vertices, indices)
+    }
+
+    /// The three spans of one axis as (start, end, start uv, end uv), and how long the middle
+    /// one is on screen at its texture size. Borders shrink evenly when the rectangle is too
+    /// small to fit both of them.
+    fn axis(
+        (position, length): (f32, f32),
+        (texels, start_inset, end_inset): (f32, f32, f32),
+        scale: f32,
+        (uv_start, uv_end): (f32, f32),
+    ) -> ([Span; 3], f32) {
+        let uv_per_texel = if texels > 0.0 {
+            (uv_end - uv_start) / texels
+        } else {
+            0.0
+        };
+        let borders = (start_inset + end_inset) * scale;
+        let fit = if borders > length && borders > 0.0 {
+            length / borders
+        } else {
+            1.0
+        };
+        let start = position + start_inset * scale * fit;
+        let end = position + length - end_inset * scale * fit;
+        let uv_inner_start = uv_start + start_inset * uv_per_texel;
+        let uv_inner_end = uv_end - end_inset * uv_per_texel;
+        (
+            [
+                (position, start, uv_start, uv_inner_start),
+                (start, end, uv_inner_start, uv_inner_end),
+                (end, position + length, uv_inner_end, uv_end),
+            ],
+            (texels - start_inset - end_inset) * scale,
+        )
+    }
+
+    /// A span split into copies `tile` long, or left whole when stretching
+    fn tiles(
+        (start, end, uv_start, uv_end): Span,
+        fill: SliceFill,
+        tile: Option<f32>,
+    ) -> Vec<Span> {
+        if end <= start {
+            return Vec::new();
+        }
+        let tile = match (fill, tile) {
+            // Tiles too small to see would just flood the batch
+            (SliceFill::Tile, Some(tile)) if tile >= 1.0 => tile,
+            _ => return vec![(start, end, uv_start, uv_end)],
+        };
+        let uv_length = uv_end - uv_start;
+
+        let mut tiles = Vec::new();
+        let mut position = start;
+        while position < end - f32::EPSILON {
+            let next = (position + tile).min(end);
+            let cut = (next - position) / tile;
+            tiles.push((position, next, uv_start, uv_start + uv_length * cut));
+            position = next;
+        }
+        tiles
+    }
+}
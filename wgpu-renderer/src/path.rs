@@ -0,0 +1,281 @@
+//! Filled polygons and bezier paths, tessellated with lyon
+
+use lyon_tessellation::{
+    BuffersBuilder, FillOptions, FillTessellator, FillVertex, StrokeOptions, StrokeTessellator,
+    StrokeVertex, VertexBuffers,
+    math::{Box2D, point},
+    path::{Path, Winding, builder::BorderRadii},
+};
+
+use crate::{
+    shapes::{LineCap, LineJoin, StrokeStyle},
+    types::Vertex,
+};
+
+/// Decides which areas of overlapping or nested sub paths are inside the shape
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FillRule {
+    /// Inside where an odd number of outlines surround the point. Any sub path inside another
+    /// one is a hole, whichever way it winds.
+    #[default]
+    EvenOdd,
+    /// Inside where outlines going one way don't cancel out the ones going the other way.
+    /// Holes have to wind the opposite way of their outline.
+    NonZero,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum PathCommand {
+    MoveTo((f32, f32)),
+    LineTo((f32, f32)),
+    QuadraticTo((f32, f32), (f32, f32)),
+    CubicTo((f32, f32), (f32, f32), (f32, f32)),
+    Close,
+    RoundedRectangle {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        radius: f32,
+    },
+}
+
+/// An outline made of straight lines and bezier curves, drawn with `Renderer::queue_path`.
+///
+/// Every `move_to` starts a new sub path, so holes are just more sub paths inside the first.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShapePath {
+    commands: Vec<PathCommand>,
+}
+
+impl ShapePath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Closed path through `points`
+    pub fn polygon(points: &[(f32, f32)]) -> Self {
+        let mut path = Self::new();
+        path.add_polygon(points);
+        path
+    }
+
+    pub fn rounded_rectangle(x: f32, y: f32, width: f32, height: f32, radius: f32) -> Self {
+        let mut path = Self::new();
+        path.add_rounded_rectangle(x, y, width, height, radius);
+        path
+    }
+
+    /// Start a new sub path at `(x, y)`
+    pub fn move_to(&mut self, x: f32, y: f32) -> &mut Self {
+        self.commands.push(PathCommand::MoveTo((x, y)));
+        self
+    }
+
+    pub fn line_to(&mut self, x: f32, y: f32) -> &mut Self {
+        self.commands.push(PathCommand::LineTo((x, y)));
+        self
+    }
+
+    /// Curve towards `(x, y)`, pulled towards the control point
+    pub fn quadratic_to(&mut self, control_x: f32, control_y: f32, x: f32, y: f32) -> &mut Self {
+        self.commands
+            .push(PathCommand::QuadraticTo((control_x, control_y), (x, y)));
+        self
+    }
+
+    pub fn cubic_to(
+        &mut self,
+        control_1: (f32, f32),
+        control_2: (f32, f32),
+        x: f32,
+        y: f32,
+    ) -> &mut Self {
+        self.commands
+            .push(PathCommand::CubicTo(control_1, control_2, (x, y)));
+        self
+    }
+
+    /// Connect the current sub path back to where it started
+    pub fn close(&mut self) -> &mut Self {
+        self.commands.push(PathCommand::Close);
+        self
+    }
+
+    pub fn add_polygon(&mut self, points: &[(f32, f32)]) -> &mut Self {
+        let Some((&(x, y), rest)) = points.split_first() else {
+            return self;
+        };
+
+        self.move_to(x, y);
+        for &(x, y) in rest {
+            self.line_to(x, y);
+        }
+        self.close()
+    }
+
+    /// Add a rectangle with rounded corners as its own sub path. The radius is clamped to fit.
+    pub fn add_rounded_rectangle(
+        &mut self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        radius: f32,
+    ) -> &mut Self {
+        self.commands.push(PathCommand::RoundedRectangle {
+            x,
+            y,
+            width,
+            height,
+            radius: radius.min(width.min(height) / 2.0).max(0.0),
+        });
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    // lyon wants every sub path explicitly begun and ended, these commands are SVG style
+    fn build(&self) -> Path {
+        let mut builder = Path::builder();
+        let mut start = (0.0, 0.0);
+        let mut current = (0.0, 0.0);
+        let mut open = false;
+
+        for command in &self.commands {
+            // Drawing without a move_to continues from wherever the last sub path ended
+            let draws = matches!(
+                command,
+                PathCommand::LineTo(_) | PathCommand::QuadraticTo(..) | PathCommand::CubicTo(..)
+            );
+            if draws && !open {
+                builder.begin(point(current.0, current.1));
+                start = current;
+                open = true;
+            }
+
+            match *command {
+                PathCommand::MoveTo(to) => {
+                    if open {
+                        builder.end(false);
+                    }
+                    builder.begin(point(to.0, to.1));
+                    open = true;
+                    start = to;
+                    current = to;
+                }
+                PathCommand::LineTo(to) => {
+                    builder.line_to(point(to.0, to.1));
+                    current = to;
+                }
+                PathCommand::QuadraticTo(control, to) => {
+                    builder.quadratic_bezier_to(point(control.0, control.1), point(to.0, to.1));
+                    current = to;
+                }
+                PathCommand::CubicTo(control_1, control_2, to) => {
+                    builder.cubic_bezier_to(
+                        point(control_1.0, control_1.1),
+                        point(control_2.0, control_2.1),
+                        point(to.0, to.1),
+                    );
+                    current = to;
+                }
+                PathCommand::Close => {
+                    if open {
+                        builder.end(true);
+                        open = false;
+                    }
+                    current = start;
+                }
+                PathCommand::RoundedRectangle {
+                    x,
+                    y,
+                    width,
+                    height,
+                    radius,
+                } => {
+                    if open {
+                        builder.end(false);
+                        open = false;
+                    }
+                    builder.add_rounded_rectangle(
+                        &Box2D::new(point(x, y), point(x + width, y + height)),
+                        &BorderRadii::new(radius),
+                        Winding::Positive,
+                    );
+                }
+            }
+        }
+
+        if open {
+            builder.end(false);
+        }
+        builder.build()
+    }
+}
+
+/// Triangles covering the inside of `path`. Empty if lyon can't make sense of the path.
+pub(crate) fn fill(
+    path: &ShapePath,
+    rule: FillRule,
+    tolerance: f32,
+    color: [f32; 4],
+) -> VertexBuffers<Vertex, u32> {
+    let options = FillOptions::tolerance(tolerance).with_fill_rule(match rule {
+        FillRule::EvenOdd => lyon_tessellation::FillRule::EvenOdd,
+        FillRule::NonZero => lyon_tessellation::FillRule::NonZero,
+    });
+
+    let mut buffers = VertexBuffers::new();
+    let result = FillTessellator::new().tessellate_path(
+        &path.build(),
+        &options,
+        &mut BuffersBuilder::new(&mut buffers, |vertex: FillVertex| {
+            Vertex::with_color(vertex.position().x, vertex.position().y, color)
+        }),
+    );
+
+    if let Err(e) = result {
+        log::warn!("Failed to tessellate path: {e:?}");
+        return VertexBuffers::new();
+    }
+    buffers
+}
+
+/// Triangles covering the outline of `path`
+pub(crate) fn stroke(
+    path: &ShapePath,
+    style: &StrokeStyle,
+    tolerance: f32,
+    color: [f32; 4],
+) -> VertexBuffers<Vertex, u32> {
+    let options = StrokeOptions::tolerance(tolerance)
+        .with_line_width(style.thickness)
+        .with_line_join(match style.join {
+            LineJoin::Miter => lyon_tessellation::LineJoin::Miter,
+            LineJoin::Bevel => lyon_tessellation::LineJoin::Bevel,
+            LineJoin::Round => lyon_tessellation::LineJoin::Round,
+        })
+        .with_line_cap(match style.cap {
+            LineCap::Butt => lyon_tessellation::LineCap::Butt,
+            LineCap::Square => lyon_tessellation::LineCap::Square,
+            LineCap::Round => lyon_tessellation::LineCap::Round,
+        });
+
+    let mut buffers = VertexBuffers::new();
+    let result = StrokeTessellator::new().tessellate_path(
+        &path.build(),
+        &options,
+        &mut BuffersBuilder::new(&mut buffers, |vertex: StrokeVertex| {
+            Vertex::with_color(vertex.position().x, vertex.position().y, color)
+        }),
+    );
+
+    if let Err(e) = result {
+        log::warn!("Failed to tessellate path outline: {e:?}");
+        return VertexBuffers::new();
+    }
+    buffers
+}
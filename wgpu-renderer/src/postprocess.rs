@@ -0,0 +1,312 @@
+//! Fullscreen effects run on the finished frame.
+//!
+//! With any effect turned on, the frame is drawn into a texture first and then passed through
+//! each effect in order before it ends up on screen. Text is part of the frame, so it gets the
+//! effects too. With nothing turned on, frames are drawn straight to the screen as usual.
+
+use wgpu::{BindGroupLayout, Buffer, Device, Sampler, TextureFormat, util::DeviceExt};
+
+use crate::{
+    lighting::LIGHT_MAP_FORMAT, material::MaterialId, renderer::QUAD_INDICES, texture::GpuTexture,
+    types::*,
+};
+
+/// One step of the post-process chain
+#[derive(Clone, Debug, PartialEq)]
+pub enum PostEffect {
+    /// Makes bright parts glow. `threshold` is the brightness (0 to 1) the glow starts at and
+    /// `radius` how far it spreads, in half resolution texels.
+    Bloom {
+        threshold: f32,
+        intensity: f32,
+        radius: f32,
+    },
+    /// Darkens the edges of the screen. `radius` is where the darkening starts, 0.5 being the
+    /// top and bottom edges, and it fades in over `softness`.
+    Vignette {
+        intensity: f32,
+        radius: f32,
+        softness: f32,
+    },
+    /// An old tube monitor, with dark lines between pixel rows and a bulging screen
+    Crt { scanlines: f32, curvature: f32 },
+    /// Remaps colors through a lookup table texture. The table is N*N pixels wide and N tall,
+    /// laid out as N squares of red across and green down, one per blue level. Tables are
+    /// usually 256x16 or 1024x32.
+    ColorGrade { lut: TextureId, intensity: f32 },
+    /// Pushes the red and blue channels apart towards the edges, `offset` pixels at most
+    ChromaticAberration { offset: f32 },
+    /// Blocky pixels `size` screen pixels wide
+    Pixelate { size: f32 },
+    /// A material drawn over the whole screen. Its fragment shader gets the frame so far as the
+    /// draw call's texture, group 1, and has to write it back out with full alpha.
+    Custom(MaterialId),
+}
+
+impl PostEffect {
+    pub fn bloom() -> Self {
+        PostEffect::Bloom {
+            threshold: 0.7,
+            intensity: 1.0,
+            radius: 1.5,
+        }
+    }
+
+    pub fn vignette() -> Self {
+        PostEffect::Vignette {
+            intensity: 0.6,
+            radius: 0.75,
+            softness: 0.45,
+        }
+    }
+
+    pub fn crt() -> Self {
+        PostEffect::Crt {
+            scanlines: 0.25,
+            curvature: 0.04,
+        }
+    }
+
+    pub fn color_grade(lut: TextureId) -> Self {
+        PostEffect::ColorGrade {
+            lut,
+            intensity: 1.0,
+        }
+    }
+
+    pub fn chromatic_aberration() -> Self {
+        PostEffect::ChromaticAberration { offset: 3.0 }
+    }
+
+    pub fn pixelate(size: f32) -> Self {
+        PostEffect::Pixelate { size }
+    }
+}
+
+/// Handle to an effect in a `PostProcess` chain. Stays valid when effects are moved around.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PostEffectId(usize);
+
+#[derive(Clone, Debug)]
+struct PostPass {
+    id: PostEffectId,
+    effect: PostEffect,
+    enabled: bool,
+}
+
+/// The effects applied to every frame, in order. Get it with `Renderer::post_process_mut`.
+#[derive(Clone, Debug, Default)]
+pub struct PostProcess {
+    passes: Vec<PostPass>,
+    next_id: usize,
+}
+
+impl PostProcess {
+    /// Add an effect after all the others
+    pub fn push(&mut self, effect: PostEffect) -> PostEffectId {
+        self.insert(self.passes.len(), effect)
+    }
+
+    /// Add an effect at `index` in the chain, clamped to its length
+    pub fn insert(&mut self, index: usize, effect: PostEffect) -> PostEffectId {
+        let id = PostEffectId(self.next_id);
+        self.next_id += 1;
+        self.passes.insert(
+            index.min(self.passes.len()),
+            PostPass {
+                id,
+                effect,
+                enabled: true,
+            },
+        );
+        id
+    }
+
+    pub fn remove(&mut self, id: PostEffectId) -> Option<PostEffect> {
+        let index = self.index_of(id)?;
+        Some(self.passes.remove(index).effect)
+    }
+
+    pub fn clear(&mut self) {
+        self.passes.clear();
+    }
+
+    /// Move an effect to `index` in the chain, clamped to its length
+    pub fn move_to(&mut self, id: PostEffectId, index: usize) {
+        if let Some(from) = self.index_of(id) {
+            let pass = self.passes.remove(from);
+            self.passes.insert(index.min(self.passes.len()), pass);
+        }
+    }
+
+    /// Where an effect is in the chain
+    pub fn index_of(&self, id: PostEffectId) -> Option<usize> {
+        self.passes.iter().position(|pass| pass.id == id)
+    }
+
+    pub fn get(&self, id: PostEffectId) -> Option<&PostEffect> {
+        self.pass(id).map(|pass| &pass.effect)
+    }
+
+    /// Change an effect's settings, they're picked up on the next frame
+    pub fn get_mut(&mut self, id: PostEffectId) -> Option<&mut PostEffect> {
+        self.pass_mut(id).map(|pass| &mut pass.effect)
+    }
+
+    /// Turn an effect off without losing its place or settings
+    pub fn set_enabled(&mut self, id: PostEffectId, enabled: bool) {
+        if let Some(pass) = self.pass_mut(id) {
+            pass.enabled = enabled;
+        }
+    }
+
+    pub fn is_enabled(&self, id: PostEffectId) -> bool {
+        self.pass(id).is_some_and(|pass| pass.enabled)
+    }
+
+    /// Every effect in order, whether it's turned on or not
+    pub fn iter(&self) -> impl Iterator<Item = (PostEffectId, &PostEffect)> {
+        self.passes.iter().map(|pass| (pass.id, &pass.effect))
+    }
+
+    /// The effects that will actually run, in order
+    pub fn enabled(&self) -> impl Iterator<Item = &PostEffect> {
+        self.passes
+            .iter()
+            .filter(|pass| pass.enabled)
+            .map(|pass| &pass.effect)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.passes.iter().any(|pass| pass.enabled)
+    }
+
+    fn pass(&self, id: PostEffectId) -> Option<&PostPass> {
+        self.passes.iter().find(|pass| pass.id == id)
+    }
+
+    fn pass_mut(&mut self, id: PostEffectId) -> Option<&mut PostPass> {
+        self.passes.iter_mut().find(|pass| pass.id == id)
+    }
+}
+
+/// The built-in effect shaders. Bloom takes a few passes, so it's split into its steps.
+/// Lighting is applied with one of these too, before any effects.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum PostShader {
+    Copy,
+    BloomExtract,
+    Blur,
+    BloomComposite,
+    Vignette,
+    Crt,
+    ColorGrade,
+    ChromaticAberration,
+    Pixelate,
+    LightComposite,
+}
+
+impl PostShader {
+    pub(crate) const ALL: [PostShader; 10] = [
+        PostShader::Copy,
+        PostShader::BloomExtract,
+        PostShader::Blur,
+        PostShader::BloomComposite,
+        PostShader::Vignette,
+        PostShader::Crt,
+        PostShader::ColorGrade,
+        PostShader::ChromaticAberration,
+        PostShader::Pixelate,
+        PostShader::LightComposite,
+    ];
+
+    /// WGSL for the effect, with the shared bindings in front
+    pub(crate) fn source(self) -> String {
+        let effect = match self {
+            PostShader::Copy => include_str!("../res/shaders/post/copy.wgsl"),
+            PostShader::BloomExtract => include_str!("../res/shaders/post/bloom_extract.wgsl"),
+            PostShader::Blur => include_str!("../res/shaders/post/blur.wgsl"),
+            PostShader::BloomComposite => {
+                include_str!("../res/shaders/post/bloom_composite.wgsl")
+            }
+            PostShader::Vignette => include_str!("../res/shaders/post/vignette.wgsl"),
+            PostShader::Crt => include_str!("../res/shaders/post/crt.wgsl"),
+            PostShader::ColorGrade => include_str!("../res/shaders/post/color_grade.wgsl"),
+            PostShader::ChromaticAberration => {
+                include_str!("../res/shaders/post/chromatic_aberration.wgsl")
+            }
+            PostShader::Pixelate => include_str!("../res/shaders/post/pixelate.wgsl"),
+            PostShader::LightComposite => {
+                include_str!("../res/shaders/post/light_composite.wgsl")
+            }
+        };
+        format!(
+            "{}\n{effect}",
+            include_str!("../res/shaders/post/common.wgsl")
+        )
+    }
+}
+
+/// Textures lighting and the chain render between, sized to the screen
+pub(crate) struct PostTargets {
+    // The frame is drawn into the first, then effects ping-pong between the two
+    pub(crate) frames: [GpuTexture; 2],
+    // Half resolution, for blurring
+    pub(crate) bloom: [GpuTexture; 2],
+    pub(crate) light_map: GpuTexture,
+    // A screen sized quad, in pixels like everything else
+    pub(crate) quad_vertices: Buffer,
+    pub(crate) quad_indices: Buffer,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+impl PostTargets {
+    pub(crate) fn new(
+        device: &Device,
+        layout: &BindGroupLayout,
+        sampler: &Sampler,
+        format: TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let target = |width: u32, height: u32| {
+            GpuTexture::render_target(device, layout, sampler, width.max(1), height.max(1), format)
+        };
+        let (w, h) = (width as f32, height as f32);
+
+        Self {
+            frames: [target(width, height), target(width, height)],
+            bloom: [target(width / 2, height / 2), target(width / 2, height / 2)],
+            light_map: GpuTexture::render_target(
+                device,
+                layout,
+                sampler,
+                width.max(1),
+                height.max(1),
+                LIGHT_MAP_FORMAT,
+            ),
+            quad_vertices: device.create_buffer_init(
+                &(wgpu::util::BufferInitDescriptor {
+                    label: Some("Post Process Quad"),
+                    contents: bytemuck::cast_slice(&[
+                        Vertex::with_tex_coords(0.0, 0.0, 0.0, 0.0, [1.0; 4]),
+                        Vertex::with_tex_coords(w, 0.0, 1.0, 0.0, [1.0; 4]),
+                        Vertex::with_tex_coords(w, h, 1.0, 1.0, [1.0; 4]),
+                        Vertex::with_tex_coords(0.0, h, 0.0, 1.0, [1.0; 4]),
+                    ]),
+                    usage: wgpu::BufferUsages::VERTEX,
+                }),
+            ),
+            quad_indices: device.create_buffer_init(
+                &(wgpu::util::BufferInitDescriptor {
+                    label: Some("Post Process Quad Indices"),
+                    contents: bytemuck::cast_slice(&QUAD_INDICES),
+                    usage: wgpu::BufferUsages::INDEX,
+                }),
+            ),
+            width,
+            height,
+        }
+    }
+}
@@ -1,10 +1,55 @@
-use std::{iter, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    f32::consts::TAU,
+    iter,
+    sync::Arc,
+};
 
-use wgpu::{BindGroup, Buffer};
-use wgpu_glyph::{Section, Text};
+use naga::ShaderStage;
+use wgpu::{BindGroup, Buffer, util::DeviceExt};
+use wgpu_glyph::{
+    GlyphCruncher, Section, SectionGlyph, Text,
+    ab_glyph::{Font, FontArc, ScaleFont},
+};
 use winit::{dpi::PhysicalSize, window::Window};
 
-use crate::{init::*, types::*};
+use crate::{
+    clip::{ClipRect, MaskMode, Stencil, StencilTarget},
+    error::{RenderError, ShaderDiagnostic},
+    gradient::Gradient,
+    init::*,
+    lighting::{ADDITIVE_BLENDING, LIGHT_MAP_FORMAT, Light, Occluder},
+    material::{
+        GpuMaterial, MAX_MATERIAL_PARAMS, MAX_MATERIAL_TEXTURES, MaterialDescriptor, MaterialId,
+        create_material_bind_group,
+    },
+    mesh::{GpuMesh, StaticMeshId},
+    nine_slice::NineSlice,
+    path::{self, FillRule, ShapePath},
+    postprocess::{PostEffect, PostProcess, PostShader, PostTargets},
+    shader::{self, ShaderCode},
+    shapes::{CurveQuality, StrokeStyle, Tessellator},
+    text::{FontId, TextBounds, TextSection},
+    texture::GpuTexture,
+    types::*,
+};
+
+/// A run of queued indices that are all drawn with the same texture and material
+#[derive(Clone, Copy, Debug)]
+struct DrawBatch {
+    texture: TextureId,
+    material: MaterialId,
+    start: u32,
+    end: u32,
+    // Draws this instead of the queued indices when set
+    static_mesh: Option<StaticMeshId>,
+    clip: Option<ClipRect>,
+    stencil: Option<Stencil>,
+    // Stencil reference, which mask the batch writes or tests against
+    mask: u32,
+}
+
+type MaterialPipelineKey = (Option<ShaderCode>, ShaderCode, Option<Stencil>);
 
 pub struct Renderer {
     surface: wgpu::Surface<'static>,
@@ -18,11 +63,68 @@ pub struct Renderer {
     staging_belt: wgpu::util::StagingBelt,
     queued_vertices: Vec<Vertex>,
     queued_indices: Vec<u32>,
+    batches: Vec<DrawBatch>,
     // Passed into shaders
     screen_size_buffer: Buffer,
     bind_group: BindGroup,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    linear_sampler: wgpu::Sampler,
+    nearest_sampler: wgpu::Sampler,
+    // Indexed by TextureId. Removed textures leave a None behind so ids are never reused.
+    textures: Vec<Option<GpuTexture>>,
+    curve_quality: CurveQuality,
+    // Built-in vertex shader, also used by materials that don't bring their own
+    vertex_shader: wgpu::ShaderModule,
+    material_bind_group_layout: wgpu::BindGroupLayout,
+    material_pipeline_layout: wgpu::PipelineLayout,
+    globals_buffer: Buffer,
+    // Indexed by MaterialId. Slot 0 is the built-in pipeline, so it's always None.
+    materials: Vec<Option<GpuMaterial>>,
+    // Keyed by (vertex, fragment) shader so materials sharing shaders share a pipeline, along
+    // with the stencil use for materials drawn in frames with a mask
+    material_pipelines: HashMap<MaterialPipelineKey, Arc<wgpu::RenderPipeline>>,
+    material: MaterialId,
+    time: f32,
+    post_process: PostProcess,
+    // Created the first time an effect is turned on, and again when the screen is resized
+    post_targets: Option<PostTargets>,
+    // Indexed by PostShader
+    post_pipelines: Vec<Arc<wgpu::RenderPipeline>>,
+    // One per pass, since every pass in a frame needs its own parameters
+    post_params: Vec<Buffer>,
+    // None when lighting is off
+    ambient_light: Option<[f32; 4]>,
+    light_pipeline: wgpu::RenderPipeline,
+    queued_lights: Vec<Light>,
+    queued_occluders: Vec<Occluder>,
+    // Triangle lists, built from the lights and occluders when the frame is drawn
+    light_vertices: Vec<Vertex>,
+    light_buffer: Buffer,
+    static_mesh_pipeline: wgpu::RenderPipeline,
+    // Group 0 of the built-in pipeline, which static meshes fill with their own transform
+    transform_bind_group_layout: wgpu::BindGroupLayout,
+    // Indexed by StaticMeshId. Removed meshes leave a None behind that the next new mesh takes.
+    static_meshes: Vec<Option<GpuMesh>>,
+    // Kept around to make the variants of the built-in pipelines that masks need
+    pipeline_layout: wgpu::PipelineLayout,
+    fragment_shader: wgpu::ShaderModule,
+    static_mesh_shader: wgpu::ShaderModule,
+    // (built-in, static mesh) for every way a batch can use the stencil buffer
+    masked_pipelines: HashMap<Stencil, (wgpu::RenderPipeline, wgpu::RenderPipeline)>,
+    // Created the first time a frame uses a mask, and again when the screen is resized
+    stencil_target: Option<StencilTarget>,
+    // Each rectangle is already cut down to fit inside the ones below it
+    clip_stack: Vec<ClipRect>,
+    stencil: Option<Stencil>,
+    // Stencil value of the current mask, 0 before the first one of the frame
+    mask: u32,
+    // Text queued while a clip rect was active, drawn in runs with their own scissor rect
+    clipped_text: Vec<(ClipRect, TextSection)>,
 }
 
+/// Two triangles covering a quad whose corners are listed clockwise from the top left
+pub const QUAD_INDICES: [u32; 6] = [2, 1, 0, 3, 2, 0];
+
 impl Renderer {
     pub fn width(&self) -> f32 {
         self.config.width as f32
@@ -45,7 +147,9 @@ impl Renderer {
         let config = create_surface_config(&surface, &adapter, size);
 
         let bind_group_layout = create_bind_group_layout(&device);
-        let pipeline_layout = create_pipeline_layout(&device, &bind_group_layout);
+        let texture_bind_group_layout = create_texture_bind_group_layout(&device);
+        let pipeline_layout =
+            create_pipeline_layout(&device, &[&bind_group_layout, &texture_bind_group_layout]);
 
         let (vert_shader, frag_shader) = create_shader_modules(&device);
 
@@ -59,13 +163,80 @@ impl Renderer {
             &pipeline_layout,
             config.format,
             &[Vertex::DESC],
-            vert_shader,
-            frag_shader,
+            &vert_shader,
+            "main",
+            &frag_shader,
+            "main",
+            // Alpha blending so textured sprites can have soft edges
+            wgpu::BlendState::ALPHA_BLENDING,
+            None,
+        );
+
+        let material_bind_group_layout = create_material_bind_group_layout(&device);
+        let material_pipeline_layout = create_pipeline_layout(
+            &device,
+            &[
+                &bind_group_layout,
+                &texture_bind_group_layout,
+                &material_bind_group_layout,
+            ],
+        );
+        let globals_buffer = create_globals_buffer(&device);
+
+        let light_shader = create_wgsl_module(
+            &device,
+            "light shader",
+            include_str!("../res/shaders/light.wgsl"),
+        );
+        let light_pipeline = create_render_pipeline(
+            &device,
+            &create_pipeline_layout(&device, &[&bind_group_layout]),
+            LIGHT_MAP_FORMAT,
+            &[Vertex::DESC],
+            &vert_shader,
+            "main",
+            &light_shader,
+            "main",
+            ADDITIVE_BLENDING,
+            None,
+        );
+        let light_buffer = create_vertex_buffer(&device, Vertex::SIZE * 6 * 16);
+
+        let static_mesh_shader = create_wgsl_module(
+            &device,
+            "static mesh shader",
+            include_str!("../res/shaders/static_mesh.wgsl"),
+        );
+        let static_mesh_pipeline = create_render_pipeline(
+            &device,
+            &pipeline_layout,
+            config.format,
+            &[Vertex::DESC],
+            &static_mesh_shader,
+            "main",
+            &frag_shader,
+            "main",
+            wgpu::BlendState::ALPHA_BLENDING,
+            None,
         );
 
         let glyph_brush = create_glyph_brush(&device, config.format);
         let staging_belt = wgpu::util::StagingBelt::new(1024);
 
+        let linear_sampler = create_sampler(&device, TextureFilter::Linear);
+        let nearest_sampler = create_sampler(&device, TextureFilter::Nearest);
+
+        // Untextured shapes are drawn with this so everything can share one pipeline
+        let white_texture = GpuTexture::new(
+            &device,
+            &queue,
+            &texture_bind_group_layout,
+            &nearest_sampler,
+            1,
+            1,
+            &[255, 255, 255, 255],
+        );
+
         surface.configure(&device, &config);
 
         Self {
@@ -80,9 +251,322 @@ impl Renderer {
             staging_belt,
             queued_vertices: Vec::new(),
             queued_indices: Vec::new(),
+            batches: Vec::new(),
             screen_size_buffer,
             bind_group,
+            texture_bind_group_layout,
+            linear_sampler,
+            nearest_sampler,
+            textures: vec![Some(white_texture)],
+            curve_quality: CurveQuality::default(),
+            vertex_shader: vert_shader,
+            material_bind_group_layout,
+            material_pipeline_layout,
+            globals_buffer,
+            materials: vec![None],
+            material_pipelines: HashMap::new(),
+            material: MaterialId::DEFAULT,
+            time: 0.0,
+            post_process: PostProcess::default(),
+            post_targets: None,
+            post_pipelines: Vec::new(),
+            post_params: Vec::new(),
+            ambient_light: None,
+            light_pipeline,
+            queued_lights: Vec::new(),
+            queued_occluders: Vec::new(),
+            light_vertices: Vec::new(),
+            light_buffer,
+            static_mesh_pipeline,
+            transform_bind_group_layout: bind_group_layout,
+            static_meshes: Vec::new(),
+            pipeline_layout,
+            fragment_shader: frag_shader,
+            static_mesh_shader,
+            masked_pipelines: HashMap::new(),
+            stencil_target: None,
+            clip_stack: Vec::new(),
+            stencil: None,
+            mask: 0,
+            clipped_text: Vec::new(),
+        }
+    }
+
+    /// Upload tightly packed RGBA8 pixels as a new texture
+    pub fn create_texture(
+        &mut self,
+        width: u32,
+        height: u32,
+        rgba: &[u8],
+        filter: TextureFilter,
+    ) -> TextureId {
+        let texture = GpuTexture::new(
+            &self.device,
+            &self.queue,
+            &self.texture_bind_group_layout,
+            self.sampler(filter),
+            width,
+            height,
+            rgba,
+        );
+
+        self.textures.push(Some(texture));
+        TextureId(self.textures.len() - 1)
+    }
+
+    /// Replace a texture's pixels. The texture is recreated if the size changed.
+    pub fn update_texture(
+        &mut self,
+        id: TextureId,
+        width: u32,
+        height: u32,
+        rgba: &[u8],
+        filter: TextureFilter,
+    ) {
+        match self.textures.get(id.0) {
+            Some(Some(texture)) if texture.width == width && texture.height == height => {
+                texture.write(&self.queue, rgba);
+            }
+            Some(Some(_)) => {
+                let texture = GpuTexture::new(
+                    &self.device,
+                    &self.queue,
+                    &self.texture_bind_group_layout,
+                    self.sampler(filter),
+                    width,
+                    height,
+                    rgba,
+                );
+                self.textures[id.0] = Some(texture);
+                self.rebuild_material_bind_groups(id);
+            }
+            _ => log::warn!("Tried to update missing texture {:?}", id),
+        }
+    }
+
+    pub fn set_texture_filter(&mut self, id: TextureId, filter: TextureFilter) {
+        let sampler = match filter {
+            TextureFilter::Linear => &self.linear_sampler,
+            TextureFilter::Nearest => &self.nearest_sampler,
+        };
+        if let Some(Some(texture)) = self.textures.get_mut(id.0) {
+            texture.set_sampler(&self.device, &self.texture_bind_group_layout, sampler);
+        }
+    }
+
+    /// Free a texture. Anything still drawn with it falls back to plain white.
+    pub fn remove_texture(&mut self, id: TextureId) {
+        if id == TextureId::WHITE {
+            return;
+        }
+        if let Some(slot) = self.textures.get_mut(id.0) {
+            *slot = None;
         }
+        self.rebuild_material_bind_groups(id);
+    }
+
+    pub fn texture_size(&self, id: TextureId) -> Option<(u32, u32)> {
+        self.textures
+            .get(id.0)?
+            .as_ref()
+            .map(|texture| (texture.width, texture.height))
+    }
+
+    fn sampler(&self, filter: TextureFilter) -> &wgpu::Sampler {
+        match filter {
+            TextureFilter::Linear => &self.linear_sampler,
+            TextureFilter::Nearest => &self.nearest_sampler,
+        }
+    }
+
+    /// Append geometry to this frame's draw list. `indices` are relative to `vertices`.
+    /// Consecutive geometry with the same texture, material, clip rect and mask is drawn in a
+    /// single batch.
+    pub fn queue_mesh(&mut self, texture: TextureId, vertices: &[Vertex], indices: &[u32]) {
+        if indices.is_empty() {
+            return;
+        }
+
+        let vertex_offset = self.queued_vertices.len() as u32;
+        self.queued_vertices.extend_from_slice(vertices);
+        self.queued_indices
+            .extend(indices.iter().map(|index| vertex_offset + index));
+
+        let end = self.queued_indices.len() as u32;
+        let clip = self.clip_rect();
+        match self.batches.last_mut() {
+            Some(batch)
+                if batch.texture == texture
+                    && batch.material == self.material
+                    && batch.static_mesh.is_none()
+                    && batch.clip == clip
+                    && batch.stencil == self.stencil
+                    && batch.mask == self.mask =>
+            {
+                batch.end = end
+            }
+            _ => self.batches.push(DrawBatch {
+                texture,
+                material: self.material,
+                start: end - indices.len() as u32,
+                end,
+                static_mesh: None,
+                clip,
+                stencil: self.stencil,
+                mask: self.mask,
+            }),
+        }
+    }
+
+    /// Upload geometry to draw again every frame with `queue_static_mesh`, without queueing
+    /// its vertices each time. Positions are in the mesh's own units, see `queue_static_mesh`.
+    pub fn create_static_mesh(
+        &mut self,
+        texture: TextureId,
+        vertices: &[Vertex],
+        indices: &[u32],
+    ) -> StaticMeshId {
+        let mesh = GpuMesh::new(
+            &self.device,
+            &self.transform_bind_group_layout,
+            texture,
+            vertices,
+            indices,
+        );
+        match self.static_meshes.iter().position(Option::is_none) {
+            Some(index) => {
+                self.static_meshes[index] = Some(mesh);
+                StaticMeshId(index)
+            }
+            None => {
+                self.static_meshes.push(Some(mesh));
+                StaticMeshId(self.static_meshes.len() - 1)
+            }
+        }
+    }
+
+    /// Replace a mesh's geometry, keeping its id
+    pub fn update_static_mesh(
+        &mut self,
+        id: StaticMeshId,
+        texture: TextureId,
+        vertices: &[Vertex],
+        indices: &[u32],
+    ) {
+        if let Some(Some(mesh)) = self.static_meshes.get_mut(id.0) {
+            mesh.update(&self.device, &self.queue, texture, vertices, indices);
+        }
+    }
+
+    /// Free a mesh. Its id goes to the next mesh created, so don't keep queueing it.
+    pub fn remove_static_mesh(&mut self, id: StaticMeshId) {
+        if let Some(slot) = self.static_meshes.get_mut(id.0) {
+            *slot = None;
+        }
+    }
+
+    /// Draw a static mesh this frame, in order with everything else queued. A vertex at
+    /// `(vx, vy)` ends up at `(x + vx * scale, y + vy * scale)` on screen. Each mesh can only
+    /// be drawn once a frame, queueing it again just moves it.
+    pub fn queue_static_mesh(&mut self, id: StaticMeshId, x: f32, y: f32, scale: f32) {
+        let Some(mesh) = self.static_meshes.get(id.0).and_then(Option::as_ref) else {
+            return;
+        };
+        if mesh.index_count == 0 {
+            return;
+        }
+        self.queue.write_buffer(
+            &mesh.transform,
+            0,
+            bytemuck::cast_slice(&[self.width(), self.height(), x, y, scale, scale]),
+        );
+
+        let end = self.queued_indices.len() as u32;
+        self.batches.push(DrawBatch {
+            texture: mesh.texture,
+            material: MaterialId::DEFAULT,
+            start: end,
+            end,
+            static_mesh: Some(id),
+            clip: self.clip_rect(),
+            stencil: self.stencil,
+            mask: self.mask,
+        });
+    }
+
+    /// Only draw inside this rectangle, in screen pixels, until it's popped. Text is clipped
+    /// too. Rectangles pushed while another is active are cut down to fit inside it, so
+    /// nested panels work as expected.
+    pub fn push_clip_rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        let rect = ClipRect::new(x, y, width, height);
+        let rect = match self.clip_stack.last() {
+            Some(top) => top.intersect(&rect),
+            None => rect,
+        };
+        self.clip_stack.push(rect);
+    }
+
+    /// Go back to the clip rect that was active before the last push
+    pub fn pop_clip_rect(&mut self) -> Option<ClipRect> {
+        self.clip_stack.pop()
+    }
+
+    /// Rectangle everything queued now is clipped to, `None` when drawing is unrestricted
+    pub fn clip_rect(&self) -> Option<ClipRect> {
+        self.clip_stack.last().copied()
+    }
+
+    /// Start a new mask. Everything queued until `apply_mask` draws into the mask instead of
+    /// onto the screen. Masks cover whole triangles, so the transparent parts of a sprite
+    /// count too, unless it's drawn with a material that discards them. Text ignores masks.
+    pub fn begin_mask(&mut self) {
+        if self.mask == u8::MAX as u32 {
+            self.wipe_masks();
+        }
+        self.mask += 1;
+        self.stencil = Some(Stencil::Write);
+    }
+
+    /// Only draw inside or outside the last mask from now on, until `clear_mask`
+    pub fn apply_mask(&mut self, mode: MaskMode) {
+        if self.mask == 0 {
+            log::warn!("Tried to apply a mask before beginning one");
+            return;
+        }
+        self.stencil = Some(Stencil::Test(mode));
+    }
+
+    /// Stop masking, so what's queued next draws everywhere again
+    pub fn clear_mask(&mut self) {
+        self.stencil = None;
+    }
+
+    /// Every stencil value has been used by a mask this frame, so reset the whole buffer to
+    /// make them fresh again
+    fn wipe_masks(&mut self) {
+        let (width, height) = (self.width(), self.height());
+        let first = self.queued_vertices.len() as u32;
+        self.queued_vertices.extend([
+            Vertex::with_color(0.0, 0.0, [0.0; 4]),
+            Vertex::with_color(width, 0.0, [0.0; 4]),
+            Vertex::with_color(width, height, [0.0; 4]),
+            Vertex::with_color(0.0, height, [0.0; 4]),
+        ]);
+        self.queued_indices
+            .extend(QUAD_INDICES.map(|index| first + index));
+
+        let end = self.queued_indices.len() as u32;
+        self.batches.push(DrawBatch {
+            texture: TextureId::WHITE,
+            material: MaterialId::DEFAULT,
+            start: end - QUAD_INDICES.len() as u32,
+            end,
+            static_mesh: None,
+            clip: None,
+            stencil: Some(Stencil::Write),
+            mask: 0,
+        });
+        self.mask = 0;
     }
 
     pub fn resize(&mut self, size: PhysicalSize<u32>) {
@@ -99,16 +583,67 @@ impl Renderer {
         self.surface.configure(&self.device, &self.config);
     }
 
+    /// Make a TTF/OTF font available to text sections. The embedded font is always
+    /// `FontId::DEFAULT`.
+    pub fn add_font(&mut self, font: FontArc) -> FontId {
+        FontId(self.glyph_brush.add_font(font).0)
+    }
+
+    /// Queue text in the default font
     pub fn queue_text(&mut self, text: &str, position: (f32, f32), size: f32, color: [f32; 4]) {
-        let section = (Section {
-            screen_position: position,
-            bounds: (self.config.width as f32, self.config.height as f32),
-            layout: wgpu_glyph::Layout::default().h_align(wgpu_glyph::HorizontalAlign::Left),
-            ..Section::default()
-        })
-        .add_text(Text::new(text).with_color(color).with_scale(size));
+        self.queue_section(&TextSection::new(position).with_text(text, size, color));
+    }
+
+    pub fn queue_section(&mut self, section: &TextSection) {
+        if let Some(clip) = self.clip_rect() {
+            self.clipped_text.push((clip, section.clone()));
+            return;
+        }
+        let layout = section.layout();
+        self.glyph_brush
+            .queue_custom_layout(glyph_section(section), &layout);
+    }
+
+    /// Bounding box the section would cover if it was queued, `None` if it has no visible glyphs
+    pub fn measure_section(&mut self, section: &TextSection) -> Option<TextBounds> {
+        let layout = section.layout();
+        self.glyph_brush
+            .glyph_bounds_custom_layout(glyph_section(section), &layout)
+            .map(TextBounds::from)
+    }
 
-        self.glyph_brush.queue(section);
+    /// Top left corner of the first glyph of every span, `None` for spans without glyphs.
+    /// Handy for drawing things inline with text, like icons.
+    pub fn span_positions(&mut self, section: &TextSection) -> Vec<Option<(f32, f32)>> {
+        let layout = section.layout();
+        let glyphs: Vec<SectionGlyph> = self
+            .glyph_brush
+            .glyphs_custom_layout(glyph_section(section), &layout)
+            .cloned()
+            .collect();
+
+        let mut positions = vec![None; section.spans.len()];
+        let fonts = self.glyph_brush.fonts();
+        for glyph in glyphs {
+            let Some(position) = positions.get_mut(glyph.section_index) else {
+                continue;
+            };
+            if position.is_some() {
+                continue;
+            }
+            // Glyphs are positioned on the baseline
+            let ascent = fonts
+                .get(glyph.font_id.0)
+                .map_or(0.0, |font| font.as_scaled(glyph.glyph.scale).ascent());
+            *position = Some((glyph.glyph.position.x, glyph.glyph.position.y - ascent));
+        }
+        positions
+    }
+
+    /// Width and height of text in the default font
+    pub fn measure_text(&mut self, text: &str, size: f32) -> (f32, f32) {
+        self.measure_section(&TextSection::new((0.0, 0.0)).with_text(text, size, [1.0; 4]))
+            .map_or((0.0, 0.0), |bounds| (bounds.width, bounds.height))
     }
 
     pub fn render_text(&mut self) -> Result<(), wgpu::SurfaceError> {
@@ -140,23 +675,59 @@ impl Renderer {
     }
 
     pub fn queue_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) {
-        let vertex_offset = self.queued_vertices.len() as u32;
+        self.queue_mesh(
+            TextureId::WHITE,
+            &[
+                Vertex::with_color(x, y, color),
+                Vertex::with_color(x + width, y, color),
+                Vertex::with_color(x + width, y + height, color),
+                Vertex::with_color(x, y + height, color),
+            ],
+            &QUAD_INDICES,
+        );
+    }
 
-        self.queued_vertices.extend_from_slice(&[
-            Vertex::with_color(x, y, color),
-            Vertex::with_color(x + width, y, color),
-            Vertex::with_color(x + width, y + height, color),
-            Vertex::with_color(x, y + height, color),
-        ]);
+    /// Draw a textured quad. `uv` is (min u, min v, max u, max v), use `FULL_UV` for the whole
+    /// texture. `color` tints the texture, white leaves it as is.
+    #[allow(clippy::too_many_arguments)]
+    pub fn queue_sprite(
+        &mut self,
+        texture: TextureId,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        uv: [f32; 4],
+        color: [f32; 4],
+    ) {
+        let [u0, v0, u1, v1] = uv;
+        self.queue_mesh(
+            texture,
+            &[
+                Vertex::with_tex_coords(x, y, u0, v0, color),
+                Vertex::with_tex_coords(x + width, y, u1, v0, color),
+                Vertex::with_tex_coords(x + width, y + height, u1, v1, color),
+                Vertex::with_tex_coords(x, y + height, u0, v1, color),
+            ],
+            &QUAD_INDICES,
+        );
+    }
 
-        self.queued_indices.extend_from_slice(&[
-            vertex_offset + 2,
-            vertex_offset + 1,
-            vertex_offset,
-            vertex_offset + 3,
-            vertex_offset + 2,
-            vertex_offset,
-        ]);
+    /// Draw a texture region cut into nine parts over a rectangle, keeping the corners at
+    /// `slice.scale` times their size in the texture
+    #[allow(clippy::too_many_arguments)]
+    pub fn queue_nine_slice(
+        &mut self,
+        texture: TextureId,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        slice: &NineSlice,
+        color: [f32; 4],
+    ) {
+        let (vertices, indices) = slice.mesh(x, y, width, height, color);
+        self.queue_mesh(texture, &vertices, &indices);
     }
 
     pub fn queue_square(&mut self, x: f32, y: f32, size: f32, color: [f32; 4]) {
@@ -164,28 +735,776 @@ impl Renderer {
     }
 
     pub fn queue_circle(&mut self, center_x: f32, center_y: f32, radius: f32, color: [f32; 4]) {
-        const SEGMENTS: usize = 32;
-        let vertex_offset = self.queued_vertices.len() as u32;
+        let mut tessellator = Tessellator::new(color, self.curve_quality);
+        tessellator.fan((center_x, center_y), radius, 0.0, TAU);
+        self.queue_tessellated(tessellator);
+    }
+
+    /// Straight line with flat ends
+    pub fn queue_line(&mut self, a: (f32, f32), b: (f32, f32), thickness: f32, color: [f32; 4]) {
+        self.queue_polyline(&[a, b], &StrokeStyle::new(thickness), false, color);
+    }
+
+    /// Connected line through `points`. `closed` also connects the last point to the first.
+    pub fn queue_polyline(
+        &mut self,
+        points: &[(f32, f32)],
+        style: &StrokeStyle,
+        closed: bool,
+        color: [f32; 4],
+    ) {
+        let mut tessellator = Tessellator::new(color, self.curve_quality);
+        tessellator.polyline(points, style, closed);
+        self.queue_tessellated(tessellator);
+    }
+
+    /// Rectangle outline. The stroke is drawn inside the rectangle so it covers the same area
+    /// as `queue_rectangle` would.
+    pub fn queue_rectangle_outline(
+        &mut self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        thickness: f32,
+        color: [f32; 4],
+    ) {
+        // Too thick to have a hole
+        if thickness * 2.0 >= width.min(height) {
+            self.queue_rectangle(x, y, width, height, color);
+            return;
+        }
+
+        let half = thickness / 2.0;
+        let (left, top) = (x + half, y + half);
+        let (right, bottom) = (x + width - half, y + height - half);
+        self.queue_polyline(
+            &[(left, top), (right, top), (right, bottom), (left, bottom)],
+            &StrokeStyle::new(thickness),
+            true,
+            color,
+        );
+    }
+
+    /// Circle outline, the stroke is centered on `radius`
+    pub fn queue_circle_outline(
+        &mut self,
+        center_x: f32,
+        center_y: f32,
+        radius: f32,
+        thickness: f32,
+        color: [f32; 4],
+    ) {
+        self.queue_arc(center_x, center_y, radius, 0.0, TAU, thickness, color);
+    }
+
+    /// Part of a circle outline from `start_angle` to `end_angle`, in radians clockwise from
+    /// the positive x axis (y points down)
+    #[allow(clippy::too_many_arguments)]
+    pub fn queue_arc(
+        &mut self,
+        center_x: f32,
+        center_y: f32,
+        radius: f32,
+        start_angle: f32,
+        end_angle: f32,
+        thickness: f32,
+        color: [f32; 4],
+    ) {
+        let half = thickness / 2.0;
+        let mut tessellator = Tessellator::new(color, self.curve_quality);
+        tessellator.ring(
+            (center_x, center_y),
+            (radius - half).max(0.0),
+            radius + half,
+            start_angle,
+            end_angle,
+        );
+        self.queue_tessellated(tessellator);
+    }
+
+    /// Filled path. Concave and self intersecting paths work, `rule` decides which parts of
+    /// overlapping sub paths are holes.
+    pub fn queue_path(&mut self, path: &ShapePath, rule: FillRule, color: [f32; 4]) {
+        let buffers = path::fill(path, rule, self.curve_quality.tolerance, color);
+        self.queue_mesh(TextureId::WHITE, &buffers.vertices, &buffers.indices);
+    }
+
+    /// Outline of a path, open sub paths get the style's caps
+    pub fn queue_path_outline(&mut self, path: &ShapePath, style: &StrokeStyle, color: [f32; 4]) {
+        let buffers = path::stroke(path, style, self.curve_quality.tolerance, color);
+        self.queue_mesh(TextureId::WHITE, &buffers.vertices, &buffers.indices);
+    }
+
+    /// Filled polygon through `points`, which doesn't have to be convex
+    pub fn queue_polygon(&mut self, points: &[(f32, f32)], color: [f32; 4]) {
+        self.queue_path(&ShapePath::polygon(points), FillRule::default(), color);
+    }
+
+    pub fn queue_rounded_rectangle(
+        &mut self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        radius: f32,
+        color: [f32; 4],
+    ) {
+        let path = ShapePath::rounded_rectangle(x, y, width, height, radius);
+        self.queue_path(&path, FillRule::default(), color);
+    }
+
+    /// Rectangle filled with a gradient. Gradient positions are in screen pixels too, so
+    /// `Gradient::linear((x, y), (x, y + height), ..)` runs from the top edge to the bottom.
+    pub fn queue_rectangle_gradient(
+        &mut self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        gradient: &Gradient,
+    ) {
+        let corners = [
+            (x, y),
+            (x + width, y),
+            (x + width, y + height),
+            (x, y + height),
+        ];
+        self.queue_gradient(&corners, &QUAD_INDICES, gradient);
+    }
+
+    pub fn queue_circle_gradient(
+        &mut self,
+        center_x: f32,
+        center_y: f32,
+        radius: f32,
+        gradient: &Gradient,
+    ) {
+        let mut tessellator = Tessellator::new([1.0; 4], self.curve_quality);
+        tessellator.fan((center_x, center_y), radius, 0.0, TAU);
+        self.queue_gradient_mesh(&tessellator.vertices, &tessellator.indices, gradient);
+    }
+
+    pub fn queue_polygon_gradient(&mut self, points: &[(f32, f32)], gradient: &Gradient) {
+        self.queue_path_gradient(&ShapePath::polygon(points), FillRule::default(), gradient);
+    }
+
+    pub fn queue_path_gradient(&mut self, path: &ShapePath, rule: FillRule, gradient: &Gradient) {
+        let buffers = path::fill(path, rule, self.curve_quality.tolerance, [1.0; 4]);
+        self.queue_gradient_mesh(&buffers.vertices, &buffers.indices, gradient);
+    }
+
+    /// Untextured geometry with its own per-vertex colors, batched along with every other
+    /// shape. Shorthand for `queue_mesh` with `TextureId::WHITE`.
+    pub fn queue_colored_mesh(&mut self, vertices: &[Vertex], indices: &[u32]) {
+        self.queue_mesh(TextureId::WHITE, vertices, indices);
+    }
+
+    fn queue_gradient_mesh(&mut self, vertices: &[Vertex], indices: &[u32], gradient: &Gradient) {
+        let positions: Vec<(f32, f32)> = vertices
+            .iter()
+            .map(|vertex| (vertex.position[0], vertex.position[1]))
+            .collect();
+        self.queue_gradient(&positions, indices, gradient);
+    }
+
+    fn queue_gradient(&mut self, positions: &[(f32, f32)], indices: &[u32], gradient: &Gradient) {
+        let (vertices, indices) = gradient.fill(positions, indices, &self.curve_quality);
+        self.queue_colored_mesh(&vertices, &indices);
+    }
+
+    /// Max distance in pixels between a curve and the straight segments it's drawn with.
+    /// Smaller is smoother but costs more vertices. Defaults to 0.25.
+    pub fn set_curve_tolerance(&mut self, tolerance: f32) {
+        self.curve_quality.tolerance = tolerance.max(0.01);
+    }
+
+    /// Always split full circles into this many segments (arcs get their share of them)
+    /// instead of picking a count based on the radius. `None` goes back to adaptive.
+    pub fn set_circle_segments(&mut self, segments: Option<usize>) {
+        self.curve_quality.circle_segments = segments.map(|segments| segments.max(3));
+    }
+
+    fn queue_tessellated(&mut self, tessellator: Tessellator) {
+        self.queue_mesh(
+            TextureId::WHITE,
+            &tessellator.vertices,
+            &tessellator.indices,
+        );
+    }
+
+    /// Compile and validate a material's shaders. Materials made from the same shaders share a
+    /// pipeline, so making many of them with different parameters is cheap.
+    pub fn create_material(
+        &mut self,
+        descriptor: &MaterialDescriptor,
+    ) -> Result<MaterialId, RenderError> {
+        if descriptor.params.len() > MAX_MATERIAL_PARAMS {
+            return Err(RenderError::ResourceError(format!(
+                "materials can have at most {MAX_MATERIAL_PARAMS} parameters"
+            )));
+        }
+        if descriptor.textures.len() > MAX_MATERIAL_TEXTURES {
+            return Err(RenderError::ResourceError(format!(
+                "materials can have at most {MAX_MATERIAL_TEXTURES} textures"
+            )));
+        }
+
+        let pipeline =
+            self.material_pipeline(descriptor.vertex.as_ref(), &descriptor.fragment, None)?;
+
+        let mut params = [[0.0f32; 4]; MAX_MATERIAL_PARAMS];
+        for (slot, (_, value)) in params.iter_mut().zip(&descriptor.params) {
+            *slot = *value;
+        }
+        let params_buffer = self.device.create_buffer_init(
+            &(wgpu::util::BufferInitDescriptor {
+                label: Some("Material Params Buffer"),
+                contents: bytemuck::cast_slice(&params),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }),
+        );
+
+        let mut textures = [TextureId::WHITE; MAX_MATERIAL_TEXTURES];
+        for (slot, texture) in textures.iter_mut().zip(&descriptor.textures) {
+            *slot = *texture;
+        }
+
+        let bind_group = self.material_bind_group(&params_buffer, &textures);
+        self.materials.push(Some(GpuMaterial {
+            pipeline,
+            shaders: (descriptor.vertex.clone(), descriptor.fragment.clone()),
+            masked: HashMap::new(),
+            bind_group,
+            params_buffer,
+            param_names: descriptor
+                .params
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect(),
+            textures,
+        }));
+        Ok(MaterialId(self.materials.len() - 1))
+    }
+
+    /// Free a material. Anything still drawn with it falls back to the built-in shader.
+    pub fn remove_material(&mut self, id: MaterialId) {
+        if id == MaterialId::DEFAULT {
+            return;
+        }
+        if let Some(slot) = self.materials.get_mut(id.0) {
+            *slot = None;
+        }
+        self.material_pipelines
+            .retain(|_, pipeline| Arc::strong_count(pipeline) > 1);
+    }
+
+    /// Material for everything queued from now on, until it's set again
+    pub fn set_material(&mut self, id: MaterialId) {
+        self.material = id;
+    }
+
+    pub fn material(&self) -> MaterialId {
+        self.material
+    }
+
+    /// Change a parameter by the name it was created with. Takes effect for the whole frame,
+    /// not just what's queued after this.
+    pub fn set_material_param(&mut self, id: MaterialId, name: &str, value: [f32; 4]) {
+        let Some(material) = self.materials.get(id.0).and_then(Option::as_ref) else {
+            log::warn!("Tried to update missing material {:?}", id);
+            return;
+        };
+        let Some(index) = material.param_index(name) else {
+            log::warn!("Material {:?} has no parameter `{name}`", id);
+            return;
+        };
+        self.queue.write_buffer(
+            &material.params_buffer,
+            (index * std::mem::size_of::<[f32; 4]>()) as wgpu::BufferAddress,
+            bytemuck::cast_slice(&value),
+        );
+    }
+
+    pub fn set_material_float(&mut self, id: MaterialId, name: &str, value: f32) {
+        self.set_material_param(id, name, [value, 0.0, 0.0, 0.0]);
+    }
+
+    pub fn set_material_color(&mut self, id: MaterialId, name: &str, color: [f32; 4]) {
+        self.set_material_param(id, name, color);
+    }
+
+    pub fn set_material_texture(&mut self, id: MaterialId, slot: usize, texture: TextureId) {
+        if slot >= MAX_MATERIAL_TEXTURES {
+            log::warn!("Materials only have {MAX_MATERIAL_TEXTURES} texture slots");
+            return;
+        }
+        let Some(material) = self.materials.get(id.0).and_then(Option::as_ref) else {
+            log::warn!("Tried to update missing material {:?}", id);
+            return;
+        };
+
+        let mut textures = material.textures;
+        textures[slot] = texture;
+        let bind_group = self.material_bind_group(&material.params_buffer, &textures);
+        if let Some(Some(material)) = self.materials.get_mut(id.0) {
+            material.bind_group = bind_group;
+            material.textures = textures;
+        }
+    }
+
+    /// Seconds passed to material shaders as `time`
+    pub fn set_time(&mut self, seconds: f32) {
+        self.time = seconds;
+    }
+
+    /// Swap a material's shaders, keeping its parameters and textures. If the new shaders
+    /// don't compile the material keeps drawing with the old ones, so this is safe to call
+    /// with half-edited files while hot reloading.
+    pub fn update_material_shaders(
+        &mut self,
+        id: MaterialId,
+        vertex: Option<&ShaderCode>,
+        fragment: &ShaderCode,
+    ) -> Result<(), RenderError> {
+        if !matches!(self.materials.get(id.0), Some(Some(_))) {
+            return Err(RenderError::ResourceError(format!("No material {id:?}")));
+        }
+
+        let pipeline = self.material_pipeline(vertex, fragment, None)?;
+        if let Some(Some(material)) = self.materials.get_mut(id.0) {
+            material.pipeline = pipeline;
+            material.shaders = (vertex.cloned(), fragment.clone());
+            material.masked.clear();
+        }
+        // Drop pipelines of shader versions nothing uses anymore
+        self.material_pipelines
+            .retain(|_, pipeline| Arc::strong_count(pipeline) > 1);
+        Ok(())
+    }
+
+    /// Swap only a material's fragment shader, keeping whichever vertex shader it has. Like
+    /// `update_material_shaders`, a shader that doesn't compile leaves the material as it was.
+    pub fn update_material_fragment(
+        &mut self,
+        id: MaterialId,
+        fragment: &ShaderCode,
+    ) -> Result<(), RenderError> {
+        let Some(Some(material)) = self.materials.get(id.0) else {
+            return Err(RenderError::ResourceError(format!("No material {id:?}")));
+        };
+        let vertex = material.shaders.0.clone();
+        self.update_material_shaders(id, vertex.as_ref(), fragment)
+    }
+
+    fn material_pipeline(
+        &mut self,
+        vertex: Option<&ShaderCode>,
+        fragment: &ShaderCode,
+        stencil: Option<Stencil>,
+    ) -> Result<Arc<wgpu::RenderPipeline>, RenderError> {
+        let key = (vertex.cloned(), fragment.clone(), stencil);
+        if let Some(pipeline) = self.material_pipelines.get(&key) {
+            return Ok(pipeline.clone());
+        }
+
+        let pipeline = Arc::new(self.create_material_pipeline(vertex, fragment, stencil)?);
+        self.material_pipelines.insert(key, pipeline.clone());
+        Ok(pipeline)
+    }
+
+    fn create_material_pipeline(
+        &self,
+        vertex: Option<&ShaderCode>,
+        fragment: &ShaderCode,
+        stencil: Option<Stencil>,
+    ) -> Result<wgpu::RenderPipeline, RenderError> {
+        let fragment = shader::compile(fragment, ShaderStage::Fragment)?;
+        let vertex = vertex
+            .map(|code| shader::compile(code, ShaderStage::Vertex))
+            .transpose()?;
+
+        // naga checked the shaders on their own, this catches them not fitting the pipeline
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let fragment_module =
+            create_wgsl_module(&self.device, "material fragment shader", &fragment.wgsl);
+        let vertex_module = vertex
+            .as_ref()
+            .map(|vertex| create_wgsl_module(&self.device, "material vertex shader", &vertex.wgsl));
+        let pipeline = create_render_pipeline(
+            &self.device,
+            &self.material_pipeline_layout,
+            self.config.format,
+            &[Vertex::DESC],
+            vertex_module.as_ref().unwrap_or(&self.vertex_shader),
+            vertex.as_ref().map_or("main", |vertex| &vertex.entry_point),
+            &fragment_module,
+            &fragment.entry_point,
+            wgpu::BlendState::ALPHA_BLENDING,
+            stencil,
+        );
+        if let Some(error) = pollster::block_on(self.device.pop_error_scope()) {
+            return Err(RenderError::ShaderError(ShaderDiagnostic::new(
+                error.to_string(),
+            )));
+        }
+        Ok(pipeline)
+    }
+
+    // Material bind groups hold on to the texture views they were made with, so they need
+    // making again when one of their textures is recreated or removed
+    fn rebuild_material_bind_groups(&mut self, texture: TextureId) {
+        for index in 0..self.materials.len() {
+            let Some(material) = self.materials[index]
+                .as_ref()
+                .filter(|material| material.textures.contains(&texture))
+            else {
+                continue;
+            };
+            let bind_group = self.material_bind_group(&material.params_buffer, &material.textures);
+            if let Some(material) = &mut self.materials[index] {
+                material.bind_group = bind_group;
+            }
+        }
+    }
+
+    fn material_bind_group(
+        &self,
+        params_buffer: &Buffer,
+        textures: &[TextureId; MAX_MATERIAL_TEXTURES],
+    ) -> BindGroup {
+        let views = textures.map(|texture| {
+            &self
+                .textures
+                .get(texture.0)
+                .and_then(Option::as_ref)
+                .unwrap_or_else(|| self.white_texture())
+                .view
+        });
+        create_material_bind_group(
+            &self.device,
+            &self.material_bind_group_layout,
+            &self.globals_buffer,
+            params_buffer,
+            views,
+            &self.linear_sampler,
+        )
+    }
+
+    /// Turn lighting on with `Some(color)`, the light everything gets even with no lights
+    /// nearby. `None` turns it off and draws everything fully lit again.
+    pub fn set_ambient_light(&mut self, color: Option<[f32; 4]>) {
+        self.ambient_light = color;
+    }
+
+    pub fn ambient_light(&self) -> Option<[f32; 4]> {
+        self.ambient_light
+    }
+
+    /// Light up part of this frame. Does nothing while lighting is off.
+    pub fn queue_light(&mut self, light: &Light) {
+        if light.radius > 0.0 {
+            self.queued_lights.push(*light);
+        }
+    }
+
+    /// Block lights queued this frame with the polygon through `points`, in pixels. Lights
+    /// inside an occluder shine through it, so a light can sit on a shape that casts shadows
+    /// from other lights.
+    pub fn queue_occluder(&mut self, points: &[(f32, f32)]) {
+        if points.len() >= 3 {
+            self.queued_occluders
+                .push(Occluder::new(points, self.curve_quality.tolerance));
+        }
+    }
+
+    /// Work out what each light can see and turn it into triangles for the light pass
+    fn build_light_vertices(&mut self) {
+        for light in &self.queued_lights {
+            light.vertices(&self.queued_occluders, &mut self.light_vertices);
+        }
+    }
+
+    /// Draw the queued lights into the light map, on top of the ambient color
+    fn draw_lights(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        targets: &PostTargets,
+        ambient: [f32; 4],
+    ) {
+        let mut render_pass = encoder.begin_render_pass(
+            &(wgpu::RenderPassDescriptor {
+                label: Some("Light Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &targets.light_map.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: ambient[0] as f64,
+                            g: ambient[1] as f64,
+                            b: ambient[2] as f64,
+                            a: 1.0,
+                        }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            }),
+        );
+
+        if !self.light_vertices.is_empty() {
+            render_pass.set_pipeline(&self.light_pipeline);
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.light_buffer.slice(..));
+            render_pass.draw(0..self.light_vertices.len() as u32, 0..1);
+        }
+    }
+
+    /// Fullscreen effects applied to every frame
+    pub fn post_process(&self) -> &PostProcess {
+        &self.post_process
+    }
+
+    pub fn post_process_mut(&mut self) -> &mut PostProcess {
+        &mut self.post_process
+    }
+
+    /// Make sure the offscreen textures match the screen and everything lighting and the
+    /// effects need is created
+    fn prepare_offscreen(&mut self) {
+        let size = (self.config.width, self.config.height);
+        if self
+            .post_targets
+            .as_ref()
+            .is_none_or(|targets| (targets.width, targets.height) != size)
+        {
+            self.post_targets = Some(PostTargets::new(
+                &self.device,
+                &self.texture_bind_group_layout,
+                &self.linear_sampler,
+                self.config.format,
+                size.0,
+                size.1,
+            ));
+        }
 
-        // Center vertex
-        self.queued_vertices
-            .push(Vertex::with_color(center_x, center_y, color));
+        if self.post_pipelines.is_empty() {
+            self.post_pipelines = PostShader::ALL
+                .iter()
+                .map(|shader| {
+                    self.material_pipeline(None, &ShaderCode::wgsl(shader.source()), None)
+                        .unwrap_or_else(|e| panic!("Built-in post effect doesn't compile: {e}"))
+                })
+                .collect();
+        }
 
-        for i in 0..SEGMENTS {
-            let angle = (2.0 * std::f32::consts::PI * (i as f32)) / (SEGMENTS as f32);
-            let x = center_x + radius * angle.cos();
-            let y = center_y + radius * angle.sin();
-            self.queued_vertices.push(Vertex::with_color(x, y, color));
+        // The first is for lighting
+        let passes = 1 + self
+            .post_process
+            .enabled()
+            .map(|effect| match effect {
+                PostEffect::Bloom { .. } => 4,
+                _ => 1,
+            })
+            .sum::<usize>();
+        while self.post_params.len() < passes {
+            self.post_params.push(self.device.create_buffer(
+                &(wgpu::BufferDescriptor {
+                    label: Some("Post Process Params Buffer"),
+                    size: (MAX_MATERIAL_PARAMS * std::mem::size_of::<[f32; 4]>()) as u64,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+            ));
         }
+    }
+
+    /// Run the frame in post target `source` through every enabled effect, the last one
+    /// drawing into `output`
+    fn run_post_process(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        mut source: usize,
+        output: &wgpu::TextureView,
+    ) {
+        let Some(targets) = &self.post_targets else {
+            return;
+        };
+        let effects: Vec<&PostEffect> = self.post_process.enabled().collect();
+        let mut params = self.post_params.iter().skip(1);
+        let mut next_params = || {
+            params
+                .next()
+                .expect("Post params are allocated in prepare_offscreen")
+        };
+
+        for (i, effect) in effects.iter().enumerate() {
+            let from = &targets.frames[source];
+            let target = if i + 1 == effects.len() {
+                output
+            } else {
+                &targets.frames[1 - source].view
+            };
+
+            let mut pass = |shader,
+                            values: [f32; 4],
+                            from: &GpuTexture,
+                            extra: Option<&wgpu::TextureView>,
+                            target: &wgpu::TextureView| {
+                self.post_shader_pass(
+                    encoder,
+                    targets,
+                    shader,
+                    next_params(),
+                    values,
+                    from,
+                    extra,
+                    target,
+                );
+            };
+
+            match **effect {
+                PostEffect::Bloom {
+                    threshold,
+                    intensity,
+                    radius,
+                } => {
+                    // Bright parts at half resolution, blurred one way then the other
+                    let [bright, blurred] = &targets.bloom;
+                    let values = [threshold, 0.0, 0.0, 0.0];
+                    pass(PostShader::BloomExtract, values, from, None, &bright.view);
+                    let values = [1.0, 0.0, radius, 0.0];
+                    pass(PostShader::Blur, values, bright, None, &blurred.view);
+                    let values = [0.0, 1.0, radius, 0.0];
+                    pass(PostShader::Blur, values, blurred, None, &bright.view);
+                    let (values, glow) = ([intensity, 0.0, 0.0, 0.0], Some(&bright.view));
+                    pass(PostShader::BloomComposite, values, from, glow, target);
+                }
+                PostEffect::Vignette {
+                    intensity,
+                    radius,
+                    softness,
+                } => {
+                    let values = [intensity, radius, softness, 0.0];
+                    pass(PostShader::Vignette, values, from, None, target);
+                }
+                PostEffect::Crt {
+                    scanlines,
+                    curvature,
+                } => {
+                    let values = [scanlines, curvature, 0.0, 0.0];
+                    pass(PostShader::Crt, values, from, None, target);
+                }
+                PostEffect::ColorGrade { lut, intensity } => {
+                    // A missing table leaves the colors alone
+                    let lut = self.textures.get(lut.0).and_then(Option::as_ref);
+                    match lut.map(|lut| &lut.view) {
+                        Some(lut) => {
+                            let values = [intensity, 0.0, 0.0, 0.0];
+                            pass(PostShader::ColorGrade, values, from, Some(lut), target);
+                        }
+                        None => pass(PostShader::Copy, [0.0; 4], from, None, target),
+                    }
+                }
+                PostEffect::ChromaticAberration { offset } => {
+                    let values = [offset, 0.0, 0.0, 0.0];
+                    pass(PostShader::ChromaticAberration, values, from, None, target);
+                }
+                PostEffect::Pixelate { size } => {
+                    let values = [size, 0.0, 0.0, 0.0];
+                    pass(PostShader::Pixelate, values, from, None, target);
+                }
+                // Removed materials are skipped, the frame is passed on as is
+                PostEffect::Custom(id) => match self.materials.get(id.0).and_then(Option::as_ref) {
+                    Some(material) => self.draw_post_pass(
+                        encoder,
+                        targets,
+                        &material.pipeline,
+                        &from.bind_group,
+                        &material.bind_group,
+                        target,
+                    ),
+                    None => pass(PostShader::Copy, [0.0; 4], from, None, target),
+                },
+            }
 
-        for i in 0..SEGMENTS {
-            let next = if i == SEGMENTS - 1 { 1 } else { i + 2 };
-            self.queued_indices.push(vertex_offset + (next as u32));
-            self.queued_indices.push(vertex_offset + ((i + 1) as u32));
-            self.queued_indices.push(vertex_offset);
+            source = 1 - source;
         }
     }
 
+    /// One built-in effect shader, reading `from` and drawing into `target`
+    #[allow(clippy::too_many_arguments)]
+    fn post_shader_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        targets: &PostTargets,
+        shader: PostShader,
+        params: &Buffer,
+        values: [f32; 4],
+        from: &GpuTexture,
+        extra: Option<&wgpu::TextureView>,
+        target: &wgpu::TextureView,
+    ) {
+        self.queue
+            .write_buffer(params, 0, bytemuck::cast_slice(&values));
+        let extra = extra.unwrap_or(&self.white_texture().view);
+        let bind_group = create_material_bind_group(
+            &self.device,
+            &self.material_bind_group_layout,
+            &self.globals_buffer,
+            params,
+            [extra; MAX_MATERIAL_TEXTURES],
+            &self.linear_sampler,
+        );
+        self.draw_post_pass(
+            encoder,
+            targets,
+            &self.post_pipelines[shader as usize],
+            &from.bind_group,
+            &bind_group,
+            target,
+        );
+    }
+
+    fn draw_post_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        targets: &PostTargets,
+        pipeline: &wgpu::RenderPipeline,
+        from: &BindGroup,
+        material: &BindGroup,
+        target: &wgpu::TextureView,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(
+            &(wgpu::RenderPassDescriptor {
+                label: Some("Post Process Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            }),
+        );
+
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_bind_group(1, from, &[]);
+        render_pass.set_bind_group(2, material, &[]);
+        render_pass.set_vertex_buffer(0, targets.quad_vertices.slice(..));
+        render_pass.set_index_buffer(targets.quad_indices.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..QUAD_INDICES.len() as u32, 0, 0..1);
+    }
+
     pub fn begin_frame(&mut self) -> Result<(), wgpu::SurfaceError> {
         self.surface.get_current_texture()?;
         Ok(())
@@ -214,17 +1533,137 @@ impl Renderer {
 
         render_pass.set_pipeline(&self.pipeline);
         render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_bind_group(1, &self.white_texture().bind_group, &[]);
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
         render_pass.draw_indexed(0..num_indices, 0, 0..1);
     }
 
+    fn white_texture(&self) -> &GpuTexture {
+        self.textures[TextureId::WHITE.0]
+            .as_ref()
+            .expect("White texture is never removed")
+    }
+
+    /// Grow the vertex, index and light buffers if this frame queued more than they can hold
+    fn ensure_buffer_capacity(&mut self) {
+        let vertex_bytes = self.queued_vertices.len() as wgpu::BufferAddress * Vertex::SIZE;
+        if vertex_bytes > self.vertex_buffer.size() {
+            self.vertex_buffer =
+                create_vertex_buffer(&self.device, vertex_bytes.next_power_of_two());
+        }
+
+        let light_bytes = self.light_vertices.len() as wgpu::BufferAddress * Vertex::SIZE;
+        if light_bytes > self.light_buffer.size() {
+            self.light_buffer = create_vertex_buffer(&self.device, light_bytes.next_power_of_two());
+        }
+
+        let index_bytes = self.queued_indices.len() as wgpu::BufferAddress * U32_SIZE;
+        if index_bytes > self.index_buffer.size() {
+            self.index_buffer = create_index_buffer(&self.device, index_bytes.next_power_of_two());
+        }
+    }
+
+    /// Make sure the stencil buffer matches the screen and every pipeline this frame's batches
+    /// need to draw with it exists
+    fn prepare_masking(&mut self) {
+        let size = (self.config.width, self.config.height);
+        if self
+            .stencil_target
+            .as_ref()
+            .is_none_or(|target| (target.width, target.height) != size)
+        {
+            self.stencil_target = Some(StencilTarget::new(&self.device, size.0, size.1));
+        }
+
+        let needed: HashSet<(MaterialId, Stencil)> = self
+            .batches
+            .iter()
+            .map(|batch| (batch.material, batch.stencil.unwrap_or(Stencil::Ignore)))
+            .collect();
+        for (id, stencil) in needed {
+            if !self.masked_pipelines.contains_key(&stencil) {
+                let pipeline = |vertex_shader| {
+                    create_render_pipeline(
+                        &self.device,
+                        &self.pipeline_layout,
+                        self.config.format,
+                        &[Vertex::DESC],
+                        vertex_shader,
+                        "main",
+                        &self.fragment_shader,
+                        "main",
+                        wgpu::BlendState::ALPHA_BLENDING,
+                        Some(stencil),
+                    )
+                };
+                let pipelines = (
+                    pipeline(&self.vertex_shader),
+                    pipeline(&self.static_mesh_shader),
+                );
+                self.masked_pipelines.insert(stencil, pipelines);
+            }
+
+            let Some(Some(material)) = self.materials.get(id.0) else {
+                continue;
+            };
+            if material.masked.contains_key(&stencil) {
+                continue;
+            }
+            let (vertex, fragment) = material.shaders.clone();
+            match self.material_pipeline(vertex.as_ref(), &fragment, Some(stencil)) {
+                Ok(pipeline) => {
+                    if let Some(Some(material)) = self.materials.get_mut(id.0) {
+                        material.masked.insert(stencil, pipeline);
+                    }
+                }
+                // Shouldn't happen since the shaders already compiled once. The batch falls
+                // back to the built-in pipeline.
+                Err(e) => log::warn!("Couldn't make masked pipeline for {id:?}: {e}"),
+            }
+        }
+    }
+
+    /// The built-in and static mesh pipelines for a batch's stencil use
+    fn builtin_pipelines(
+        &self,
+        stencil: Option<Stencil>,
+    ) -> (&wgpu::RenderPipeline, &wgpu::RenderPipeline) {
+        match stencil.and_then(|stencil| self.masked_pipelines.get(&stencil)) {
+            Some((pipeline, static_mesh)) => (pipeline, static_mesh),
+            None => (&self.pipeline, &self.static_mesh_pipeline),
+        }
+    }
+
     pub fn render_frame(&mut self) -> Result<(), wgpu::SurfaceError> {
         match self.surface.get_current_texture() {
             Ok(frame) => {
                 let view = frame.texture.create_view(&Default::default());
 
+                // With lighting or effects on, the frame is drawn into a texture they can read
+                let lighting = self.ambient_light;
+                let post_process = self.post_process.is_active();
+                if lighting.is_some() || post_process {
+                    self.prepare_offscreen();
+                }
+                if lighting.is_some() {
+                    self.build_light_vertices();
+                }
+                // The stencil buffer is only attached when something needs it
+                let masking = self.batches.iter().any(|batch| batch.stencil.is_some());
+                if masking {
+                    self.prepare_masking();
+                }
+
                 // Handle buffer uploads
+                self.ensure_buffer_capacity();
+                if !self.light_vertices.is_empty() {
+                    self.queue.write_buffer(
+                        &self.light_buffer,
+                        0,
+                        bytemuck::cast_slice(&self.light_vertices),
+                    );
+                }
                 if !self.queued_vertices.is_empty() {
                     self.queue.write_buffer(
                         &self.vertex_buffer,
@@ -237,18 +1676,29 @@ impl Renderer {
                         bytemuck::cast_slice(&self.queued_indices),
                     );
                 }
+                self.queue.write_buffer(
+                    &self.globals_buffer,
+                    0,
+                    bytemuck::cast_slice(&[self.time, 0.0, self.width(), self.height()]),
+                );
 
                 let mut encoder = self
                     .device
                     .create_command_encoder(&(wgpu::CommandEncoderDescriptor { label: None }));
 
+                let offscreen = self
+                    .post_targets
+                    .as_ref()
+                    .filter(|_| lighting.is_some() || post_process);
+                let frame_view = offscreen.map_or(&view, |targets| &targets.frames[0].view);
+
                 // Create render pass with clear and render shapes
                 {
                     let mut render_pass = encoder.begin_render_pass(
                         &(wgpu::RenderPassDescriptor {
                             label: Some("Shape Render Pass"),
                             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                                view: &view,
+                                view: frame_view,
                                 resolve_target: None,
                                 ops: wgpu::Operations {
                                     load: wgpu::LoadOp::Clear(wgpu::Color {
@@ -261,36 +1711,197 @@ impl Renderer {
                                 },
                                 depth_slice: None,
                             })],
-                            depth_stencil_attachment: None,
+                            depth_stencil_attachment: self
+                                .stencil_target
+                                .as_ref()
+                                .filter(|_| masking)
+                                .map(|target| wgpu::RenderPassDepthStencilAttachment {
+                                    view: &target.view,
+                                    depth_ops: None,
+                                    stencil_ops: Some(wgpu::Operations {
+                                        load: wgpu::LoadOp::Clear(0),
+                                        store: wgpu::StoreOp::Discard,
+                                    }),
+                                }),
                             timestamp_writes: None,
                             occlusion_query_set: None,
                         }),
                     );
 
-                    if !self.queued_vertices.is_empty() {
-                        render_pass.set_pipeline(&self.pipeline);
+                    if !self.batches.is_empty() {
                         render_pass.set_bind_group(0, &self.bind_group, &[]);
                         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
                         render_pass.set_index_buffer(
                             self.index_buffer.slice(..),
                             wgpu::IndexFormat::Uint32,
                         );
-                        render_pass.draw_indexed(0..self.queued_indices.len() as u32, 0, 0..1);
+
+                        let (target_width, target_height) = (self.config.width, self.config.height);
+                        let mut bound_material = None;
+                        let mut bound_clip = None;
+                        let mut bound_mask = 0;
+                        for batch in &self.batches {
+                            if batch.clip != bound_clip {
+                                let scissor = match batch.clip {
+                                    Some(clip) => clip.scissor(target_width, target_height),
+                                    None => Some([0, 0, target_width, target_height]),
+                                };
+                                // Clipped away entirely
+                                let Some([x, y, width, height]) = scissor else {
+                                    continue;
+                                };
+                                render_pass.set_scissor_rect(x, y, width, height);
+                                bound_clip = batch.clip;
+                            }
+                            if masking && batch.mask != bound_mask {
+                                render_pass.set_stencil_reference(batch.mask);
+                                bound_mask = batch.mask;
+                            }
+                            // With a stencil buffer attached every pipeline has to use it
+                            let stencil = masking.then(|| batch.stencil.unwrap_or(Stencil::Ignore));
+                            let (builtin_pipeline, static_mesh_pipeline) =
+                                self.builtin_pipelines(stencil);
+
+                            if let Some(id) = batch.static_mesh {
+                                let Some(mesh) = self.static_meshes[id.0].as_ref() else {
+                                    continue;
+                                };
+                                let texture = self
+                                    .textures
+                                    .get(mesh.texture.0)
+                                    .and_then(Option::as_ref)
+                                    .unwrap_or_else(|| self.white_texture());
+                                render_pass.set_pipeline(static_mesh_pipeline);
+                                render_pass.set_bind_group(0, &mesh.bind_group, &[]);
+                                render_pass.set_bind_group(1, &texture.bind_group, &[]);
+                                render_pass.set_vertex_buffer(0, mesh.vertices.slice(..));
+                                render_pass.set_index_buffer(
+                                    mesh.indices.slice(..),
+                                    wgpu::IndexFormat::Uint32,
+                                );
+                                render_pass.draw_indexed(0..mesh.index_count, 0, 0..1);
+
+                                // Back to the queued geometry for whatever comes next
+                                render_pass.set_bind_group(0, &self.bind_group, &[]);
+                                render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+                                render_pass.set_index_buffer(
+                                    self.index_buffer.slice(..),
+                                    wgpu::IndexFormat::Uint32,
+                                );
+                                bound_material = None;
+                                continue;
+                            }
+
+                            if bound_material != Some((batch.material, stencil)) {
+                                // Removed materials fall back to the built-in pipeline
+                                let material = self
+                                    .materials
+                                    .get(batch.material.0)
+                                    .and_then(Option::as_ref);
+                                let material_pipeline =
+                                    material.and_then(|material| match stencil {
+                                        Some(stencil) => material.masked.get(&stencil),
+                                        None => Some(&material.pipeline),
+                                    });
+                                match (material, material_pipeline) {
+                                    (Some(material), Some(pipeline)) => {
+                                        render_pass.set_pipeline(pipeline);
+                                        render_pass.set_bind_group(2, &material.bind_group, &[]);
+                                    }
+                                    _ => render_pass.set_pipeline(builtin_pipeline),
+                                }
+                                bound_material = Some((batch.material, stencil));
+                            }
+
+                            // Textures removed mid-frame fall back to white instead of crashing
+                            let texture = self
+                                .textures
+                                .get(batch.texture.0)
+                                .and_then(Option::as_ref)
+                                .unwrap_or_else(|| self.white_texture());
+                            render_pass.set_bind_group(1, &texture.bind_group, &[]);
+                            render_pass.draw_indexed(batch.start..batch.end, 0, 0..1);
+                        }
                     }
                 }
 
+                // Light the frame, straight onto the screen unless effects come after
+                let mut source = 0;
+                if let (Some(targets), Some(ambient)) = (offscreen, lighting) {
+                    self.draw_lights(&mut encoder, targets, ambient);
+                    let target = if post_process {
+                        &targets.frames[1].view
+                    } else {
+                        &view
+                    };
+                    self.post_shader_pass(
+                        &mut encoder,
+                        targets,
+                        PostShader::LightComposite,
+                        &self.post_params[0],
+                        [0.0; 4],
+                        &targets.frames[0],
+                        Some(&targets.light_map.view),
+                        target,
+                    );
+                    source = 1;
+                }
+                let text_view = match offscreen {
+                    Some(targets) if post_process => &targets.frames[source].view,
+                    _ => &view,
+                };
+
                 // Render text on top
                 self.glyph_brush
                     .draw_queued(
                         &self.device,
                         &mut self.staging_belt,
                         &mut encoder,
-                        &view,
+                        text_view,
                         self.config.width,
                         self.config.height,
                     )
                     .unwrap();
 
+                // Then clipped text, one draw for every run queued under the same clip rect
+                for run in self
+                    .clipped_text
+                    .chunk_by(|(clip, _), (next, _)| clip == next)
+                {
+                    let Some([x, y, width, height]) =
+                        run[0].0.scissor(self.config.width, self.config.height)
+                    else {
+                        continue;
+                    };
+                    for (_, section) in run {
+                        let layout = section.layout();
+                        self.glyph_brush
+                            .queue_custom_layout(glyph_section(section), &layout);
+                    }
+                    self.glyph_brush
+                        .draw_queued_with_transform_and_scissoring(
+                            &self.device,
+                            &mut self.staging_belt,
+                            &mut encoder,
+                            text_view,
+                            wgpu_glyph::orthographic_projection(
+                                self.config.width,
+                                self.config.height,
+                            ),
+                            wgpu_glyph::Region {
+                                x,
+                                y,
+                                width,
+                                height,
+                            },
+                        )
+                        .unwrap();
+                }
+
+                if post_process {
+                    self.run_post_process(&mut encoder, source, &view);
+                }
+
                 self.staging_belt.finish();
                 self.queue.submit(iter::once(encoder.finish()));
                 frame.present();
@@ -298,6 +1909,14 @@ impl Renderer {
                 // Clear queued data for next frame
                 self.queued_vertices.clear();
                 self.queued_indices.clear();
+                self.batches.clear();
+                self.queued_lights.clear();
+                self.queued_occluders.clear();
+                self.light_vertices.clear();
+                self.clipped_text.clear();
+                self.clip_stack.clear();
+                self.stencil = None;
+                self.mask = 0;
 
                 // Reclaim staging belt memory
                 // If we don't do this, we get a memory leak.
@@ -309,3 +1928,23 @@ impl Renderer {
         }
     }
 }
+
+fn glyph_section(section: &TextSection) -> Section<'_> {
+    let text = section
+        .spans
+        .iter()
+        .map(|span| {
+            Text::new(&span.text)
+                .with_font_id(wgpu_glyph::FontId(span.font.0))
+                .with_color(span.color)
+                .with_scale(span.size)
+        })
+        .collect();
+
+    Section {
+        screen_position: section.position,
+        bounds: section.bounds(),
+        layout: wgpu_glyph::Layout::default(),
+        text,
+    }
+}
@@ -0,0 +1,161 @@
+//! Loading and checking shaders at runtime. Everything goes through naga before it gets anywhere
+//! near the GPU, so mistakes come back as readable errors with line numbers instead of wgpu
+//! panics.
+
+use std::path::{Path, PathBuf};
+
+use naga::{
+    Module, ShaderStage, SourceLocation,
+    back::wgsl,
+    front::glsl,
+    valid::{Capabilities, ValidationFlags, Validator},
+};
+
+use crate::error::{RenderError, ShaderDiagnostic};
+
+/// The languages the renderer understands
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ShaderLanguage {
+    Wgsl,
+    /// GLSL 450, translated to WGSL when it's compiled
+    Glsl,
+}
+
+impl ShaderLanguage {
+    /// `.wgsl` is WGSL, `.vert`, `.frag` and `.glsl` are GLSL
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "wgsl" => Some(Self::Wgsl),
+            "vert" | "frag" | "glsl" => Some(Self::Glsl),
+            _ => None,
+        }
+    }
+}
+
+/// Shader source code, and where it came from for error messages
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ShaderCode {
+    pub language: ShaderLanguage,
+    pub source: String,
+    pub path: Option<PathBuf>,
+}
+
+impl ShaderCode {
+    pub fn wgsl(source: impl Into<String>) -> Self {
+        Self {
+            language: ShaderLanguage::Wgsl,
+            source: source.into(),
+            path: None,
+        }
+    }
+
+    pub fn glsl(source: impl Into<String>) -> Self {
+        Self {
+            language: ShaderLanguage::Glsl,
+            source: source.into(),
+            path: None,
+        }
+    }
+
+    /// Read a shader file, the language is picked by its extension
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RenderError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|e| {
+            RenderError::ResourceError(format!("Failed to read {}: {e}", path.display()))
+        })?;
+        Self::from_source(source, path)
+    }
+
+    /// Shader code that was read from `path` some other way
+    pub fn from_source(source: impl Into<String>, path: &Path) -> Result<Self, RenderError> {
+        let language = ShaderLanguage::from_path(path).ok_or_else(|| {
+            RenderError::ResourceError(format!("Unknown shader type: {}", path.display()))
+        })?;
+        Ok(Self {
+            language,
+            source: source.into(),
+            path: Some(path.to_path_buf()),
+        })
+    }
+
+    fn error(
+        &self,
+        message: String,
+        location: Option<SourceLocation>,
+        report: String,
+    ) -> RenderError {
+        RenderError::ShaderError(ShaderDiagnostic {
+            message,
+            path: self.path.clone(),
+            line: location.map(|location| location.line_number),
+            column: location.map(|location| location.line_position),
+            report,
+        })
+    }
+}
+
+/// A shader stage that passed validation, as WGSL wgpu can take as is
+#[derive(Clone, Debug)]
+pub(crate) struct CompiledShader {
+    pub(crate) wgsl: String,
+    pub(crate) entry_point: String,
+}
+
+/// Parse and validate `code`, and find its entry point for `stage`
+pub(crate) fn compile(
+    code: &ShaderCode,
+    stage: ShaderStage,
+) -> Result<CompiledShader, RenderError> {
+    let source = code.source.as_str();
+    let module = match code.language {
+        ShaderLanguage::Wgsl => naga::front::wgsl::parse_str(source).map_err(|e| {
+            code.error(
+                e.message().to_string(),
+                e.location(source),
+                e.emit_to_string(source),
+            )
+        })?,
+        ShaderLanguage::Glsl => glsl::Frontend::default()
+            .parse(&glsl::Options::from(stage), source)
+            .map_err(|e| {
+                let first = e.errors.first();
+                code.error(
+                    first.map_or_else(String::new, |error| error.kind.to_string()),
+                    first.and_then(|error| error.location(source)),
+                    e.emit_to_string(source),
+                )
+            })?,
+    };
+
+    let info = Validator::new(ValidationFlags::all(), Capabilities::empty())
+        .validate(&module)
+        .map_err(|e| {
+            code.error(
+                e.as_inner().to_string(),
+                e.location(source),
+                e.emit_to_string(source),
+            )
+        })?;
+
+    let entry_point = entry_point(&module, stage).ok_or_else(|| {
+        let message = format!("no {stage:?} entry point");
+        code.error(message.clone(), None, message)
+    })?;
+
+    let wgsl = match code.language {
+        // Passed through untouched so wgpu's own errors point at the user's lines
+        ShaderLanguage::Wgsl => code.source.clone(),
+        ShaderLanguage::Glsl => wgsl::write_string(&module, &info, wgsl::WriterFlags::empty())
+            .map_err(|e| code.error(e.to_string(), None, e.to_string()))?,
+    };
+
+    Ok(CompiledShader { wgsl, entry_point })
+}
+
+fn entry_point(module: &Module, stage: ShaderStage) -> Option<String> {
+    module
+        .entry_points
+        .iter()
+        .find(|entry_point| entry_point.stage == stage)
+        .map(|entry_point| entry_point.name.clone())
+}
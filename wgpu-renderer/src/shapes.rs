@@ -0,0 +1,312 @@
+//! Turning lines, outlines and curves into triangles
+
+use std::f32::consts::{PI, TAU};
+
+use crate::types::Vertex;
+
+/// How two segments of a polyline are connected
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum LineJoin {
+    /// Sharp corner. Falls back to `Bevel` on very sharp angles so the tip doesn't shoot off.
+    #[default]
+    Miter,
+    /// Corner cut off flat
+    Bevel,
+    Round,
+}
+
+/// How the open ends of a polyline look
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum LineCap {
+    /// Ends exactly at the end point
+    #[default]
+    Butt,
+    /// Extends past the end point by half the thickness
+    Square,
+    Round,
+}
+
+/// How a line is stroked
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct StrokeStyle {
+    pub thickness: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        Self {
+            thickness: 1.0,
+            join: LineJoin::default(),
+            cap: LineCap::default(),
+        }
+    }
+}
+
+impl StrokeStyle {
+    pub fn new(thickness: f32) -> Self {
+        Self {
+            thickness,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_join(mut self, join: LineJoin) -> Self {
+        self.join = join;
+        self
+    }
+
+    pub fn with_cap(mut self, cap: LineCap) -> Self {
+        self.cap = cap;
+        self
+    }
+}
+
+// Miters longer than this many half thicknesses are beveled instead
+const MITER_LIMIT: f32 = 4.0;
+
+type Point = (f32, f32);
+
+/// Decides how many segments curves are split into
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct CurveQuality {
+    /// Max distance in pixels between the real curve and its segments
+    pub(crate) tolerance: f32,
+    /// Fixed segment count for a full circle instead of adapting to the radius
+    pub(crate) circle_segments: Option<usize>,
+}
+
+impl Default for CurveQuality {
+    fn default() -> Self {
+        Self {
+            tolerance: 0.25,
+            circle_segments: None,
+        }
+    }
+}
+
+impl CurveQuality {
+    /// Segments needed for an arc of `angle` radians
+    pub(crate) fn segments(&self, radius: f32, angle: f32) -> usize {
+        let fraction = angle.abs() / TAU;
+        let full_circle = match self.circle_segments {
+            Some(segments) => segments as f32,
+            None => {
+                // Each segment's chord can be this far off the circle: r * (1 - cos(step / 2))
+                let ratio = (1.0 - self.tolerance / radius.max(self.tolerance)).clamp(-1.0, 1.0);
+                let step = 2.0 * ratio.acos();
+                if step <= f32::EPSILON {
+                    256.0
+                } else {
+                    (TAU / step).clamp(8.0, 256.0)
+                }
+            }
+        };
+        ((full_circle * fraction).ceil() as usize).max(1)
+    }
+}
+
+/// Collects triangles in a single color
+pub(crate) struct Tessellator {
+    pub(crate) vertices: Vec<Vertex>,
+    pub(crate) indices: Vec<u32>,
+    color: [f32; 4],
+    quality: CurveQuality,
+}
+
+impl Tessellator {
+    pub(crate) fn new(color: [f32; 4], quality: CurveQuality) -> Self {
+        Self {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            color,
+            quality,
+        }
+    }
+
+    fn vertex(&mut self, (x, y): Point) -> u32 {
+        self.vertices.push(Vertex::with_color(x, y, self.color));
+        (self.vertices.len() - 1) as u32
+    }
+
+    fn triangle(&mut self, a: Point, b: Point, c: Point) {
+        let a = self.vertex(a);
+        let b = self.vertex(b);
+        let c = self.vertex(c);
+        self.indices.extend([a, b, c]);
+    }
+
+    fn quad(&mut self, a: Point, b: Point, c: Point, d: Point) {
+        let a = self.vertex(a);
+        let b = self.vertex(b);
+        let c = self.vertex(c);
+        let d = self.vertex(d);
+        self.indices.extend([a, b, c, a, c, d]);
+    }
+
+    /// Filled pie slice from `start` to `end` radians
+    pub(crate) fn fan(&mut self, center: Point, radius: f32, start: f32, end: f32) {
+        let segments = self.quality.segments(radius, end - start);
+        let center_index = self.vertex(center);
+        let first = self.vertices.len() as u32;
+
+        for i in 0..=segments {
+            let angle = start + (end - start) * i as f32 / segments as f32;
+            self.vertex(point_on_circle(center, radius, angle));
+        }
+        for i in 0..segments as u32 {
+            self.indices
+                .extend([center_index, first + i, first + i + 1]);
+        }
+    }
+
+    /// Band between two radii from `start` to `end` radians
+    pub(crate) fn ring(&mut self, center: Point, inner: f32, outer: f32, start: f32, end: f32) {
+        let segments = self.quality.segments(outer, end - start);
+        let first = self.vertices.len() as u32;
+
+        for i in 0..=segments {
+            let angle = start + (end - start) * i as f32 / segments as f32;
+            self.vertex(point_on_circle(center, inner, angle));
+            self.vertex(point_on_circle(center, outer, angle));
+        }
+        for i in 0..segments as u32 {
+            let (inner_a, outer_a) = (first + i * 2, first + i * 2 + 1);
+            let (inner_b, outer_b) = (inner_a + 2, outer_a + 2);
+            self.indices
+                .extend([inner_a, outer_a, outer_b, inner_a, outer_b, inner_b]);
+        }
+    }
+
+    /// Stroke through `points`. Segments are separate quads with the joins filled in on the
+    /// outside of each corner, so translucent lines get slightly darker on the inside of
+    /// corners where they overlap.
+    pub(crate) fn polyline(&mut self, points: &[Point], style: &StrokeStyle, closed: bool) {
+        // Zero length segments have no direction to stroke along
+        let mut points: Vec<Point> = points.to_vec();
+        points.dedup();
+        if closed && points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        if points.len() < 2 || style.thickness <= 0.0 {
+            return;
+        }
+
+        let half = style.thickness / 2.0;
+        let segment_count = if closed {
+            points.len()
+        } else {
+            points.len() - 1
+        };
+        let segment = |i: usize| (points[i], points[(i + 1) % points.len()]);
+
+        for i in 0..segment_count {
+            let (mut a, mut b) = segment(i);
+            let along = direction(a, b);
+
+            // Square caps just make the first and last segments longer
+            if !closed && style.cap == LineCap::Square {
+                if i == 0 {
+                    a = (a.0 - along.0 * half, a.1 - along.1 * half);
+                }
+                if i == segment_count - 1 {
+                    b = (b.0 + along.0 * half, b.1 + along.1 * half);
+                }
+            }
+
+            let normal = (-along.1 * half, along.0 * half);
+            self.quad(
+                (a.0 + normal.0, a.1 + normal.1),
+                (b.0 + normal.0, b.1 + normal.1),
+                (b.0 - normal.0, b.1 - normal.1),
+                (a.0 - normal.0, a.1 - normal.1),
+            );
+        }
+
+        // Joins between segment i and the next one
+        let join_count = if closed {
+            segment_count
+        } else {
+            segment_count - 1
+        };
+        for i in 0..join_count {
+            let (a, corner) = segment(i);
+            let (_, c) = segment((i + 1) % segment_count);
+            self.join(a, corner, c, half, style.join);
+        }
+
+        if !closed && style.cap == LineCap::Round {
+            let (start, next) = segment(0);
+            let (previous, end) = segment(segment_count - 1);
+            self.round_cap(start, direction(next, start), half);
+            self.round_cap(end, direction(previous, end), half);
+        }
+    }
+
+    fn join(&mut self, a: Point, corner: Point, c: Point, half: f32, join: LineJoin) {
+        let incoming = direction(a, corner);
+        let outgoing = direction(corner, c);
+        let cross = incoming.0 * outgoing.1 - incoming.1 * outgoing.0;
+        if cross.abs() <= f32::EPSILON {
+            return;
+        }
+
+        // The gap to fill is on the outside of the turn
+        let side = if cross > 0.0 { -1.0 } else { 1.0 };
+        let normal_in = (-incoming.1 * half * side, incoming.0 * half * side);
+        let normal_out = (-outgoing.1 * half * side, outgoing.0 * half * side);
+        let outer_in = (corner.0 + normal_in.0, corner.1 + normal_in.1);
+        let outer_out = (corner.0 + normal_out.0, corner.1 + normal_out.1);
+
+        match join {
+            LineJoin::Round => {
+                let start = normal_in.1.atan2(normal_in.0);
+                let mut end = normal_out.1.atan2(normal_out.0);
+                // Go the short way around
+                if end - start > PI {
+                    end -= TAU;
+                } else if start - end > PI {
+                    end += TAU;
+                }
+                self.fan(corner, half, start, end);
+            }
+            LineJoin::Miter => {
+                self.triangle(corner, outer_in, outer_out);
+
+                // The miter tip is where the two outer edges meet
+                let bisector = (normal_in.0 + normal_out.0, normal_in.1 + normal_out.1);
+                let length = (bisector.0 * bisector.0 + bisector.1 * bisector.1).sqrt();
+                let cos_half_angle = length / (2.0 * half);
+                if cos_half_angle > f32::EPSILON && 1.0 / cos_half_angle <= MITER_LIMIT {
+                    let miter_length = half / cos_half_angle;
+                    let tip = (
+                        corner.0 + bisector.0 / length * miter_length,
+                        corner.1 + bisector.1 / length * miter_length,
+                    );
+                    self.triangle(outer_in, tip, outer_out);
+                }
+            }
+            LineJoin::Bevel => self.triangle(corner, outer_in, outer_out),
+        }
+    }
+
+    // Half circle sticking out of `point` in `outward` direction
+    fn round_cap(&mut self, point: Point, outward: Point, half: f32) {
+        let angle = outward.1.atan2(outward.0);
+        self.fan(point, half, angle - PI / 2.0, angle + PI / 2.0);
+    }
+}
+
+fn direction(a: Point, b: Point) -> Point {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length = (dx * dx + dy * dy).sqrt();
+    (dx / length, dy / length)
+}
+
+fn point_on_circle(center: Point, radius: f32, angle: f32) -> Point {
+    (
+        center.0 + radius * angle.cos(),
+        center.1 + radius * angle.sin(),
+    )
+}
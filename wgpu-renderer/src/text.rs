@@ -0,0 +1,241 @@
+//! Text sections made of spans that can each use their own font, size and color
+
+use std::hash::{Hash, Hasher};
+
+use glyph_brush::{
+    BuiltInLineBreaker, GlyphPositioner, Layout, SectionGeometry, SectionGlyph, ToSectionText,
+    ab_glyph::{Font, Rect},
+};
+
+/// Handle to a font added with `Renderer::add_font`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FontId(pub(crate) usize);
+
+impl FontId {
+    /// The embedded PressStart2P font
+    pub const DEFAULT: FontId = FontId(0);
+}
+
+/// A piece of text drawn with a single font, size and color
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextSpan {
+    pub text: String,
+    pub font: FontId,
+    pub size: f32,
+    pub color: [f32; 4],
+}
+
+impl TextSpan {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            font: FontId::DEFAULT,
+            size: 16.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+        }
+    }
+
+    pub fn with_font(mut self, font: FontId) -> Self {
+        self.font = font;
+        self
+    }
+
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+/// Where a section's text goes relative to its position horizontally
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum HorizontalAlign {
+    /// Text starts at the position
+    #[default]
+    Left,
+    Center,
+    /// Text ends at the position
+    Right,
+}
+
+/// Where a section's text goes relative to its position vertically
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum VerticalAlign {
+    /// The first line's top is at the position
+    #[default]
+    Top,
+    Center,
+    /// The last line's bottom is at the position
+    Bottom,
+}
+
+/// A block of text anchored at a screen position. Spans are laid out one after another, so a
+/// single section can mix fonts, sizes and colors.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextSection {
+    /// The anchor point, which part of the text sits on it depends on the alignment
+    pub position: (f32, f32),
+    pub h_align: HorizontalAlign,
+    pub v_align: VerticalAlign,
+    /// Words that would go past this width move to the next line. `None` only breaks on `\n`.
+    pub wrap_width: Option<f32>,
+    /// Multiplier for the distance between lines
+    pub line_spacing: f32,
+    pub spans: Vec<TextSpan>,
+}
+
+impl Default for TextSection {
+    fn default() -> Self {
+        Self {
+            position: (0.0, 0.0),
+            h_align: HorizontalAlign::default(),
+            v_align: VerticalAlign::default(),
+            wrap_width: None,
+            line_spacing: 1.0,
+            spans: Vec::new(),
+        }
+    }
+}
+
+impl TextSection {
+    pub fn new(position: (f32, f32)) -> Self {
+        Self {
+            position,
+            ..Self::default()
+        }
+    }
+
+    /// E.g. `(HorizontalAlign::Right, VerticalAlign::Top)` to hang text off the top right corner
+    pub fn with_align(mut self, h_align: HorizontalAlign, v_align: VerticalAlign) -> Self {
+        self.h_align = h_align;
+        self.v_align = v_align;
+        self
+    }
+
+    pub fn with_wrap(mut self, max_width: f32) -> Self {
+        self.wrap_width = Some(max_width);
+        self
+    }
+
+    pub fn with_line_spacing(mut self, line_spacing: f32) -> Self {
+        self.line_spacing = line_spacing;
+        self
+    }
+
+    pub fn with_span(mut self, span: TextSpan) -> Self {
+        self.spans.push(span);
+        self
+    }
+
+    /// Shorthand for a span in the default font
+    pub fn with_text(self, text: impl Into<String>, size: f32, color: [f32; 4]) -> Self {
+        self.with_span(TextSpan::new(text).with_size(size).with_color(color))
+    }
+
+    pub(crate) fn layout(&self) -> SpacedLayout {
+        let h_align = match self.h_align {
+            HorizontalAlign::Left => glyph_brush::HorizontalAlign::Left,
+            HorizontalAlign::Center => glyph_brush::HorizontalAlign::Center,
+            HorizontalAlign::Right => glyph_brush::HorizontalAlign::Right,
+        };
+        let v_align = match self.v_align {
+            VerticalAlign::Top => glyph_brush::VerticalAlign::Top,
+            VerticalAlign::Center => glyph_brush::VerticalAlign::Center,
+            VerticalAlign::Bottom => glyph_brush::VerticalAlign::Bottom,
+        };
+
+        SpacedLayout {
+            // Single line layouts stop at the first \n, so always wrap and leave the width
+            // unbounded when there's no wrap width
+            layout: Layout::default_wrap().h_align(h_align).v_align(v_align),
+            v_align: self.v_align,
+            line_spacing: self.line_spacing,
+        }
+    }
+
+    pub(crate) fn bounds(&self) -> (f32, f32) {
+        (self.wrap_width.unwrap_or(f32::INFINITY), f32::INFINITY)
+    }
+}
+
+/// Bounding box of laid out text, in screen pixels
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct TextBounds {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl From<Rect> for TextBounds {
+    fn from(rect: Rect) -> Self {
+        Self {
+            x: rect.min.x,
+            y: rect.min.y,
+            width: rect.width(),
+            height: rect.height(),
+        }
+    }
+}
+
+/// glyph_brush's layout with line spacing on top, which it doesn't support itself
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct SpacedLayout {
+    layout: Layout<BuiltInLineBreaker>,
+    v_align: VerticalAlign,
+    line_spacing: f32,
+}
+
+// Layouts are hashed for glyph_brush's caching, and f32 isn't Hash
+impl Hash for SpacedLayout {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.layout.hash(state);
+        self.line_spacing.to_bits().hash(state);
+    }
+}
+
+impl GlyphPositioner for SpacedLayout {
+    fn calculate_glyphs<F, S>(
+        &self,
+        fonts: &[F],
+        geometry: &SectionGeometry,
+        sections: &[S],
+    ) -> Vec<SectionGlyph>
+    where
+        F: Font,
+        S: ToSectionText,
+    {
+        let mut glyphs = self.layout.calculate_glyphs(fonts, geometry, sections);
+        if self.line_spacing == 1.0 || glyphs.is_empty() {
+            return glyphs;
+        }
+
+        // Every glyph on a line shares its baseline, so spreading the baselines out from the
+        // anchored line spaces the lines out
+        let (first, last) = glyphs
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(min, max), glyph| {
+                let y = glyph.glyph.position.y;
+                (min.min(y), max.max(y))
+            });
+        let pivot = match self.v_align {
+            VerticalAlign::Top => first,
+            VerticalAlign::Center => (first + last) / 2.0,
+            VerticalAlign::Bottom => last,
+        };
+
+        for glyph in &mut glyphs {
+            let y = &mut glyph.glyph.position.y;
+            *y = pivot + (*y - pivot) * self.line_spacing;
+        }
+        glyphs
+    }
+
+    fn bounds_rect(&self, geometry: &SectionGeometry) -> Rect {
+        self.layout.bounds_rect(geometry)
+    }
+}
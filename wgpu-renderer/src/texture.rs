@@ -0,0 +1,154 @@
+//! GPU texture storage. Every texture carries its own bind group so switching textures between
+//! batches is a single `set_bind_group` call.
+
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindingResource, Device,
+    Extent3d, Origin3d, Queue, Sampler, TexelCopyBufferLayout, TexelCopyTextureInfo, TextureAspect,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor,
+};
+
+pub(crate) struct GpuTexture {
+    pub(crate) texture: wgpu::Texture,
+    pub(crate) view: wgpu::TextureView,
+    pub(crate) bind_group: BindGroup,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+impl GpuTexture {
+    /// Create a texture from tightly packed RGBA8 pixels and upload them
+    pub(crate) fn new(
+        device: &Device,
+        queue: &Queue,
+        layout: &BindGroupLayout,
+        sampler: &Sampler,
+        width: u32,
+        height: u32,
+        rgba: &[u8],
+    ) -> Self {
+        let texture = device.create_texture(
+            &(TextureDescriptor {
+                label: Some("Texture"),
+                size: Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rgba8UnormSrgb,
+                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+                view_formats: &[],
+            }),
+        );
+
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        let bind_group = create_texture_bind_group(device, layout, &view, sampler);
+
+        let gpu_texture = Self {
+            texture,
+            view,
+            bind_group,
+            width,
+            height,
+        };
+        gpu_texture.write(queue, rgba);
+        gpu_texture
+    }
+
+    /// A texture that can be drawn into and then drawn with, like the post-process chain's frames
+    pub(crate) fn render_target(
+        device: &Device,
+        layout: &BindGroupLayout,
+        sampler: &Sampler,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+    ) -> Self {
+        let texture = device.create_texture(
+            &(TextureDescriptor {
+                label: Some("Render Target"),
+                size: Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format,
+                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            }),
+        );
+
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        let bind_group = create_texture_bind_group(device, layout, &view, sampler);
+
+        Self {
+            texture,
+            view,
+            bind_group,
+            width,
+            height,
+        }
+    }
+
+    /// Swap the sampler this texture is drawn with
+    pub(crate) fn set_sampler(
+        &mut self,
+        device: &Device,
+        layout: &BindGroupLayout,
+        sampler: &Sampler,
+    ) {
+        self.bind_group = create_texture_bind_group(device, layout, &self.view, sampler);
+    }
+
+    /// Overwrite the whole texture. `rgba` has to match the texture's size.
+    pub(crate) fn write(&self, queue: &Queue, rgba: &[u8]) {
+        queue.write_texture(
+            TexelCopyTextureInfo {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            rgba,
+            TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * self.width),
+                rows_per_image: Some(self.height),
+            },
+            Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+fn create_texture_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    view: &wgpu::TextureView,
+    sampler: &Sampler,
+) -> BindGroup {
+    device.create_bind_group(
+        &(BindGroupDescriptor {
+            label: Some("Texture BG"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(sampler),
+                },
+            ],
+        }),
+    )
+}
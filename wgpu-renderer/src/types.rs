@@ -7,6 +7,7 @@ pub const U32_SIZE: BufferAddress = std::mem::size_of::<u32>() as BufferAddress;
 pub struct Vertex {
     pub position: [f32; 2],
     pub color: [f32; 4], // RGBA
+    pub tex_coords: [f32; 2],
 }
 
 // Lets me convert vertices to raw bytes
@@ -18,6 +19,7 @@ impl Vertex {
         Self {
             position: [x, y],
             color: [1.0, 1.0, 1.0, 1.0],
+            tex_coords: [0.0, 0.0],
         }
     }
 
@@ -25,6 +27,15 @@ impl Vertex {
         Self {
             position: [x, y],
             color,
+            tex_coords: [0.0, 0.0],
+        }
+    }
+
+    pub fn with_tex_coords(x: f32, y: f32, u: f32, v: f32, color: [f32; 4]) -> Self {
+        Self {
+            position: [x, y],
+            color,
+            tex_coords: [u, v],
         }
     }
 
@@ -34,11 +45,34 @@ impl Vertex {
         step_mode: VertexStepMode::Vertex,
         attributes: &wgpu::vertex_attr_array![
             0 => Float32x2,
-            1 => Float32x4
+            1 => Float32x4,
+            2 => Float32x2
         ],
     };
 }
 
+/// Handle to a texture uploaded with `Renderer::create_texture`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TextureId(pub(crate) usize);
+
+impl TextureId {
+    /// 1x1 white texture that untextured shapes are drawn with
+    pub const WHITE: TextureId = TextureId(0);
+}
+
+/// How a texture is sampled when it's drawn bigger or smaller than its actual size
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TextureFilter {
+    /// Smooth, good for soft edged sprites
+    #[default]
+    Linear,
+    /// Blocky, good for pixel art
+    Nearest,
+}
+
+/// UV coordinates covering an entire texture (min u, min v, max u, max v)
+pub const FULL_UV: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
 pub const UNBOUNDED_F32: f32 = std::f32::INFINITY;
 
 #[derive(Debug)]